@@ -0,0 +1,53 @@
+/*
+ * C declarations for the gitbom crate's `capi` feature. Build the
+ * library with
+ *
+ *     cargo rustc --release --features capi --crate-type cdylib
+ *
+ * Keep in sync with src/ffi.rs and src/capi.rs.
+ */
+
+#ifndef GITBOM_H
+#define GITBOM_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define GITBOM_SHA1 1
+#define GITBOM_SHA256 2
+
+#define GITBOM_MAX_DIGEST_LEN 32
+
+typedef struct {
+    uint8_t hash_algorithm; /* GITBOM_SHA1 or GITBOM_SHA256 */
+    uint8_t object_type;    /* git's numbers: commit 1, tree 2, blob 3, tag 4 */
+    uint8_t digest_len;
+    uint8_t digest[GITBOM_MAX_DIGEST_LEN];
+} gitbom_gitoid;
+
+typedef enum {
+    GITBOM_OK = 0,
+    GITBOM_INVALID_INPUT = 1,
+    GITBOM_INVALID_DATA = 2,
+    GITBOM_NOT_FOUND = 3,
+    GITBOM_IO = 4,
+} gitbom_status;
+
+gitbom_status gitbom_gitoid_from_bytes(uint8_t hash_algorithm, const uint8_t *data,
+                                       size_t len, gitbom_gitoid *out);
+
+gitbom_status gitbom_gitoid_from_file(uint8_t hash_algorithm, const char *path,
+                                      gitbom_gitoid *out);
+
+/* out_len must be at least 65 for SHA256, 41 for SHA1 */
+gitbom_status gitbom_gitoid_hex(const gitbom_gitoid *gitoid, char *out, size_t out_len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* GITBOM_H */
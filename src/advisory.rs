@@ -0,0 +1,114 @@
+use crate::{GitBom, GitOid};
+
+/// A vulnerability advisory, e.g. an [OSV](https://osv.dev) record,
+/// reduced to the artifact gitoids it names as affected
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Advisory {
+    id: String,
+    artifacts: GitBom,
+}
+
+impl Advisory {
+    /// Create an advisory with the identifier `id` (e.g. `GHSA-...`)
+    /// affecting the given artifacts
+    pub fn new<S: ToString>(id: S, artifacts: GitBom) -> Self {
+        Advisory {
+            id: id.to_string(),
+            artifacts,
+        }
+    }
+
+    /// The advisory's identifier
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The affected artifacts
+    pub fn artifacts(&self) -> &GitBom {
+        &self.artifacts
+    }
+}
+
+/// A BOM root affected by an advisory
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AdvisoryMatch {
+    advisory: String,
+    root: GitOid,
+    artifacts: GitBom,
+}
+
+impl AdvisoryMatch {
+    /// The identifier of the matching advisory
+    pub fn advisory(&self) -> &str {
+        &self.advisory
+    }
+
+    /// The BOM root that's affected
+    pub fn root(&self) -> GitOid {
+        self.root
+    }
+
+    /// The affected artifacts found in the root's BOM
+    pub fn artifacts(&self) -> &GitBom {
+        &self.artifacts
+    }
+}
+
+/// Find which BOM roots are affected by which advisories: the core query
+/// for incident response. Each root is given with the `GitBom` of the
+/// artifacts built into it, and a root matches an advisory if any of
+/// them is named in it.
+///
+/// The matches are sorted by advisory identifier and then root
+pub fn match_advisories(advisories: &[Advisory], roots: &[(GitOid, GitBom)]) -> Vec<AdvisoryMatch> {
+    let mut ret = Vec::new();
+    for advisory in advisories {
+        for (root, gitbom) in roots {
+            let artifacts = gitbom.intersection(&advisory.artifacts);
+            if !artifacts.is_empty() {
+                ret.push(AdvisoryMatch {
+                    advisory: advisory.id.clone(),
+                    root: *root,
+                    artifacts,
+                });
+            }
+        }
+    }
+    ret.sort_by(|a, b| (&a.advisory, a.root).cmp(&(&b.advisory, b.root)));
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make(names: &[&str]) -> GitBom {
+        names
+            .iter()
+            .map(|name| GitOid::new_from_str(name))
+            .collect()
+    }
+
+    #[test]
+    fn test_match_advisories() {
+        let app = GitOid::new_from_str("app");
+        let tool = GitOid::new_from_str("tool");
+        let roots = vec![
+            (app, make(&["openssl.c", "main.c"])),
+            (tool, make(&["zlib.c", "tool.c"])),
+        ];
+        let advisories = vec![
+            Advisory::new("OSV-2", make(&["zlib.c", "openssl.c"])),
+            Advisory::new("OSV-1", make(&["openssl.c"])),
+            Advisory::new("OSV-3", make(&["unused.c"])),
+        ];
+
+        let matches = match_advisories(&advisories, &roots);
+
+        let found: Vec<(&str, GitOid)> = matches.iter().map(|m| (m.advisory(), m.root())).collect();
+        let mut expected = vec![("OSV-1", app), ("OSV-2", app), ("OSV-2", tool)];
+        expected.sort();
+        assert_eq!(expected, found);
+        assert_eq!(&make(&["openssl.c"]), matches[0].artifacts());
+    }
+}
@@ -0,0 +1,219 @@
+use crate::document::{bad_document, parse_gitoid};
+use crate::{GitOid, HashAlgorithm};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Error, ErrorKind, Result as IOResult};
+
+/// Names people recognize (`openssl-3.0.13.tar.gz`, ...) for gitoids,
+/// with lookup in both directions.
+///
+/// A name maps to one gitoid, but a gitoid can have any number of
+/// names. Like `Annotations`, the table lives in a sidecar so documents
+/// stay content-addressed:
+///
+/// ```text
+/// gitoid:aliases:sha256
+/// blob <hex> <name>
+/// ```
+///
+/// with one line per name, sorted by gitoid and then name
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AliasTable {
+    hash_algo: HashAlgorithm,
+    by_name: BTreeMap<String, GitOid>,
+    by_gitoid: BTreeMap<GitOid, BTreeSet<String>>,
+}
+
+impl AliasTable {
+    /// An empty table for gitoids using `hash_algo`
+    pub fn new(hash_algo: HashAlgorithm) -> Self {
+        AliasTable {
+            hash_algo,
+            by_name: BTreeMap::new(),
+            by_gitoid: BTreeMap::new(),
+        }
+    }
+
+    /// The hash algorithm of the gitoids in the table
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algo
+    }
+
+    /// Name `gitoid` `name`. Inserting a name twice for the same gitoid
+    /// is a no-op. Will return an `Err` if `gitoid` uses a different hash
+    /// algorithm from the table, the name is empty, starts or ends with
+    /// whitespace or contains a line break, or already names another
+    /// gitoid (`remove` it first)
+    pub fn insert<S: ToString>(&mut self, gitoid: GitOid, name: S) -> IOResult<()> {
+        if gitoid.hash_algorithm() != self.hash_algo {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Can't alias {} in a {} table", gitoid, self.hash_algo),
+            ));
+        }
+        let name = name.to_string();
+        if name.is_empty() || name.trim() != name || name.contains(['\n', '\r']) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Bad alias {:?}", name),
+            ));
+        }
+        match self.by_name.get(&name) {
+            Some(existing) if *existing == gitoid => return Ok(()),
+            Some(existing) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("Alias {:?} already names {}", name, existing),
+                ))
+            }
+            None => {}
+        }
+        self.by_gitoid
+            .entry(gitoid)
+            .or_default()
+            .insert(name.clone());
+        self.by_name.insert(name, gitoid);
+        Ok(())
+    }
+
+    /// Drop `name`, returning the gitoid it named
+    pub fn remove(&mut self, name: &str) -> Option<GitOid> {
+        let gitoid = self.by_name.remove(name)?;
+        if let Some(names) = self.by_gitoid.get_mut(&gitoid) {
+            names.remove(name);
+            if names.is_empty() {
+                self.by_gitoid.remove(&gitoid);
+            }
+        }
+        Some(gitoid)
+    }
+
+    /// The gitoid `name` names, if any
+    pub fn gitoid(&self, name: &str) -> Option<GitOid> {
+        self.by_name.get(name).copied()
+    }
+
+    /// All of `gitoid`'s names, sorted
+    pub fn names(&self, gitoid: &GitOid) -> Vec<&str> {
+        self.by_gitoid
+            .get(gitoid)
+            .map(|names| names.iter().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    /// Every gitoid with at least one name, with its names, sorted by
+    /// gitoid
+    pub fn iter(&self) -> impl Iterator<Item = (GitOid, Vec<&str>)> {
+        self.by_gitoid
+            .iter()
+            .map(|(gitoid, names)| (*gitoid, names.iter().map(String::as_str).collect()))
+    }
+
+    /// The number of names in the table
+    pub fn len(&self) -> usize {
+        self.by_name.len()
+    }
+
+    /// Does the table have no names?
+    pub fn is_empty(&self) -> bool {
+        self.by_name.is_empty()
+    }
+
+    /// Serialize the sidecar
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut ret = format!("gitoid:aliases:{}\n", self.hash_algo.document_name());
+        for (gitoid, names) in &self.by_gitoid {
+            for name in names {
+                ret.push_str(&format!(
+                    "{} {} {}\n",
+                    gitoid.object_type(),
+                    gitoid.hex_hash(),
+                    name
+                ));
+            }
+        }
+        ret.into_bytes()
+    }
+
+    /// Parse a sidecar written by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> IOResult<Self> {
+        let text = std::str::from_utf8(bytes).map_err(|_| bad_document("not UTF-8"))?;
+        let mut lines = text.lines();
+
+        let hash_algo = lines
+            .next()
+            .and_then(|header| header.strip_prefix("gitoid:aliases:"))
+            .and_then(HashAlgorithm::from_document_name)
+            .ok_or_else(|| bad_document("missing gitoid:aliases:<algorithm> header"))?;
+
+        let mut ret = AliasTable::new(hash_algo);
+        for line in lines {
+            let bad_line = || bad_document(&format!("bad alias line {:?}", line));
+            let mut parts = line.splitn(3, ' ');
+            let (object_type, hash, name) = match (parts.next(), parts.next(), parts.next()) {
+                (Some(object_type), Some(hash), Some(name)) => (object_type, hash, name),
+                _ => return Err(bad_line()),
+            };
+            let gitoid = parse_gitoid(hash_algo, &format!("{} {}", object_type, hash))?;
+            ret.insert(gitoid, name).map_err(|_| bad_line())?;
+        }
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aliases_round_trip() {
+        let openssl = GitOid::new(HashAlgorithm::SHA256, b"openssl");
+        let zlib = GitOid::new(HashAlgorithm::SHA256, b"zlib");
+
+        let mut aliases = AliasTable::new(HashAlgorithm::SHA256);
+        aliases.insert(openssl, "openssl-3.0.13.tar.gz").unwrap();
+        aliases.insert(openssl, "openssl latest").unwrap();
+        aliases.insert(zlib, "zlib-1.3.1.tar.gz").unwrap();
+        aliases.insert(zlib, "zlib-1.3.1.tar.gz").unwrap();
+        assert_eq!(3, aliases.len());
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            aliases
+                .insert(zlib, "openssl-3.0.13.tar.gz")
+                .unwrap_err()
+                .kind()
+        );
+        assert!(aliases.insert(zlib, " padded").is_err());
+        assert!(aliases.insert(zlib, "two\nlines").is_err());
+        #[cfg(feature = "sha1")]
+        assert!(aliases
+            .insert(GitOid::new(HashAlgorithm::SHA1, b"zlib"), "zlib")
+            .is_err());
+
+        assert_eq!(Some(openssl), aliases.gitoid("openssl-3.0.13.tar.gz"));
+        assert_eq!(
+            vec!["openssl latest", "openssl-3.0.13.tar.gz"],
+            aliases.names(&openssl)
+        );
+        assert!(aliases
+            .names(&GitOid::new(HashAlgorithm::SHA256, b""))
+            .is_empty());
+
+        let parsed = AliasTable::from_bytes(&aliases.to_bytes()).unwrap();
+        assert_eq!(aliases, parsed);
+
+        assert_eq!(Some(zlib), aliases.remove("zlib-1.3.1.tar.gz"));
+        assert_eq!(None, aliases.gitoid("zlib-1.3.1.tar.gz"));
+        assert_eq!(1, aliases.iter().count());
+        assert!(aliases.insert(zlib, "openssl latest").is_err());
+    }
+
+    #[test]
+    fn test_bad_alias_sidecars() {
+        assert!(AliasTable::from_bytes(b"").is_err());
+        assert!(AliasTable::from_bytes(b"gitoid:annotations:sha256\n").is_err());
+        assert!(AliasTable::from_bytes(b"gitoid:aliases:sha256\nblob 00\n").is_err());
+        assert!(AliasTable::from_bytes(b"gitoid:aliases:sha256\n")
+            .unwrap()
+            .is_empty());
+    }
+}
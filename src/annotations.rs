@@ -0,0 +1,196 @@
+use crate::document::{bad_document, parse_gitoid};
+use crate::{GitBom, GitOid, HashAlgorithm};
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind, Result as IOResult};
+
+/// Key-value attributes (build target, license hint, ...) attached to
+/// the entries of a GitBOM document.
+///
+/// Annotations live in a sidecar next to the document, never in it, so
+/// the canonical document bytes and gitoid stay as the spec defines
+/// them. The sidecar names the gitoid of the document it annotates:
+///
+/// ```text
+/// gitoid:annotations:sha256
+/// document <hex>
+/// blob <hex> <key>=<value>
+/// ```
+///
+/// with one line per annotation, grouped by gitoid and sorted by key
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Annotations {
+    document: GitOid,
+    entries: BTreeMap<GitOid, BTreeMap<String, String>>,
+}
+
+impl Annotations {
+    /// Start annotating the document whose gitoid is `document`, e.g.
+    /// from `GitBom::document_gitoid`
+    pub fn new(document: GitOid) -> Self {
+        Annotations {
+            document,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// The gitoid of the annotated document
+    pub fn document(&self) -> GitOid {
+        self.document
+    }
+
+    /// Does this sidecar annotate `gitbom`'s document?
+    pub fn applies_to(&self, gitbom: &GitBom) -> IOResult<bool> {
+        Ok(gitbom.document_gitoid(self.document.hash_algorithm())? == self.document)
+    }
+
+    /// Set `key` to `value` on `gitoid`, replacing any previous value.
+    /// Will return an `Err` if `gitoid` uses a different hash algorithm
+    /// from the document, the key is empty or contains whitespace or `=`,
+    /// or the value contains a line break
+    pub fn insert<K: ToString, V: ToString>(
+        &mut self,
+        gitoid: GitOid,
+        key: K,
+        value: V,
+    ) -> IOResult<()> {
+        if gitoid.hash_algorithm() != self.document.hash_algorithm() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Can't annotate {} in a {} sidecar",
+                    gitoid,
+                    self.document.hash_algorithm()
+                ),
+            ));
+        }
+        let (key, value) = (key.to_string(), value.to_string());
+        if key.is_empty() || key.contains(|c: char| c.is_whitespace() || c == '=') {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Bad annotation key {:?}", key),
+            ));
+        }
+        if value.contains(['\n', '\r']) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Annotation value for {} has a line break", key),
+            ));
+        }
+        self.entries.entry(gitoid).or_default().insert(key, value);
+        Ok(())
+    }
+
+    /// The value of `key` on `gitoid`, if set
+    pub fn get(&self, gitoid: &GitOid, key: &str) -> Option<&str> {
+        self.entries.get(gitoid)?.get(key).map(String::as_str)
+    }
+
+    /// All of `gitoid`'s annotations, sorted by key
+    pub fn get_all(&self, gitoid: &GitOid) -> Vec<(&str, &str)> {
+        self.entries
+            .get(gitoid)
+            .map(|keys| {
+                keys.iter()
+                    .map(|(key, value)| (key.as_str(), value.as_str()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Serialize the sidecar
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut ret = format!(
+            "gitoid:annotations:{}\ndocument {}\n",
+            self.document.hash_algorithm().document_name(),
+            self.document.hex_hash()
+        );
+        for (gitoid, keys) in &self.entries {
+            for (key, value) in keys {
+                ret.push_str(&format!(
+                    "{} {} {}={}\n",
+                    gitoid.object_type(),
+                    gitoid.hex_hash(),
+                    key,
+                    value
+                ));
+            }
+        }
+        ret.into_bytes()
+    }
+
+    /// Parse a sidecar written by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> IOResult<Self> {
+        let text = std::str::from_utf8(bytes).map_err(|_| bad_document("not UTF-8"))?;
+        let mut lines = text.lines();
+
+        let hash_algo = lines
+            .next()
+            .and_then(|header| header.strip_prefix("gitoid:annotations:"))
+            .and_then(HashAlgorithm::from_document_name)
+            .ok_or_else(|| bad_document("missing gitoid:annotations:<algorithm> header"))?;
+        let document = lines
+            .next()
+            .and_then(|line| line.strip_prefix("document "))
+            .ok_or_else(|| bad_document("missing document line"))?;
+
+        let mut ret = Annotations::new(parse_gitoid(hash_algo, &format!("blob {}", document))?);
+        for line in lines {
+            let bad_line = || bad_document(&format!("bad annotation line {:?}", line));
+            let mut parts = line.splitn(3, ' ');
+            let (object_type, hash, annotation) = match (parts.next(), parts.next(), parts.next()) {
+                (Some(object_type), Some(hash), Some(annotation)) => {
+                    (object_type, hash, annotation)
+                }
+                _ => return Err(bad_line()),
+            };
+            let gitoid = parse_gitoid(hash_algo, &format!("{} {}", object_type, hash))?;
+            let (key, value) = annotation.split_once('=').ok_or_else(bad_line)?;
+            ret.insert(gitoid, key, value).map_err(|_| bad_line())?;
+        }
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotations_round_trip() {
+        let hello = GitOid::new(HashAlgorithm::SHA256, b"hello world");
+        let gitbom = GitBom::new().add(hello);
+        let document = gitbom.document_gitoid(HashAlgorithm::SHA256).unwrap();
+
+        let mut annotations = Annotations::new(document);
+        annotations.insert(hello, "target", "x86_64-linux").unwrap();
+        annotations
+            .insert(hello, "license", "MIT OR Apache-2.0")
+            .unwrap();
+        assert!(annotations.insert(hello, "bad key", "x").is_err());
+        #[cfg(feature = "sha1")]
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            annotations
+                .insert(GitOid::new(HashAlgorithm::SHA1, b"hello world"), "a", "b")
+                .unwrap_err()
+                .kind()
+        );
+
+        let bytes = annotations.to_bytes();
+        assert_eq!(
+            format!(
+                "gitoid:annotations:sha256\ndocument {}\nblob {} license=MIT OR Apache-2.0\nblob {} target=x86_64-linux\n",
+                document.hex_hash(),
+                hello.hex_hash(),
+                hello.hex_hash()
+            ),
+            String::from_utf8(bytes.clone()).unwrap()
+        );
+
+        let parsed = Annotations::from_bytes(&bytes).unwrap();
+        assert_eq!(annotations, parsed);
+        assert_eq!(Some("x86_64-linux"), parsed.get(&hello, "target"));
+        assert!(parsed.applies_to(&gitbom).unwrap());
+        assert!(!parsed.applies_to(&GitBom::new()).unwrap());
+    }
+}
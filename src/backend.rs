@@ -0,0 +1,88 @@
+use crate::HashAlgorithm;
+use std::fmt::{Display, Formatter, Result};
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+cpufeatures::new!(shani_cpuid, "sha", "sse2", "ssse3", "sse4.1");
+
+/// The implementation of the compression function that will be used
+/// to compute a hash on the current CPU.
+///
+/// The `sha1` and `sha2` crates do runtime CPU feature detection on their
+/// own and pick an accelerated implementation when one is available. This
+/// reports the same decision so users can confirm which one ran.
+#[derive(Clone, Copy, PartialOrd, Eq, Ord, Debug, Hash, PartialEq)]
+pub enum Backend {
+    /// The portable, pure-Rust implementation
+    Software,
+    /// The [Intel SHA extensions](https://en.wikipedia.org/wiki/Intel_SHA_extensions)
+    /// (SHA-NI) on x86 and x86_64
+    X86ShaNi,
+}
+
+impl Display for Backend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Backend::Software => write!(f, "software"),
+            Backend::X86ShaNi => write!(f, "x86-sha-ni"),
+        }
+    }
+}
+
+impl Backend {
+    /// Detect the backend used for the given algorithm on this CPU.
+    ///
+    /// On ARM the digest crates only use the SHA2 extensions when built with
+    /// their `asm` feature, which this crate does not enable, so ARM
+    /// always reports `Software`.
+    pub fn detect(hash_algo: HashAlgorithm) -> Backend {
+        match hash_algo {
+            #[cfg(feature = "sha1")]
+            HashAlgorithm::SHA1 => Backend::detect_sha_ni(),
+            HashAlgorithm::SHA256 => Backend::detect_sha_ni(),
+        }
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn detect_sha_ni() -> Backend {
+        if shani_cpuid::get() {
+            Backend::X86ShaNi
+        } else {
+            Backend::Software
+        }
+    }
+
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+    fn detect_sha_ni() -> Backend {
+        Backend::Software
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_is_stable() {
+        // detection is cached, asking twice must give the same answer
+        assert_eq!(
+            Backend::detect(HashAlgorithm::SHA256),
+            Backend::detect(HashAlgorithm::SHA256)
+        );
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_sha1_and_sha256_agree() {
+        // both algorithms use the same CPU extension on x86
+        assert_eq!(
+            Backend::detect(HashAlgorithm::SHA1),
+            Backend::detect(HashAlgorithm::SHA256)
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!("software", Backend::Software.to_string());
+        assert_eq!("x86-sha-ni", Backend::X86ShaNi.to_string());
+    }
+}
@@ -0,0 +1,182 @@
+use crate::{GitBom, GitOid};
+use std::io::{Error, ErrorKind, Read, Result as IOResult, Write};
+
+/// magic bytes and version for the serialized filter
+const BLOOM_MAGIC: &[u8; 4] = b"GBBF";
+const BLOOM_VERSION: u8 = 1;
+
+/// A [Bloom filter](https://en.wikipedia.org/wiki/Bloom_filter) of gitoids,
+/// for distributing "is this artifact known?" checks to edge scanners
+/// without shipping the full list of hashes.
+///
+/// `contains` never misses a gitoid that was inserted, but may say yes for
+/// one that wasn't, at about the false positive rate the set was sized for.
+///
+/// The digests are already uniformly distributed, so the bit positions are
+/// derived from the digest bytes directly with double hashing
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BloomSet {
+    bits: Vec<u8>,
+    num_bits: u64,
+    num_hashes: u8,
+}
+
+impl BloomSet {
+    /// Create an empty filter sized for `expected_items` with the given
+    /// false positive rate, e.g. `0.01` for 1%
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        let n = expected_items.max(1) as f64;
+        let p = false_positive_rate.clamp(f64::MIN_POSITIVE, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+
+        let num_bits = ((-n * p.ln()) / (ln2 * ln2)).ceil().max(8.0) as u64;
+        let num_hashes = ((num_bits as f64 / n) * ln2).round().clamp(1.0, 32.0) as u8;
+
+        BloomSet {
+            bits: vec![0u8; num_bits.div_ceil(8) as usize],
+            num_bits,
+            num_hashes,
+        }
+    }
+
+    /// Build a filter holding every gitoid in `gitbom`
+    pub fn from_gitbom(gitbom: &GitBom, false_positive_rate: f64) -> Self {
+        let mut ret = BloomSet::new(gitbom.len(), false_positive_rate);
+        for gitoid in gitbom.get_oids() {
+            ret.insert(&gitoid);
+        }
+        ret
+    }
+
+    /// Add a gitoid
+    pub fn insert(&mut self, gitoid: &GitOid) {
+        for bit in self.bit_positions(gitoid) {
+            self.bits[(bit / 8) as usize] |= 1 << (bit % 8);
+        }
+    }
+
+    /// Might the gitoid have been inserted?
+    pub fn contains(&self, gitoid: &GitOid) -> bool {
+        self.bit_positions(gitoid)
+            .all(|bit| self.bits[(bit / 8) as usize] & (1 << (bit % 8)) != 0)
+    }
+
+    /// The size of the filter in bits
+    pub fn num_bits(&self) -> u64 {
+        self.num_bits
+    }
+
+    /// The number of bits set per gitoid
+    pub fn num_hashes(&self) -> u8 {
+        self.num_hashes
+    }
+
+    /// Write the filter: a 16 byte header (magic, version, number of
+    /// hashes, number of bits) followed by the bits
+    pub fn write<W: Write>(&self, mut out: W) -> IOResult<()> {
+        out.write_all(BLOOM_MAGIC)?;
+        out.write_all(&[BLOOM_VERSION, self.num_hashes, 0, 0])?;
+        out.write_all(&self.num_bits.to_be_bytes())?;
+        out.write_all(&self.bits)?;
+        out.flush()
+    }
+
+    /// Read a filter written by `write`
+    pub fn read<R: Read>(mut input: R) -> IOResult<Self> {
+        let mut header = [0u8; 16];
+        input.read_exact(&mut header)?;
+        if &header[..4] != BLOOM_MAGIC || header[4] != BLOOM_VERSION {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Not a version 1 Bloom filter",
+            ));
+        }
+
+        let num_hashes = header[5];
+        let num_bits = u64::from_be_bytes(header[8..].try_into().unwrap());
+        if num_hashes == 0 || num_bits == 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "Empty Bloom filter"));
+        }
+
+        let mut bits = Vec::new();
+        input.read_to_end(&mut bits)?;
+        if bits.len() as u64 != num_bits.div_ceil(8) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Bloom filter size doesn't match its header",
+            ));
+        }
+
+        Ok(BloomSet {
+            bits,
+            num_bits,
+            num_hashes,
+        })
+    }
+
+    fn bit_positions(&self, gitoid: &GitOid) -> impl Iterator<Item = u64> {
+        let digest = gitoid.hash_value();
+        let h1 = u64::from_le_bytes(digest[..8].try_into().unwrap());
+        // odd, so the probes don't cycle early when num_bits is even
+        let h2 = u64::from_le_bytes(digest[8..16].try_into().unwrap()) | 1;
+        let num_bits = self.num_bits;
+
+        (0..self.num_hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gitbom(range: std::ops::Range<usize>) -> GitBom {
+        range
+            .map(|i| GitOid::new_from_str(&i.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_no_false_negatives() {
+        let known = gitbom(0..1000);
+        let filter = BloomSet::from_gitbom(&known, 0.01);
+
+        for gitoid in known.get_oids() {
+            assert!(filter.contains(&gitoid));
+        }
+    }
+
+    #[test]
+    fn test_false_positive_rate() {
+        let filter = BloomSet::from_gitbom(&gitbom(0..1000), 0.01);
+
+        let false_positives = gitbom(1000..11000)
+            .get_oids()
+            .iter()
+            .filter(|gitoid| filter.contains(gitoid))
+            .count();
+
+        // about 100 are expected, allow plenty of slack
+        assert!(false_positives < 250, "{} false positives", false_positives);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let filter = BloomSet::from_gitbom(&gitbom(0..100), 0.001);
+
+        let mut out = Vec::new();
+        filter.write(&mut out).unwrap();
+        let read = BloomSet::read(&out[..]).unwrap();
+
+        assert_eq!(filter, read);
+        assert!(read.contains(&GitOid::new_from_str("42")));
+    }
+
+    #[test]
+    fn test_rejects_truncated() {
+        let mut out = Vec::new();
+        BloomSet::new(10, 0.01).write(&mut out).unwrap();
+        out.pop();
+
+        assert!(BloomSet::read(&out[..]).is_err());
+    }
+}
@@ -0,0 +1,42 @@
+use crate::metrics::finalize_content;
+use crate::{GitOid, GitOidHasher, HashAlgorithm};
+use bytes::Buf;
+
+impl GitOidHasher {
+    /// Hash all the remaining bytes of `buf`, chunk by chunk, without
+    /// copying them into a contiguous buffer
+    pub fn update_buf<B: Buf>(&mut self, mut buf: B) {
+        while buf.has_remaining() {
+            let chunk = buf.chunk();
+            let len = chunk.len();
+            self.update(chunk);
+            buf.advance(len);
+        }
+    }
+}
+
+impl GitOid {
+    /// Compute the blob GitOid of the remaining bytes of `buf`, e.g. a
+    /// `Bytes` or a chain of them, without copying it into a `Vec`
+    pub fn new_from_buf<B: Buf>(hash_algo: HashAlgorithm, buf: B) -> Self {
+        let mut hasher = GitOidHasher::new(hash_algo, buf.remaining());
+        hasher.update_buf(buf);
+        finalize_content(hasher).unwrap() // the length is always the expected length
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    #[test]
+    fn test_chained_bufs() {
+        let buf = Bytes::from_static(b"hello ").chain(Bytes::from_static(b"world"));
+
+        assert_eq!(
+            GitOid::new(HashAlgorithm::SHA256, b"hello world"),
+            GitOid::new_from_buf(HashAlgorithm::SHA256, buf)
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! C functions over the `ffi` types, behind the `capi` feature.
+//!
+//! Build a shared library with
+//! `cargo rustc --release --features capi --crate-type cdylib` and use the
+//! declarations in `include/gitbom.h`. Every function returns an
+//! `ffi::Status` and writes its result through an out pointer.
+
+use crate::ffi::{GitOid as FfiGitOid, Status};
+use crate::{GitOid, HashAlgorithm};
+use std::ffi::CStr;
+use std::fs::File;
+use std::io::BufReader;
+use std::os::raw::c_char;
+
+/// Compute the blob gitoid of `len` bytes at `data`.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes (or may be null if `len` is
+/// 0) and `out` must point to a writable `GitOid`
+#[no_mangle]
+pub unsafe extern "C" fn gitbom_gitoid_from_bytes(
+    hash_algorithm: u8,
+    data: *const u8,
+    len: usize,
+    out: *mut FfiGitOid,
+) -> Status {
+    let hash_algo = match HashAlgorithm::from_tag(hash_algorithm) {
+        Some(hash_algo) => hash_algo,
+        None => return Status::InvalidInput,
+    };
+    if out.is_null() || (data.is_null() && len > 0) {
+        return Status::InvalidInput;
+    }
+
+    let content = if len == 0 {
+        &[][..]
+    } else {
+        std::slice::from_raw_parts(data, len)
+    };
+    *out = FfiGitOid::from(&GitOid::new(hash_algo, content));
+    Status::Ok
+}
+
+/// Compute the blob gitoid of the file at the NUL-terminated UTF-8 `path`.
+///
+/// # Safety
+///
+/// `path` must be a valid NUL-terminated string and `out` must point to a
+/// writable `GitOid`
+#[no_mangle]
+pub unsafe extern "C" fn gitbom_gitoid_from_file(
+    hash_algorithm: u8,
+    path: *const c_char,
+    out: *mut FfiGitOid,
+) -> Status {
+    let hash_algo = match HashAlgorithm::from_tag(hash_algorithm) {
+        Some(hash_algo) => hash_algo,
+        None => return Status::InvalidInput,
+    };
+    if out.is_null() || path.is_null() {
+        return Status::InvalidInput;
+    }
+    let path = match CStr::from_ptr(path).to_str() {
+        Ok(path) => path,
+        Err(_) => return Status::InvalidInput,
+    };
+
+    let result = File::open(path).and_then(|file| {
+        let len = file.metadata()?.len() as usize;
+        GitOid::new_from_reader(hash_algo, BufReader::new(file), len)
+    });
+    match result {
+        Ok(gitoid) => {
+            *out = FfiGitOid::from(&gitoid);
+            Status::Ok
+        }
+        Err(e) => Status::from(&e),
+    }
+}
+
+/// Write the hex hash of `gitoid` to `out` as a NUL-terminated string.
+/// `out_len` must be at least 65 for a SHA256 gitoid, 41 for SHA1.
+///
+/// # Safety
+///
+/// `gitoid` must point to a `GitOid` and `out` to `out_len` writable bytes
+#[no_mangle]
+pub unsafe extern "C" fn gitbom_gitoid_hex(
+    gitoid: *const FfiGitOid,
+    out: *mut c_char,
+    out_len: usize,
+) -> Status {
+    if gitoid.is_null() || out.is_null() {
+        return Status::InvalidInput;
+    }
+    let hex = match GitOid::try_from(&*gitoid) {
+        Ok(gitoid) => gitoid.hex_hash(),
+        Err(e) => return Status::from(&e),
+    };
+    if out_len <= hex.len() {
+        return Status::InvalidInput;
+    }
+
+    std::ptr::copy_nonoverlapping(hex.as_ptr() as *const c_char, out, hex.len());
+    *out.add(hex.len()) = 0;
+    Status::Ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+    use std::mem::MaybeUninit;
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_from_bytes_and_hex() {
+        let content = b"hello world";
+        let mut gitoid = MaybeUninit::<FfiGitOid>::uninit();
+        let mut hex = [0 as c_char; 65];
+
+        unsafe {
+            assert_eq!(
+                Status::Ok,
+                gitbom_gitoid_from_bytes(1, content.as_ptr(), content.len(), gitoid.as_mut_ptr())
+            );
+            assert_eq!(
+                Status::Ok,
+                gitbom_gitoid_hex(gitoid.as_ptr(), hex.as_mut_ptr(), hex.len())
+            );
+            assert_eq!(
+                "95d09f2b10159347eece71399a7e2e907ea3df4f",
+                CStr::from_ptr(hex.as_ptr()).to_str().unwrap()
+            );
+            assert_eq!(
+                Status::InvalidInput,
+                gitbom_gitoid_hex(gitoid.as_ptr(), hex.as_mut_ptr(), 40)
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_file() {
+        let path = CString::new("test/data/hello_world.txt").unwrap();
+        let missing = CString::new("test/data/missing.txt").unwrap();
+        let mut gitoid = MaybeUninit::<FfiGitOid>::uninit();
+
+        unsafe {
+            assert_eq!(
+                Status::Ok,
+                gitbom_gitoid_from_file(2, path.as_ptr(), gitoid.as_mut_ptr())
+            );
+            assert_eq!(
+                GitOid::new(HashAlgorithm::SHA256, b"hello world"),
+                GitOid::try_from(&gitoid.assume_init()).unwrap()
+            );
+            assert_eq!(
+                Status::NotFound,
+                gitbom_gitoid_from_file(2, missing.as_ptr(), gitoid.as_mut_ptr())
+            );
+            assert_eq!(
+                Status::InvalidInput,
+                gitbom_gitoid_from_file(7, path.as_ptr(), gitoid.as_mut_ptr())
+            );
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::fmt::{Display, Formatter, Result};
+use std::fs::{self, Metadata};
+use std::io::{Error, ErrorKind, Result as IOResult};
+use std::path::{Path, PathBuf};
+
+/// A file's size or modification time changed while it was being hashed,
+/// so its gitoid may not match any version of the file.
+///
+/// This is the inner error of an `io::Error` of kind `InvalidData`; use
+/// `ChangedDuringRead::from_io_error` to tell it apart
+#[derive(Debug)]
+pub struct ChangedDuringRead {
+    path: PathBuf,
+}
+
+impl ChangedDuringRead {
+    /// The file that changed
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The `ChangedDuringRead` inside `error`, if that's what it is
+    pub fn from_io_error(error: &Error) -> Option<&ChangedDuringRead> {
+        error.get_ref()?.downcast_ref()
+    }
+}
+
+impl Display for ChangedDuringRead {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{} changed while it was hashed", self.path.display())
+    }
+}
+
+impl std::error::Error for ChangedDuringRead {}
+
+/// Stat `path` again and return a `ChangedDuringRead` error if its size
+/// or modification time differ from `before`
+pub(crate) fn check_unchanged(path: &Path, before: &Metadata) -> IOResult<()> {
+    let after = fs::metadata(path)?;
+    if after.len() != before.len() || after.modified().ok() != before.modified().ok() {
+        return Err(changed_during_read(path));
+    }
+    Ok(())
+}
+
+/// a `ChangedDuringRead` error for `path`, e.g. for a file that ended
+/// before the length it had when hashing started
+pub(crate) fn changed_during_read(path: &Path) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        ChangedDuringRead {
+            path: path.to_path_buf(),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+
+    #[test]
+    fn test_check_unchanged() {
+        let dir = TempDir::new("changed");
+        let path = dir.join("file.txt");
+        fs::write(&path, "before").unwrap();
+        let before = fs::metadata(&path).unwrap();
+        let unchanged = check_unchanged(&path, &before);
+        fs::write(&path, "and after").unwrap();
+        let changed = check_unchanged(&path, &before);
+
+        assert!(unchanged.is_ok());
+        let error = changed.unwrap_err();
+        assert_eq!(ErrorKind::InvalidData, error.kind());
+        assert_eq!(
+            path,
+            ChangedDuringRead::from_io_error(&error).unwrap().path()
+        );
+    }
+}
@@ -0,0 +1,293 @@
+use crate::document::bad_document;
+use crate::scan::{hash_file_digest, path_order};
+use crate::{DirectoryScan, GitBom, GitOid, HashAlgorithm, ScanError, ScanEvent, ScanOptions};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::io::Result as IOResult;
+use std::path::{Path, PathBuf};
+
+/// A file's entry in a checksum file: its path and the plain digest of its
+/// content, which is *not* a gitoid since it has no git object prefix,
+/// and the file's gitoid if it's known
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Checksum {
+    path: PathBuf,
+    digest: Vec<u8>,
+    gitoid: Option<GitOid>,
+}
+
+impl Checksum {
+    /// The path, relative to the directory the checksums are for
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The plain digest of the content
+    pub fn digest(&self) -> &[u8] {
+        &self.digest
+    }
+
+    /// The gitoid of the content. `None` for an imported checksum file,
+    /// since a plain digest can't be turned into a gitoid
+    pub fn gitoid(&self) -> Option<GitOid> {
+        self.gitoid
+    }
+}
+
+/// The checksum files `sha256sum` and `sha1sum` read and write, so
+/// release processes built on them can be bridged to GitBOM:
+///
+/// ```text
+/// <hex digest>  <path>
+/// ```
+///
+/// A `*` instead of the second space marks binary mode, which makes no
+/// difference here. A line starting with `\` has a path with `\\`, `\n`
+/// and `\r` escapes. The algorithm isn't named, so it's taken from the
+/// length of the digests
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChecksumFile {
+    hash_algorithm: HashAlgorithm,
+    entries: Vec<Checksum>,
+}
+
+impl ChecksumFile {
+    /// Hash every file under the directory at `root`, computing both the
+    /// plain digest and the gitoid in one read of each file, along with
+    /// an error for each file that couldn't be hashed. Will return an
+    /// `Err` only if `root` itself can't be read. `dedup_contents` has
+    /// no effect
+    pub fn from_directory<P: AsRef<Path>>(
+        root: P,
+        hash_algo: HashAlgorithm,
+        options: &ScanOptions,
+    ) -> IOResult<(ChecksumFile, Vec<ScanError>)> {
+        // by gitoid, so later links to a hard-linked file find theirs
+        let mut digests = HashMap::new();
+        let mut entries = Vec::new();
+        let mut errors = Vec::new();
+        let retried = Cell::new(0);
+        DirectoryScan::walk(
+            root.as_ref(),
+            options,
+            |_, path, metadata| {
+                let (gitoid, digest) =
+                    hash_file_digest(hash_algo, path, metadata, options, &retried, true)?;
+                if let Some(digest) = digest {
+                    digests.insert(gitoid, digest.into_vec());
+                }
+                Ok(gitoid)
+            },
+            |event| match event {
+                ScanEvent::FileHashed { path, gitoid, .. } => entries.push((path, gitoid)),
+                ScanEvent::Error { path, error } => errors.push(ScanError::new(path, error)),
+                _ => {}
+            },
+        )?;
+
+        let mut entries: Vec<Checksum> = entries
+            .into_iter()
+            .map(|(path, gitoid)| Checksum {
+                path,
+                digest: digests[&gitoid].clone(),
+                gitoid: Some(gitoid),
+            })
+            .collect();
+        entries.sort_by_cached_key(|checksum| path_order(&checksum.path));
+        errors.sort_by_cached_key(|error| path_order(error.path()));
+        Ok((
+            ChecksumFile {
+                hash_algorithm: hash_algo,
+                entries,
+            },
+            errors,
+        ))
+    }
+
+    /// Parse a checksum file. Nothing is hashed, so the entries have no
+    /// gitoids. Will return an `Err` for a malformed line or digests of
+    /// different lengths
+    pub fn from_bytes(bytes: &[u8]) -> IOResult<Self> {
+        let mut hash_algorithm = None;
+        let mut entries = Vec::new();
+        for line in bytes.split(|b| *b == b'\n') {
+            if line.is_empty() {
+                continue;
+            }
+            let bad_line = || {
+                bad_document(&format!(
+                    "bad checksum line {:?}",
+                    String::from_utf8_lossy(line)
+                ))
+            };
+            let (escaped, line) = match line.strip_prefix(b"\\") {
+                Some(line) => (true, line),
+                None => (false, line),
+            };
+            let space = line.iter().position(|b| *b == b' ').ok_or_else(bad_line)?;
+            let (hex_digest, rest) = line.split_at(space);
+            let path = match rest {
+                [b' ', b' ' | b'*', path @ ..] if !path.is_empty() => path,
+                _ => return Err(bad_line()),
+            };
+            let digest = hex::decode(hex_digest).map_err(|_| bad_line())?;
+            let algo = algorithm_for(&digest).ok_or_else(bad_line)?;
+            if *hash_algorithm.get_or_insert(algo) != algo {
+                return Err(bad_document("the digests are of different lengths"));
+            }
+            let path = if escaped {
+                unescape(path).ok_or_else(bad_line)?
+            } else {
+                path.to_vec()
+            };
+            entries.push(Checksum {
+                path: path_from_bytes(path).ok_or_else(bad_line)?,
+                digest,
+                gitoid: None,
+            });
+        }
+
+        Ok(ChecksumFile {
+            hash_algorithm: hash_algorithm.unwrap_or(HashAlgorithm::SHA256),
+            entries,
+        })
+    }
+
+    /// Write the checksum file, which `sha256sum -c` (or `sha1sum -c`)
+    /// can check. Paths use `/` separators
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut ret = Vec::new();
+        for checksum in &self.entries {
+            let path = path_order(&checksum.path);
+            if path.iter().any(|b| matches!(b, b'\\' | b'\n' | b'\r')) {
+                ret.push(b'\\');
+            }
+            ret.extend_from_slice(hex::encode(&checksum.digest).as_bytes());
+            ret.extend_from_slice(b"  ");
+            for b in path {
+                match b {
+                    b'\\' => ret.extend_from_slice(b"\\\\"),
+                    b'\n' => ret.extend_from_slice(b"\\n"),
+                    b'\r' => ret.extend_from_slice(b"\\r"),
+                    b => ret.push(b),
+                }
+            }
+            ret.push(b'\n');
+        }
+        ret
+    }
+
+    /// The algorithm the digests were computed with
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+
+    /// The entries, in file order
+    pub fn entries(&self) -> &[Checksum] {
+        &self.entries
+    }
+
+    /// A `GitBom` of the entries' gitoids, empty for an imported file
+    pub fn gitbom(&self) -> GitBom {
+        self.entries
+            .iter()
+            .filter_map(|checksum| checksum.gitoid)
+            .collect()
+    }
+}
+
+/// the algorithm for a plain digest of this length
+fn algorithm_for(digest: &[u8]) -> Option<HashAlgorithm> {
+    [
+        #[cfg(feature = "sha1")]
+        HashAlgorithm::SHA1,
+        HashAlgorithm::SHA256,
+    ]
+    .into_iter()
+    .find(|algo| algo.digest_len() == digest.len())
+}
+
+/// undo the `\\`, `\n` and `\r` escapes in a path
+fn unescape(path: &[u8]) -> Option<Vec<u8>> {
+    let mut ret = Vec::with_capacity(path.len());
+    let mut bytes = path.iter();
+    while let Some(b) = bytes.next() {
+        match b {
+            b'\\' => match bytes.next()? {
+                b'\\' => ret.push(b'\\'),
+                b'n' => ret.push(b'\n'),
+                b'r' => ret.push(b'\r'),
+                _ => return None,
+            },
+            b => ret.push(*b),
+        }
+    }
+    Some(ret)
+}
+
+#[cfg(unix)]
+fn path_from_bytes(path: Vec<u8>) -> Option<PathBuf> {
+    use std::os::unix::ffi::OsStringExt;
+    Some(PathBuf::from(std::ffi::OsString::from_vec(path)))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(path: Vec<u8>) -> Option<PathBuf> {
+    String::from_utf8(path).ok().map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_export_and_import() {
+        let (exported, errors) = ChecksumFile::from_directory(
+            "test/data/tree",
+            HashAlgorithm::SHA256,
+            &ScanOptions::new(),
+        )
+        .unwrap();
+        assert!(errors.is_empty());
+        let a = &exported.entries()[0];
+        assert_eq!(Path::new("a.txt"), a.path());
+        assert_eq!(&Sha256::digest(b"a\n")[..], a.digest());
+        assert_eq!(Some(GitOid::new(HashAlgorithm::SHA256, b"a\n")), a.gitoid());
+        assert_eq!(
+            DirectoryScan::new("test/data/tree", HashAlgorithm::SHA256, &ScanOptions::new())
+                .unwrap()
+                .gitbom(),
+            exported.gitbom()
+        );
+
+        let text = exported.to_bytes();
+        assert!(String::from_utf8_lossy(&text).ends_with(&format!(
+            "{}  sub/b.txt\n",
+            hex::encode(Sha256::digest(b"b\n"))
+        )));
+        let imported = ChecksumFile::from_bytes(&text).unwrap();
+        assert_eq!(HashAlgorithm::SHA256, imported.hash_algorithm());
+        assert_eq!(3, imported.entries().len());
+        assert_eq!(a.digest(), imported.entries()[0].digest());
+        assert!(imported.gitbom().is_empty());
+        assert_eq!(text, imported.to_bytes());
+    }
+
+    #[test]
+    fn test_import_escapes_and_binary_mode() {
+        let digest = hex::encode(Sha256::digest(b""));
+        let text = format!(
+            "\\{}  dir/new\\nline\\\\name\n{} *spaced name\n",
+            digest, digest
+        );
+        let imported = ChecksumFile::from_bytes(text.as_bytes()).unwrap();
+
+        assert_eq!(
+            Path::new("dir/new\nline\\name"),
+            imported.entries()[0].path()
+        );
+        assert_eq!(Path::new("spaced name"), imported.entries()[1].path());
+        assert_eq!(text.replace(" *", "  ").into_bytes(), imported.to_bytes());
+    }
+}
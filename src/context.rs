@@ -0,0 +1,127 @@
+use crate::ChangedDuringRead;
+use std::fmt::{Display, Formatter, Result};
+use std::io::Error;
+use std::path::{Path, PathBuf};
+
+/// Where an error happened: the file, and for documents the line and the
+/// byte offset it starts at, so a message reads "line 48213 (byte
+/// 3384840) of objects/ab/cd: ..." rather than just what went wrong.
+///
+/// This is the inner error of an `io::Error` with the same kind as the
+/// original; use `ErrorContext::from_io_error` to get at it
+#[derive(Debug)]
+pub struct ErrorContext {
+    path: Option<PathBuf>,
+    line: Option<u64>,
+    offset: Option<u64>,
+    error: Error,
+}
+
+impl ErrorContext {
+    /// The file the error happened in, if known
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// The line of a document the error happened on, counting the header
+    /// as line 1
+    pub fn line(&self) -> Option<u64> {
+        self.line
+    }
+
+    /// The byte offset of the start of that line
+    pub fn offset(&self) -> Option<u64> {
+        self.offset
+    }
+
+    /// The error without its context
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+
+    /// The `ErrorContext` inside `error`, if it has one
+    pub fn from_io_error(error: &Error) -> Option<&ErrorContext> {
+        error.get_ref()?.downcast_ref()
+    }
+
+    /// take the context out of `error`, or start a new one
+    fn take(error: Error) -> ErrorContext {
+        if ErrorContext::from_io_error(&error).is_some() {
+            // checked above
+            return *error.into_inner().unwrap().downcast().unwrap();
+        }
+        ErrorContext {
+            path: None,
+            line: None,
+            offset: None,
+            error,
+        }
+    }
+
+    fn into_error(self) -> Error {
+        Error::new(self.error.kind(), self)
+    }
+}
+
+impl Display for ErrorContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match (self.line, self.offset) {
+            (Some(line), Some(offset)) => write!(f, "line {} (byte {})", line, offset)?,
+            (Some(line), None) => write!(f, "line {}", line)?,
+            (None, Some(offset)) => write!(f, "byte {}", offset)?,
+            (None, None) => {}
+        }
+        match (&self.path, self.line.is_some() || self.offset.is_some()) {
+            (Some(path), true) => write!(f, " of {}", path.display())?,
+            (Some(path), false) => write!(f, "{}", path.display())?,
+            (None, _) => {}
+        }
+        write!(f, ": {}", self.error)
+    }
+}
+
+impl std::error::Error for ErrorContext {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Record that `error` happened in the file at `path`. A
+/// `ChangedDuringRead` already names its file, so it's passed on as is
+pub(crate) fn in_file(error: Error, path: &Path) -> Error {
+    if ChangedDuringRead::from_io_error(&error).is_some() {
+        return error;
+    }
+    let mut context = ErrorContext::take(error);
+    context.path = Some(path.to_path_buf());
+    context.into_error()
+}
+
+/// Record that `error` happened on the line starting at `offset`
+pub(crate) fn at_line(error: Error, line: u64, offset: u64) -> Error {
+    let mut context = ErrorContext::take(error);
+    context.line = Some(line);
+    context.offset = Some(offset);
+    context.into_error()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::ErrorKind;
+
+    #[test]
+    fn test_context_keeps_the_kind() {
+        let error = Error::new(ErrorKind::InvalidData, "bad hex");
+        let error = in_file(at_line(error, 48213, 3384840), Path::new("objects/ab/cd"));
+
+        assert_eq!(ErrorKind::InvalidData, error.kind());
+        assert_eq!(
+            "line 48213 (byte 3384840) of objects/ab/cd: bad hex",
+            error.to_string()
+        );
+        let context = ErrorContext::from_io_error(&error).unwrap();
+        assert_eq!(Some(48213), context.line());
+        assert_eq!("bad hex", context.error().to_string());
+    }
+}
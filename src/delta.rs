@@ -0,0 +1,175 @@
+use crate::document::{bad_document, parse_gitoid};
+use crate::{GitBom, GitOid, HashAlgorithm};
+use std::io::{Error, ErrorKind, Result as IOResult};
+
+/// The change between two versions of a GitBOM document: the
+/// identifier of the base document plus the oids added and removed.
+/// Successive builds usually change only a few inputs, so a delta is
+/// much smaller than the full document it describes.
+///
+/// Serialized as text in the style of a document:
+///
+/// ```text
+/// gitoid:delta:sha256
+/// base <hex>
+/// - blob <hex>
+/// + blob <hex>
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct GitBomDelta {
+    base: GitOid,
+    added: Vec<GitOid>,
+    removed: Vec<GitOid>,
+}
+
+impl GitBomDelta {
+    /// Compute the delta that turns `base` into `updated`. Will return an
+    /// `Err` if either has oids that weren't hashed with `hash_algo`
+    pub fn between(base: &GitBom, updated: &GitBom, hash_algo: HashAlgorithm) -> IOResult<Self> {
+        // the base is checked when its document is hashed below
+        updated.to_document(hash_algo)?;
+        let mut added: Vec<GitOid> = updated.difference(base).get_oids().into_iter().collect();
+        let mut removed: Vec<GitOid> = base.difference(updated).get_oids().into_iter().collect();
+        added.sort_unstable();
+        removed.sort_unstable();
+
+        Ok(GitBomDelta {
+            base: base.document_gitoid(hash_algo)?,
+            added,
+            removed,
+        })
+    }
+
+    /// The identifier of the document this delta applies to
+    pub fn base(&self) -> GitOid {
+        self.base
+    }
+
+    /// The oids the delta adds, sorted
+    pub fn added(&self) -> &[GitOid] {
+        &self.added
+    }
+
+    /// The oids the delta removes, sorted
+    pub fn removed(&self) -> &[GitOid] {
+        &self.removed
+    }
+
+    /// Apply the delta to `base`. Will return an `Err` if `base` isn't the
+    /// document the delta was computed against
+    pub fn apply(&self, base: &GitBom) -> IOResult<GitBom> {
+        let actual = base.document_gitoid(self.base.hash_algorithm())?;
+        if actual != self.base {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Delta applies to {} but the document is {}",
+                    self.base, actual
+                ),
+            ));
+        }
+
+        let removed: GitBom = self.removed.iter().copied().collect();
+        Ok(base
+            .difference(&removed)
+            .add_many(self.added.iter().copied()))
+    }
+
+    /// Serialize the delta
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut ret = format!(
+            "gitoid:delta:{}\nbase {}\n",
+            self.base.hash_algorithm().document_name(),
+            self.base.hex_hash()
+        );
+        for gitoid in &self.removed {
+            ret.push_str(&format!(
+                "- {} {}\n",
+                gitoid.object_type(),
+                gitoid.hex_hash()
+            ));
+        }
+        for gitoid in &self.added {
+            ret.push_str(&format!(
+                "+ {} {}\n",
+                gitoid.object_type(),
+                gitoid.hex_hash()
+            ));
+        }
+        ret.into_bytes()
+    }
+
+    /// Parse a delta written by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> IOResult<Self> {
+        let text = std::str::from_utf8(bytes).map_err(|_| bad_document("not UTF-8"))?;
+        let mut lines = text.lines();
+
+        let hash_algo = lines
+            .next()
+            .and_then(|header| header.strip_prefix("gitoid:delta:"))
+            .and_then(HashAlgorithm::from_document_name)
+            .ok_or_else(|| bad_document("missing gitoid:delta:<algorithm> header"))?;
+        let base = lines
+            .next()
+            .and_then(|line| line.strip_prefix("base "))
+            .ok_or_else(|| bad_document("missing base line"))?;
+        let base = parse_gitoid(hash_algo, &format!("blob {}", base))?;
+
+        let mut added = Vec::new();
+        let mut removed = Vec::new();
+        for line in lines {
+            if let Some(entry) = line.strip_prefix("+ ") {
+                added.push(parse_gitoid(hash_algo, entry)?);
+            } else if let Some(entry) = line.strip_prefix("- ") {
+                removed.push(parse_gitoid(hash_algo, entry)?);
+            } else {
+                return Err(bad_document(&format!("bad delta line {:?}", line)));
+            }
+        }
+
+        Ok(GitBomDelta {
+            base,
+            added,
+            removed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make(names: &[&str]) -> GitBom {
+        names
+            .iter()
+            .map(|name| GitOid::new_from_str(name))
+            .collect()
+    }
+
+    #[test]
+    fn test_delta_apply() {
+        let base = make(&["a", "b", "c"]);
+        let updated = make(&["b", "c", "d"]);
+        let delta = GitBomDelta::between(&base, &updated, HashAlgorithm::SHA256).unwrap();
+
+        assert_eq!(&[GitOid::new_from_str("d")], delta.added());
+        assert_eq!(&[GitOid::new_from_str("a")], delta.removed());
+        assert_eq!(updated, delta.apply(&base).unwrap());
+    }
+
+    #[test]
+    fn test_delta_round_trip() {
+        let base = make(&["a", "b"]);
+        let delta = GitBomDelta::between(&base, &make(&["b", "c"]), HashAlgorithm::SHA256).unwrap();
+
+        assert_eq!(delta, GitBomDelta::from_bytes(&delta.to_bytes()).unwrap());
+    }
+
+    #[test]
+    fn test_delta_wrong_base() {
+        let delta =
+            GitBomDelta::between(&make(&["a"]), &make(&["b"]), HashAlgorithm::SHA256).unwrap();
+
+        assert!(delta.apply(&make(&["c"])).is_err());
+    }
+}
@@ -0,0 +1,722 @@
+use crate::context::at_line;
+use crate::{Finding, FindingKind, GitBom, GitOid, HashAlgorithm, ObjectType, Severity};
+use std::io::{BufRead, Error, ErrorKind, Read, Result as IOResult};
+
+impl HashAlgorithm {
+    /// the lowercase name used in GitBOM documents
+    pub(crate) fn document_name(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "sha1")]
+            HashAlgorithm::SHA1 => "sha1",
+            HashAlgorithm::SHA256 => "sha256",
+        }
+    }
+
+    pub(crate) fn from_document_name(name: &str) -> Option<HashAlgorithm> {
+        match name {
+            #[cfg(feature = "sha1")]
+            "sha1" => Some(HashAlgorithm::SHA1),
+            "sha256" => Some(HashAlgorithm::SHA256),
+            _ => None,
+        }
+    }
+}
+
+impl ObjectType {
+    /// the name used in GitBOM documents
+    pub(crate) fn document_name(&self) -> &'static str {
+        match self {
+            ObjectType::Blob => "blob",
+            ObjectType::Tree => "tree",
+            ObjectType::Commit => "commit",
+            ObjectType::Tag => "tag",
+        }
+    }
+
+    pub(crate) fn from_document_name(name: &str) -> Option<ObjectType> {
+        match name {
+            "blob" => Some(ObjectType::Blob),
+            "tree" => Some(ObjectType::Tree),
+            "commit" => Some(ObjectType::Commit),
+            "tag" => Some(ObjectType::Tag),
+            _ => None,
+        }
+    }
+}
+
+impl GitOid {
+    /// The `gitoid:<type>:<algorithm>:<hex>` URI for this GitOid, as used
+    /// to identify artifacts in GitBOM documents and other formats
+    pub fn uri(&self) -> String {
+        format!(
+            "gitoid:{}:{}:{}",
+            self.object_type(),
+            self.hash_algorithm().document_name(),
+            self.hex_hash()
+        )
+    }
+
+    /// Parse a `gitoid:<type>:<algorithm>:<hex>` URI
+    pub fn from_uri(uri: &str) -> IOResult<GitOid> {
+        let bad_uri = || {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("{:?} is not a gitoid URI", uri),
+            )
+        };
+        let mut parts = uri.splitn(4, ':');
+        if parts.next() != Some("gitoid") {
+            return Err(bad_uri());
+        }
+        let (object_type, hash_algo, hash) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(object_type), Some(hash_algo), Some(hash)) => (object_type, hash_algo, hash),
+            _ => return Err(bad_uri()),
+        };
+        let hash_algo = HashAlgorithm::from_document_name(hash_algo).ok_or_else(bad_uri)?;
+
+        parse_gitoid(hash_algo, &format!("{} {}", object_type, hash)).map_err(|_| bad_uri())
+    }
+}
+
+/// The versions of the document format. Both start with a
+/// `gitoid:blob:<algorithm>` header and list one entry per line, sorted
+#[derive(Clone, Copy, PartialOrd, Eq, Ord, Debug, Hash, PartialEq, Default)]
+pub enum SpecVersion {
+    /// The original [GitBOM](https://gitbom.dev/spec/) format, with
+    /// `<type> <hex>` lines
+    #[default]
+    GitBom,
+    /// The [OmniBOR](https://omnibor.io/) input manifest format, with a
+    /// bare `<hex>` line per blob. Manifest links (`<hex> manifest
+    /// <hex>`) aren't supported
+    OmniBor,
+}
+
+impl GitBom {
+    /// Serialize as a [GitBOM document](https://gitbom.dev/spec/): a
+    /// `gitoid:blob:<algorithm>` header line followed by one
+    /// `<type> <hex>` line per oid, sorted, so the same set of oids always
+    /// produces the same bytes. Will return an `Err` if any oid wasn't
+    /// hashed with `hash_algo`
+    pub fn to_document(&self, hash_algo: HashAlgorithm) -> IOResult<Vec<u8>> {
+        self.to_document_version(hash_algo, SpecVersion::GitBom)
+    }
+
+    /// Serialize in the given version of the document format. Will return
+    /// an `Err` if any oid wasn't hashed with `hash_algo`, or for
+    /// `SpecVersion::OmniBor`, if any oid isn't a blob
+    pub fn to_document_version(
+        &self,
+        hash_algo: HashAlgorithm,
+        version: SpecVersion,
+    ) -> IOResult<Vec<u8>> {
+        let mut gitoids = Vec::with_capacity(self.len());
+        for gitoid in self.get_oids() {
+            if gitoid.hash_algorithm() != hash_algo {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("{} is not a {} oid", gitoid, hash_algo),
+                ));
+            }
+            if version == SpecVersion::OmniBor && gitoid.object_type() != ObjectType::Blob {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "{} isn't a blob, so can't be in an OmniBOR manifest",
+                        gitoid
+                    ),
+                ));
+            }
+            gitoids.push(gitoid);
+        }
+        // the order of the formatted lines, without formatting them to sort
+        gitoids.sort_unstable_by(|a, b| {
+            (a.object_type().document_name(), a.hash_value())
+                .cmp(&(b.object_type().document_name(), b.hash_value()))
+        });
+
+        let mut ret = format!("gitoid:blob:{}\n", hash_algo.document_name()).into_bytes();
+        ret.reserve(gitoids.len() * (hash_algo.digest_len() * 2 + 8));
+        let mut hex = [0u8; 64];
+        for gitoid in gitoids {
+            if version == SpecVersion::GitBom {
+                ret.extend_from_slice(gitoid.object_type().document_name().as_bytes());
+                ret.push(b' ');
+            }
+            ret.extend_from_slice(gitoid.hex_hash_into(&mut hex).as_bytes());
+            ret.push(b'\n');
+        }
+        Ok(ret)
+    }
+
+    /// The `GitOid` of the document from `to_document`, i.e. the GitBOM
+    /// identifier of this set of oids
+    pub fn document_gitoid(&self, hash_algo: HashAlgorithm) -> IOResult<GitOid> {
+        Ok(GitOid::hash_object(
+            hash_algo,
+            ObjectType::Blob,
+            &self.to_document(hash_algo)?,
+        ))
+    }
+
+    /// Parse a document written by `to_document` or `to_document_version`,
+    /// in either format version. Will return an `Err` if the header or
+    /// any line is malformed, the lines mix versions or they aren't in
+    /// canonical order. Use `DocumentReader` for documents too big to
+    /// hold in memory
+    pub fn from_document(document: &[u8]) -> IOResult<GitBom> {
+        Ok(GitBom::from_document_with_options(document, &ParseOptions::new())?.0)
+    }
+
+    /// Parse a document as `options` say, returning any warnings from
+    /// lenient parsing along with the `GitBom`
+    pub fn from_document_with_options(
+        document: &[u8],
+        options: &ParseOptions,
+    ) -> IOResult<(GitBom, Vec<Finding>)> {
+        let mut entries = DocumentReader::with_options(document, options)?.entries();
+        let mut gitoids = Vec::new();
+        for gitoid in &mut entries {
+            gitoids.push(gitoid?);
+        }
+        Ok((GitBom::new_from_iterator(gitoids), entries.into_warnings()))
+    }
+}
+
+/// The longest line accepted by default. Canonical lines are under 80
+/// bytes
+const DEFAULT_MAX_LINE_LENGTH: usize = 1024;
+
+/// How strictly `DocumentReader` checks a document, and how much of it
+/// it will read, for documents from untrusted sources
+#[derive(Clone, Debug)]
+pub struct ParseOptions {
+    lenient: bool,
+    max_line_length: usize,
+    max_entries: Option<usize>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            lenient: false,
+            max_line_length: DEFAULT_MAX_LINE_LENGTH,
+            max_entries: None,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// The defaults: strict parsing, lines of up to 1024 bytes and any
+    /// number of entries
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail on a line longer than `max` bytes, newline included, before
+    /// reading the rest of it
+    pub fn max_line_length(mut self, max: usize) -> Self {
+        self.max_line_length = max;
+        self
+    }
+
+    /// Fail on a document with more than `max` entries
+    pub fn max_entries(mut self, max: usize) -> Self {
+        self.max_entries = Some(max);
+        self
+    }
+
+    /// Accept documents that aren't canonical, i.e. with unsorted or
+    /// duplicate entries, uppercase, CRLF line endings or no final
+    /// newline, and normalize them, recording a warning for each problem.
+    /// For ingesting documents from other tools; the gitoid of a
+    /// normalized document differs from the original's
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+}
+
+/// Reads a GitBOM document one line at a time, so documents with millions
+/// of entries (e.g. from monorepo builds) can be processed without
+/// holding them in memory. The canonical ordering is checked as each
+/// line is read
+pub struct DocumentReader<R> {
+    reader: R,
+    hash_algorithm: HashAlgorithm,
+    options: ParseOptions,
+    warnings: Vec<Finding>,
+    /// the length of the header
+    offset: u64,
+}
+
+impl<R: BufRead> DocumentReader<R> {
+    /// Read the header line. Will return an `Err` if it's malformed
+    pub fn new(reader: R) -> IOResult<Self> {
+        DocumentReader::with_options(reader, &ParseOptions::new())
+    }
+
+    /// Read the header line, parsing as `options` say. Will return an
+    /// `Err` if it's malformed
+    pub fn with_options(mut reader: R, options: &ParseOptions) -> IOResult<Self> {
+        let mut warnings = Vec::new();
+        let mut header = String::new();
+        let mut read_header = || {
+            let size = read_limited_line(&mut reader, &mut header, options.max_line_length)?;
+            let hash_algorithm = normalize_line(&mut header, options, &mut warnings, true)?
+                .and_then(|header| header.strip_prefix("gitoid:blob:"))
+                .and_then(HashAlgorithm::from_document_name)
+                .ok_or_else(|| bad_document("missing gitoid:blob:<algorithm> header"))?;
+            Ok((size, hash_algorithm))
+        };
+        let (size, hash_algorithm) = read_header().map_err(|error| at_line(error, 1, 0))?;
+
+        Ok(DocumentReader {
+            reader,
+            hash_algorithm,
+            options: options.clone(),
+            warnings,
+            offset: size as u64,
+        })
+    }
+
+    /// The hash algorithm from the header
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+
+    /// Iterate over the entries. After the first `Err` the iterator ends
+    pub fn entries(self) -> DocumentEntries<R> {
+        DocumentEntries {
+            reader: self.reader,
+            hash_algorithm: self.hash_algorithm,
+            options: self.options,
+            warnings: self.warnings,
+            line: String::new(),
+            previous: String::new(),
+            spec_version: None,
+            count: 0,
+            line_number: 1,
+            line_offset: self.offset,
+            offset: self.offset,
+            done: false,
+        }
+    }
+}
+
+/// The entries of a document, from `DocumentReader::entries`
+pub struct DocumentEntries<R> {
+    reader: R,
+    hash_algorithm: HashAlgorithm,
+    options: ParseOptions,
+    warnings: Vec<Finding>,
+    line: String,
+    previous: String,
+    spec_version: Option<SpecVersion>,
+    count: usize,
+    /// the line being parsed, counting the header as line 1, and where
+    /// it starts
+    line_number: u64,
+    line_offset: u64,
+    offset: u64,
+    done: bool,
+}
+
+impl<R: BufRead> DocumentEntries<R> {
+    /// The format version, detected from the first entry. `None` until
+    /// an entry has been read, and for documents with no entries
+    pub fn spec_version(&self) -> Option<SpecVersion> {
+        self.spec_version
+    }
+
+    /// What lenient parsing has normalized so far. Always empty when
+    /// parsing strictly
+    pub fn warnings(&self) -> &[Finding] {
+        &self.warnings
+    }
+
+    /// Take the warnings, dropping the reader
+    pub fn into_warnings(self) -> Vec<Finding> {
+        self.warnings
+    }
+
+    fn next_entry(&mut self) -> IOResult<Option<GitOid>> {
+        self.line.clear();
+        self.line_number += 1;
+        self.line_offset = self.offset;
+        let size = read_limited_line(
+            &mut self.reader,
+            &mut self.line,
+            self.options.max_line_length,
+        )?;
+        if size == 0 {
+            return Ok(None);
+        }
+        self.offset += size as u64;
+        self.count += 1;
+        if let Some(max) = self.options.max_entries {
+            if self.count > max {
+                return Err(bad_document(&format!("more than {} entries", max)));
+            }
+        }
+        let line = normalize_line(&mut self.line, &self.options, &mut self.warnings, false)?
+            .ok_or_else(|| bad_document("the last line has no newline"))?;
+        if !self.previous.is_empty() && line <= self.previous.as_str() {
+            let msg = format!("{:?} is out of order or a duplicate", line);
+            if !self.options.lenient {
+                return Err(bad_document(&msg));
+            }
+            self.warnings.push(noncanonical(msg));
+        }
+
+        let (version, gitoid) = match line.split_once(' ') {
+            None => (
+                SpecVersion::OmniBor,
+                parse_gitoid(self.hash_algorithm, &format!("blob {}", line))?,
+            ),
+            Some((_, rest)) if rest.starts_with("manifest ") => {
+                return Err(bad_document(&format!(
+                    "manifest links aren't supported in {:?}",
+                    line
+                )))
+            }
+            Some(_) => (
+                SpecVersion::GitBom,
+                parse_gitoid(self.hash_algorithm, line)?,
+            ),
+        };
+        if *self.spec_version.get_or_insert(version) != version {
+            return Err(bad_document(&format!(
+                "{:?} is in a different format version",
+                line
+            )));
+        }
+
+        if line > self.previous.as_str() {
+            self.previous.clear();
+            self.previous.push_str(line);
+        }
+        Ok(Some(gitoid))
+    }
+}
+
+impl<R: BufRead> Iterator for DocumentEntries<R> {
+    type Item = IOResult<GitOid>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let ret = self
+            .next_entry()
+            .map_err(|error| at_line(error, self.line_number, self.line_offset))
+            .transpose();
+        if !matches!(ret, Some(Ok(_))) {
+            self.done = true;
+        }
+        ret
+    }
+}
+
+/// read a line, failing once it's longer than `max` bytes
+fn read_limited_line<R: BufRead>(reader: &mut R, line: &mut String, max: usize) -> IOResult<usize> {
+    let size = reader.by_ref().take(max as u64 + 1).read_line(line)?;
+    if size > max {
+        return Err(bad_document(&format!(
+            "a line is longer than {} bytes",
+            max
+        )));
+    }
+    Ok(size)
+}
+
+/// strip the newline from `line`, or in lenient mode fix up its line
+/// ending and casing with a warning. `None` if a strict line has no
+/// newline
+fn normalize_line<'a>(
+    line: &'a mut String,
+    options: &ParseOptions,
+    warnings: &mut Vec<Finding>,
+    header: bool,
+) -> IOResult<Option<&'a str>> {
+    if !options.lenient {
+        if line.contains(|c: char| c.is_ascii_uppercase()) {
+            return Err(bad_document(&format!("{:?} isn't lowercase", line)));
+        }
+        return Ok(line.strip_suffix('\n'));
+    }
+
+    let what = if header { "the header" } else { "an entry" };
+    match line.strip_suffix('\n') {
+        Some(rest) if rest.ends_with('\r') => {
+            warnings.push(noncanonical(format!("{} has a CRLF line ending", what)));
+            line.truncate(line.len() - 2);
+        }
+        Some(_) => line.truncate(line.len() - 1),
+        None => warnings.push(noncanonical(format!("{} has no newline", what))),
+    }
+    if line.contains(|c: char| c.is_ascii_uppercase()) {
+        warnings.push(noncanonical(format!("{:?} isn't lowercase", line)));
+        line.make_ascii_lowercase();
+    }
+    Ok(Some(line.as_str()))
+}
+
+fn noncanonical(msg: String) -> Finding {
+    Finding::new(Severity::Warning, FindingKind::Noncanonical, msg)
+}
+
+/// parse a `<type> <hex>` line
+pub(crate) fn parse_gitoid(hash_algo: HashAlgorithm, line: &str) -> IOResult<GitOid> {
+    let (object_type, hash) = line
+        .split_once(' ')
+        .ok_or_else(|| bad_document(&format!("bad line {:?}", line)))?;
+    let object_type = ObjectType::from_document_name(object_type)
+        .ok_or_else(|| bad_document(&format!("unknown object type in {:?}", line)))?;
+    let digest = hex::decode(hash).map_err(|_| bad_document(&format!("bad hex in {:?}", line)))?;
+
+    GitOid::from_raw(hash_algo, object_type, &digest).map_err(|e| bad_document(&e.to_string()))
+}
+
+pub(crate) fn bad_document(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("Invalid document: {}", msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ErrorContext;
+
+    #[test]
+    fn test_document_is_canonical() {
+        let gitbom: GitBom = vec!["b", "a"]
+            .into_iter()
+            .map(GitOid::new_from_str)
+            .collect();
+        let document =
+            String::from_utf8(gitbom.to_document(HashAlgorithm::SHA256).unwrap()).unwrap();
+
+        let mut expected = [
+            format!("blob {}", GitOid::new_from_str("a").hex_hash()),
+            format!("blob {}", GitOid::new_from_str("b").hex_hash()),
+        ];
+        expected.sort();
+        assert_eq!(
+            format!("gitoid:blob:sha256\n{}\n{}\n", expected[0], expected[1]),
+            document
+        );
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_uri() {
+        assert_eq!(
+            "gitoid:blob:sha1:95d09f2b10159347eece71399a7e2e907ea3df4f",
+            GitOid::new(HashAlgorithm::SHA1, b"hello world").uri()
+        );
+
+        let gitoid = GitOid::new_object(HashAlgorithm::SHA256, ObjectType::Tree, b"");
+        assert_eq!(gitoid, GitOid::from_uri(&gitoid.uri()).unwrap());
+        assert!(GitOid::from_uri("gitoid:blob:sha256").is_err());
+        assert!(GitOid::from_uri("gitoid:blob:md5:00").is_err());
+    }
+
+    #[test]
+    fn test_mixed_types_sort_as_lines() {
+        let gitbom: GitBom = [
+            ObjectType::Tree,
+            ObjectType::Blob,
+            ObjectType::Tag,
+            ObjectType::Commit,
+        ]
+        .into_iter()
+        .flat_map(|object_type| {
+            ["x", "y"].map(|content| {
+                GitOid::new_object(HashAlgorithm::SHA256, object_type, content.as_bytes())
+            })
+        })
+        .collect();
+        let document =
+            String::from_utf8(gitbom.to_document(HashAlgorithm::SHA256).unwrap()).unwrap();
+
+        let mut lines: Vec<String> = gitbom
+            .get_oids()
+            .into_iter()
+            .map(|gitoid| format!("{} {}\n", gitoid.object_type(), gitoid.hex_hash()))
+            .collect();
+        lines.sort();
+        assert_eq!(format!("gitoid:blob:sha256\n{}", lines.concat()), document);
+    }
+
+    #[test]
+    fn test_errors_give_the_line_and_offset() {
+        let gitbom: GitBom = (0..3)
+            .map(|n| GitOid::new(HashAlgorithm::SHA256, n.to_string().as_bytes()))
+            .collect();
+        let mut document = gitbom.to_document(HashAlgorithm::SHA256).unwrap();
+        document.extend_from_slice(b"blob nothex\n");
+
+        let err = GitBom::from_document(&document).unwrap_err();
+        assert_eq!(ErrorKind::InvalidData, err.kind());
+        let context = ErrorContext::from_io_error(&err).unwrap();
+        assert_eq!(Some(5), context.line());
+        assert_eq!(Some(19 + 3 * 70), context.offset());
+        assert!(err.to_string().starts_with("line 5 (byte 229): "));
+
+        let err = GitBom::from_document(b"gitoid:blob:md5\n").unwrap_err();
+        assert_eq!(Some(1), ErrorContext::from_io_error(&err).unwrap().line());
+    }
+
+    #[test]
+    fn test_document_round_trip() {
+        let gitbom: GitBom = (0..50)
+            .map(|i| GitOid::new_from_str(&i.to_string()))
+            .collect();
+        let document = gitbom.to_document(HashAlgorithm::SHA256).unwrap();
+
+        assert_eq!(gitbom, GitBom::from_document(&document).unwrap());
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_streaming_reader() {
+        let gitbom: GitBom = (0..10)
+            .map(|i| GitOid::new(HashAlgorithm::SHA1, i.to_string().as_bytes()))
+            .collect();
+        let document = gitbom.to_document(HashAlgorithm::SHA1).unwrap();
+        let reader = DocumentReader::new(&document[..]).unwrap();
+
+        assert_eq!(HashAlgorithm::SHA1, reader.hash_algorithm());
+        let entries: Vec<GitOid> = reader.entries().map(Result::unwrap).collect();
+        assert_eq!(
+            gitbom.get_sorted_oids().into_iter().collect::<Vec<_>>(),
+            entries
+        );
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_streaming_reader_checks_order() {
+        let a = format!(
+            "blob {}\n",
+            GitOid::new(HashAlgorithm::SHA1, b"a").hex_hash()
+        );
+        let b = format!(
+            "blob {}\n",
+            GitOid::new(HashAlgorithm::SHA1, b"b").hex_hash()
+        );
+        let (first, second) = if a < b { (a, b) } else { (b, a) };
+
+        for lines in [vec![&second, &first], vec![&first, &first]] {
+            let document = format!("gitoid:blob:sha1\n{}{}", lines[0], lines[1]);
+            let results: Vec<IOResult<GitOid>> = DocumentReader::new(document.as_bytes())
+                .unwrap()
+                .entries()
+                .collect();
+
+            assert_eq!(2, results.len());
+            assert!(results[0].is_ok());
+            assert!(results[1].is_err());
+        }
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_document_rejects_mixed_algorithms() {
+        let gitbom = GitBom::new().add(GitOid::new(HashAlgorithm::SHA1, b"hello world"));
+
+        assert!(gitbom.to_document(HashAlgorithm::SHA256).is_err());
+        assert!(GitBom::from_document(
+            b"gitoid:blob:sha256\nblob 95d09f2b10159347eece71399a7e2e907ea3df4f\n"
+        )
+        .is_err());
+    }
+
+    #[cfg(not(feature = "sha1"))]
+    #[test]
+    fn test_sha1_is_rejected_without_the_feature() {
+        assert!(GitBom::from_document(b"gitoid:blob:sha1\n").is_err());
+        assert!(
+            GitOid::from_uri("gitoid:blob:sha1:95d09f2b10159347eece71399a7e2e907ea3df4f").is_err()
+        );
+    }
+
+    #[test]
+    fn test_spec_versions() {
+        let hello = GitOid::new(HashAlgorithm::SHA256, b"hello world");
+        let gitbom = GitBom::new().add(hello);
+        let omnibor = gitbom
+            .to_document_version(HashAlgorithm::SHA256, SpecVersion::OmniBor)
+            .unwrap();
+
+        assert_eq!(
+            format!("gitoid:blob:sha256\n{}\n", hello.hex_hash()),
+            String::from_utf8(omnibor.clone()).unwrap()
+        );
+        let mut entries = DocumentReader::new(&omnibor[..]).unwrap().entries();
+        assert_eq!(None, entries.spec_version());
+        assert_eq!(hello, entries.next().unwrap().unwrap());
+        assert_eq!(Some(SpecVersion::OmniBor), entries.spec_version());
+        assert_eq!(gitbom, GitBom::from_document(&omnibor).unwrap());
+
+        let tree = GitOid::new_object(HashAlgorithm::SHA256, ObjectType::Tree, b"");
+        assert!(GitBom::new()
+            .add(tree)
+            .to_document_version(HashAlgorithm::SHA256, SpecVersion::OmniBor)
+            .is_err());
+        let mixed = format!(
+            "gitoid:blob:sha256\nblob {}\n{}\n",
+            GitOid::new(HashAlgorithm::SHA256, b"a").hex_hash(),
+            GitOid::new(HashAlgorithm::SHA256, b"b").hex_hash()
+        );
+        assert!(GitBom::from_document(mixed.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_lenient_parsing() {
+        let a = GitOid::new(HashAlgorithm::SHA256, b"a");
+        let b = GitOid::new(HashAlgorithm::SHA256, b"b");
+        let (first, second) = if a.hex_hash() < b.hex_hash() {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        let document = format!(
+            "gitoid:blob:sha256\r\nblob {}\nblob {}\nBLOB {}",
+            second.hex_hash(),
+            first.hex_hash(),
+            first.hex_hash().to_uppercase()
+        );
+
+        assert!(GitBom::from_document(document.as_bytes()).is_err());
+        let (gitbom, warnings) = GitBom::from_document_with_options(
+            document.as_bytes(),
+            &ParseOptions::new().lenient(true),
+        )
+        .unwrap();
+        assert_eq!(GitBom::new().add(a).add(b), gitbom);
+        // CRLF, out of order, uppercase, duplicate, no newline
+        assert_eq!(5, warnings.len());
+        assert!(warnings
+            .iter()
+            .all(|warning| warning.kind() == FindingKind::Noncanonical));
+    }
+
+    #[test]
+    fn test_resource_limits() {
+        let gitbom: GitBom = (0..3)
+            .map(|i| GitOid::new_from_str(&i.to_string()))
+            .collect();
+        let document = gitbom.to_document(HashAlgorithm::SHA256).unwrap();
+        let parse = |options: ParseOptions| GitBom::from_document_with_options(&document, &options);
+
+        assert!(parse(ParseOptions::new().max_entries(3)).is_ok());
+        assert!(parse(ParseOptions::new().max_entries(2)).is_err());
+        assert!(parse(ParseOptions::new().max_line_length(70)).is_ok());
+        assert!(parse(ParseOptions::new().max_line_length(69)).is_err());
+
+        let long = format!("gitoid:blob:sha256\nblob {}\n", "0".repeat(1 << 20));
+        let err = GitBom::from_document(long.as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("longer than 1024 bytes"));
+    }
+}
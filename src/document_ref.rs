@@ -0,0 +1,249 @@
+use crate::document::bad_document;
+use crate::{GitBom, GitOid, HashAlgorithm, ObjectType, SpecVersion};
+use std::cmp::Ordering;
+use std::io::Result as IOResult;
+use std::str::SplitTerminator;
+
+/// A canonical GitBOM document parsed in place: the entries stay in the
+/// input buffer and are only decoded as they're read, so checking
+/// membership needs no allocation at all. For services that parse
+/// documents only to answer `contains` queries; use `to_gitbom` for an
+/// owned `GitBom`
+#[derive(Clone, Copy, Debug)]
+pub struct DocumentRef<'a> {
+    hash_algorithm: HashAlgorithm,
+    spec_version: Option<SpecVersion>,
+    /// the entry lines, each ending in a newline
+    body: &'a str,
+    len: usize,
+}
+
+impl<'a> DocumentRef<'a> {
+    /// Check that `document` is a canonical document in either format
+    /// version, without copying it. Will return an `Err` for anything
+    /// `GitBom::from_document` would reject
+    pub fn parse(document: &'a [u8]) -> IOResult<Self> {
+        let text = std::str::from_utf8(document).map_err(|_| bad_document("not UTF-8"))?;
+        let (header, body) = text
+            .split_once('\n')
+            .ok_or_else(|| bad_document("missing gitoid:blob:<algorithm> header"))?;
+        let hash_algorithm = header
+            .strip_prefix("gitoid:blob:")
+            .and_then(HashAlgorithm::from_document_name)
+            .ok_or_else(|| bad_document("missing gitoid:blob:<algorithm> header"))?;
+        if !body.is_empty() && !body.ends_with('\n') {
+            return Err(bad_document("the last line has no newline"));
+        }
+
+        let mut spec_version = None;
+        let mut previous = "";
+        let mut len = 0;
+        // split on `\n` alone, so a `\r` is part of the line and rejected
+        for line in body.split_terminator('\n') {
+            if line.contains(|c: char| c.is_ascii_uppercase()) {
+                return Err(bad_document(&format!("{:?} isn't lowercase", line)));
+            }
+            if line <= previous {
+                return Err(bad_document(&format!(
+                    "{:?} is out of order or a duplicate",
+                    line
+                )));
+            }
+            let (version, _) = parse_line(hash_algorithm, line)?;
+            if *spec_version.get_or_insert(version) != version {
+                return Err(bad_document(&format!(
+                    "{:?} is in a different format version",
+                    line
+                )));
+            }
+            previous = line;
+            len += 1;
+        }
+
+        Ok(DocumentRef {
+            hash_algorithm,
+            spec_version,
+            body,
+            len,
+        })
+    }
+
+    /// The hash algorithm from the header
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+
+    /// The format version, `None` for a document with no entries
+    pub fn spec_version(&self) -> Option<SpecVersion> {
+        self.spec_version
+    }
+
+    /// The number of entries
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Does the document have no entries?
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Is `gitoid` listed? A binary search over the sorted lines in the
+    /// buffer
+    pub fn contains(&self, gitoid: &GitOid) -> bool {
+        let mut buf = [0u8; 80];
+        let target = match self.entry_line(gitoid, &mut buf) {
+            Some(target) => target,
+            None => return false,
+        };
+
+        let body = self.body;
+        let (mut lo, mut hi) = (0, body.len());
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            let start = body[lo..mid].rfind('\n').map_or(lo, |i| lo + i + 1);
+            // every line ends in a newline
+            let end = start + body[start..].find('\n').unwrap();
+            match body[start..end].cmp(target) {
+                Ordering::Equal => return true,
+                Ordering::Less => lo = end + 1,
+                Ordering::Greater => hi = start,
+            }
+        }
+        false
+    }
+
+    /// The entries, decoded as they're iterated
+    pub fn entries(&self) -> DocumentRefEntries<'a> {
+        DocumentRefEntries {
+            hash_algorithm: self.hash_algorithm,
+            lines: self.body.split_terminator('\n'),
+        }
+    }
+
+    /// Copy the entries into an owned `GitBom`
+    pub fn to_gitbom(&self) -> GitBom {
+        GitBom::new_from_iterator(self.entries())
+    }
+
+    /// the line `gitoid` would have in this document, if it could be in it
+    fn entry_line<'b>(&self, gitoid: &GitOid, buf: &'b mut [u8; 80]) -> Option<&'b str> {
+        if gitoid.hash_algorithm() != self.hash_algorithm {
+            return None;
+        }
+        let mut len = 0;
+        if self.spec_version != Some(SpecVersion::OmniBor) {
+            let name = gitoid.object_type().document_name().as_bytes();
+            buf[..name.len()].copy_from_slice(name);
+            buf[name.len()] = b' ';
+            len = name.len() + 1;
+        } else if gitoid.object_type() != ObjectType::Blob {
+            return None;
+        }
+        len += gitoid.write_hex(&mut buf[len..]).ok()?.len();
+        std::str::from_utf8(&buf[..len]).ok()
+    }
+}
+
+/// The entries of a `DocumentRef`
+#[derive(Clone, Debug)]
+pub struct DocumentRefEntries<'a> {
+    hash_algorithm: HashAlgorithm,
+    lines: SplitTerminator<'a, char>,
+}
+
+impl Iterator for DocumentRefEntries<'_> {
+    type Item = GitOid;
+
+    fn next(&mut self) -> Option<GitOid> {
+        let line = self.lines.next()?;
+        // every line was checked by `DocumentRef::parse`
+        Some(parse_line(self.hash_algorithm, line).unwrap().1)
+    }
+}
+
+/// parse a `<type> <hex>` or bare `<hex>` line without allocating
+fn parse_line(hash_algo: HashAlgorithm, line: &str) -> IOResult<(SpecVersion, GitOid)> {
+    let bad_line = || bad_document(&format!("bad line {:?}", line));
+    let (version, object_type, hash) = match line.split_once(' ') {
+        None => (SpecVersion::OmniBor, ObjectType::Blob, line),
+        Some((object_type, hash)) => (
+            SpecVersion::GitBom,
+            ObjectType::from_document_name(object_type).ok_or_else(bad_line)?,
+            hash,
+        ),
+    };
+    if hash.len() != hash_algo.digest_len() * 2 {
+        return Err(bad_line());
+    }
+    let mut digest = [0u8; 32];
+    let digest = &mut digest[..hash.len() / 2];
+    hex::decode_to_slice(hash, digest).map_err(|_| bad_line())?;
+    Ok((version, GitOid::from_raw(hash_algo, object_type, digest)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_borrowed_document() {
+        let gitbom: GitBom = (0..20)
+            .map(|n| GitOid::new(HashAlgorithm::SHA256, n.to_string().as_bytes()))
+            .chain([GitOid::new_object(
+                HashAlgorithm::SHA256,
+                ObjectType::Tree,
+                b"",
+            )])
+            .collect();
+        let document = gitbom.to_document(HashAlgorithm::SHA256).unwrap();
+        let parsed = DocumentRef::parse(&document).unwrap();
+
+        assert_eq!(21, parsed.len());
+        assert_eq!(Some(SpecVersion::GitBom), parsed.spec_version());
+        assert_eq!(gitbom, parsed.to_gitbom());
+        for gitoid in gitbom.get_oids() {
+            assert!(parsed.contains(&gitoid));
+        }
+        assert!(!parsed.contains(&GitOid::new(HashAlgorithm::SHA256, b"20")));
+        assert!(!parsed.contains(&GitOid::new_object(
+            HashAlgorithm::SHA256,
+            ObjectType::Commit,
+            b"0"
+        )));
+
+        let omnibor = gitbom
+            .difference(&GitBom::new().add(GitOid::new_object(
+                HashAlgorithm::SHA256,
+                ObjectType::Tree,
+                b"",
+            )))
+            .to_document_version(HashAlgorithm::SHA256, SpecVersion::OmniBor)
+            .unwrap();
+        let parsed = DocumentRef::parse(&omnibor).unwrap();
+        assert!(parsed.contains(&GitOid::new(HashAlgorithm::SHA256, b"7")));
+        assert!(DocumentRef::parse(b"gitoid:blob:sha256\n")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_borrowed_parse_is_strict() {
+        let a = GitOid::new(HashAlgorithm::SHA256, b"a");
+        let b = GitOid::new(HashAlgorithm::SHA256, b"b");
+        let (first, second) = if a < b { (a, b) } else { (b, a) };
+        let unsorted = format!(
+            "gitoid:blob:sha256\nblob {}\nblob {}\n",
+            second.hex_hash(),
+            first.hex_hash()
+        );
+        let upper = format!("gitoid:blob:sha256\nblob {}\n", a.hex_hash().to_uppercase());
+        let truncated = format!("gitoid:blob:sha256\nblob {}", a.hex_hash());
+        let crlf = format!("gitoid:blob:sha256\nblob {}\r\n", a.hex_hash());
+
+        for bad in [unsorted, upper, truncated, crlf] {
+            assert!(DocumentRef::parse(bad.as_bytes()).is_err());
+            assert!(GitBom::from_document(bad.as_bytes()).is_err());
+        }
+    }
+}
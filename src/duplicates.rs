@@ -0,0 +1,112 @@
+use crate::changed::check_unchanged;
+use crate::metrics::count_error;
+use crate::pool::PooledBuffer;
+use crate::scan::{hash_file, with_retries};
+use crate::{GitOid, HashAlgorithm, ScanOptions};
+use std::cell::Cell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs::{File, Metadata};
+use std::hash::Hasher;
+use std::io::{Read, Result as IOResult};
+use std::path::{Path, PathBuf};
+
+/// how much of each file the cheap prefix hash covers
+const PREFIX_LEN: usize = 4096;
+
+/// the chunk size for comparing files
+const COMPARE_LEN: usize = 64 * 1024;
+
+/// Hashes one file of each group of byte-identical files. Files are
+/// grouped by size and a cheap hash of their first 4 KiB; a file whose
+/// group already has a hashed file with the same bytes gets that file's
+/// gitoid after a full comparison, which is much cheaper than hashing
+pub(crate) struct DuplicateFilter {
+    /// by size and prefix hash
+    groups: HashMap<(u64, u64), Vec<Hashed>>,
+}
+
+/// a file that was hashed, and its metadata at the time
+struct Hashed {
+    path: PathBuf,
+    metadata: Metadata,
+    gitoid: GitOid,
+}
+
+impl DuplicateFilter {
+    pub(crate) fn new() -> Self {
+        DuplicateFilter {
+            groups: HashMap::new(),
+        }
+    }
+
+    /// the gitoid of the file, from an identical file if one was hashed
+    pub(crate) fn hash_file(
+        &mut self,
+        hash_algo: HashAlgorithm,
+        path: &Path,
+        metadata: Metadata,
+        options: &ScanOptions,
+        retried: &Cell<usize>,
+    ) -> IOResult<GitOid> {
+        let prefix = with_retries(options, retried, || prefix_hash(path));
+        let key = (metadata.len(), count_error(prefix)?);
+        let group = self.groups.entry(key).or_default();
+        for other in group.iter() {
+            let same = with_retries(options, retried, || {
+                same_contents(path, &other.path, metadata.len())
+            });
+            // a copy that changed since it was hashed doesn't count
+            if matches!(count_error(same), Ok(true))
+                && check_unchanged(&other.path, &other.metadata).is_ok()
+            {
+                check_unchanged(path, &metadata)?;
+                return Ok(other.gitoid);
+            }
+        }
+
+        let gitoid = hash_file(hash_algo, path, metadata.clone(), options, retried)?;
+        group.push(Hashed {
+            path: path.to_path_buf(),
+            metadata,
+            gitoid,
+        });
+        Ok(gitoid)
+    }
+}
+
+/// a cheap, non-cryptographic hash of the start of the file
+fn prefix_hash(path: &Path) -> IOResult<u64> {
+    let mut buf = [0u8; PREFIX_LEN];
+    let mut file = File::open(path)?.take(PREFIX_LEN as u64);
+    let mut len = 0;
+    loop {
+        match file.read(&mut buf[len..])? {
+            0 => break,
+            size => len += size,
+        }
+    }
+    let mut hasher = DefaultHasher::new();
+    hasher.write(&buf[..len]);
+    Ok(hasher.finish())
+}
+
+/// are the first `len` bytes of both files the same?
+fn same_contents(a: &Path, b: &Path, len: u64) -> IOResult<bool> {
+    let (mut a, mut b) = (File::open(a)?, File::open(b)?);
+    let (mut a_buf, mut b_buf) = (
+        PooledBuffer::new(COMPARE_LEN),
+        PooledBuffer::new(COMPARE_LEN),
+    );
+    let mut remaining = len;
+    while remaining > 0 {
+        let want = remaining.min(COMPARE_LEN as u64) as usize;
+        a.read_exact(&mut a_buf[..want])?;
+        b.read_exact(&mut b_buf[..want])?;
+        if a_buf[..want] != b_buf[..want] {
+            return Ok(false);
+        }
+        remaining -= want as u64;
+    }
+    Ok(true)
+}
@@ -0,0 +1,253 @@
+//! Text encodings for digests that the `hex` crate doesn't cover
+
+use crate::{GitOid, HashAlgorithm, ObjectType};
+use std::io::{Error, ErrorKind, Result as IOResult};
+
+/// An [RFC 4648](https://www.rfc-editor.org/rfc/rfc4648) encoding
+struct Encoding {
+    name: &'static str,
+    alphabet: &'static [u8],
+    bits_per_char: u32,
+    /// the number of characters in a padded group
+    group_len: usize,
+    pad: bool,
+    case_insensitive: bool,
+}
+
+const BASE64: Encoding = Encoding {
+    name: "base64",
+    alphabet: b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/",
+    bits_per_char: 6,
+    group_len: 4,
+    pad: true,
+    case_insensitive: false,
+};
+
+/// the URL and filename safe alphabet, without padding
+const BASE64_URL: Encoding = Encoding {
+    name: "base64url",
+    alphabet: b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_",
+    bits_per_char: 6,
+    group_len: 4,
+    pad: false,
+    case_insensitive: false,
+};
+
+/// lower case and without padding, so it's usable as a DNS label or a
+/// file name on a case-insensitive filesystem
+const BASE32: Encoding = Encoding {
+    name: "base32",
+    alphabet: b"abcdefghijklmnopqrstuvwxyz234567",
+    bits_per_char: 5,
+    group_len: 8,
+    pad: false,
+    case_insensitive: true,
+};
+
+impl Encoding {
+    fn encode(&self, data: &[u8]) -> String {
+        let mut ret = String::with_capacity(
+            (data.len() * 8).div_ceil(self.bits_per_char as usize) + self.group_len,
+        );
+        let mask = (1u32 << self.bits_per_char) - 1;
+        let mut acc: u32 = 0;
+        let mut bits = 0;
+        for byte in data {
+            acc = (acc << 8) | *byte as u32;
+            bits += 8;
+            while bits >= self.bits_per_char {
+                bits -= self.bits_per_char;
+                ret.push(self.alphabet[((acc >> bits) & mask) as usize] as char);
+            }
+            acc &= (1 << bits) - 1;
+        }
+        if bits > 0 {
+            ret.push(self.alphabet[((acc << (self.bits_per_char - bits)) & mask) as usize] as char);
+        }
+        if self.pad {
+            while !ret.len().is_multiple_of(self.group_len) {
+                ret.push('=');
+            }
+        }
+        ret
+    }
+
+    /// Decode, accepting the input with or without padding. If padding
+    /// is present it must be correct
+    fn decode(&self, text: &str) -> IOResult<Vec<u8>> {
+        let unpadded = text.trim_end_matches('=');
+        let padding = text.len() - unpadded.len();
+        if padding > 0
+            && (padding >= self.group_len
+                || !text.len().is_multiple_of(self.group_len)
+                || unpadded.len().is_multiple_of(self.group_len))
+        {
+            return Err(bad_encoding(self.name, text));
+        }
+
+        let mut ret = Vec::with_capacity(unpadded.len() * self.bits_per_char as usize / 8);
+        let mut acc: u32 = 0;
+        let mut bits = 0;
+        for c in unpadded.bytes() {
+            let c = if self.case_insensitive {
+                c.to_ascii_lowercase()
+            } else {
+                c
+            };
+            let v = match self.alphabet.iter().position(|&a| a == c) {
+                Some(v) => v as u32,
+                None => return Err(bad_encoding(self.name, text)),
+            };
+            acc = (acc << self.bits_per_char) | v;
+            bits += self.bits_per_char;
+            if bits >= 8 {
+                bits -= 8;
+                ret.push((acc >> bits) as u8);
+                acc &= (1 << bits) - 1;
+            }
+        }
+
+        // a trailing character that doesn't complete a byte isn't valid,
+        // and the unused low bits must be zero for the encoding to be canonical
+        if bits >= self.bits_per_char || acc != 0 {
+            return Err(bad_encoding(self.name, text));
+        }
+        Ok(ret)
+    }
+}
+
+/// Encode with the standard base64 alphabet, with padding
+pub(crate) fn base64_encode(data: &[u8]) -> String {
+    BASE64.encode(data)
+}
+
+/// Decode standard base64. Padding is optional, but if present it must be correct
+pub(crate) fn base64_decode(text: &str) -> IOResult<Vec<u8>> {
+    BASE64.decode(text)
+}
+
+impl GitOid {
+    /// The hash as unpadded lower case base32. A SHA256 hash is 52
+    /// characters, short enough for a DNS label
+    pub fn to_base32(&self) -> String {
+        BASE32.encode(self.hash_value())
+    }
+
+    /// The hash as unpadded base64 with the URL and filename safe alphabet.
+    /// A SHA256 hash is 43 characters
+    pub fn to_base64(&self) -> String {
+        BASE64_URL.encode(self.hash_value())
+    }
+
+    /// Parse a hash made by `to_base32`. Upper case is accepted
+    pub fn from_base32(
+        hash_algo: HashAlgorithm,
+        object_type: ObjectType,
+        text: &str,
+    ) -> IOResult<GitOid> {
+        GitOid::from_raw(hash_algo, object_type, &BASE32.decode(text)?)
+    }
+
+    /// Parse a hash made by `to_base64`
+    pub fn from_base64(
+        hash_algo: HashAlgorithm,
+        object_type: ObjectType,
+        text: &str,
+    ) -> IOResult<GitOid> {
+        GitOid::from_raw(hash_algo, object_type, &BASE64_URL.decode(text)?)
+    }
+}
+
+fn bad_encoding(encoding: &str, text: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("'{}' is not valid {}", text, encoding),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // test vectors from RFC 4648 section 10
+    const VECTORS: [(&str, &str); 7] = [
+        ("", ""),
+        ("f", "Zg=="),
+        ("fo", "Zm8="),
+        ("foo", "Zm9v"),
+        ("foob", "Zm9vYg=="),
+        ("fooba", "Zm9vYmE="),
+        ("foobar", "Zm9vYmFy"),
+    ];
+
+    #[test]
+    fn test_base64_round_trip() {
+        for (plain, encoded) in VECTORS {
+            assert_eq!(encoded, base64_encode(plain.as_bytes()));
+            assert_eq!(plain.as_bytes(), &base64_decode(encoded).unwrap()[..]);
+        }
+    }
+
+    #[test]
+    fn test_base64_rejects_garbage() {
+        assert!(base64_decode("Zm9v!").is_err());
+        assert!(base64_decode("Z").is_err());
+        assert!(base64_decode("Zh==").is_err());
+        assert!(base64_decode("Zg===").is_err());
+        assert!(base64_decode("Zg======").is_err());
+    }
+
+    // test vectors from RFC 4648 section 10, lower cased and unpadded
+    const BASE32_VECTORS: [(&str, &str); 7] = [
+        ("", ""),
+        ("f", "my"),
+        ("fo", "mzxq"),
+        ("foo", "mzxw6"),
+        ("foob", "mzxw6yq"),
+        ("fooba", "mzxw6ytb"),
+        ("foobar", "mzxw6ytboi"),
+    ];
+
+    #[test]
+    fn test_base32_round_trip() {
+        for (plain, encoded) in BASE32_VECTORS {
+            assert_eq!(encoded, BASE32.encode(plain.as_bytes()));
+            assert_eq!(plain.as_bytes(), &BASE32.decode(encoded).unwrap()[..]);
+            assert_eq!(
+                plain.as_bytes(),
+                &BASE32.decode(&encoded.to_uppercase()).unwrap()[..]
+            );
+        }
+        assert_eq!(b"foob".to_vec(), BASE32.decode("MZXW6YQ=").unwrap());
+    }
+
+    #[test]
+    fn test_base32_rejects_garbage() {
+        assert!(BASE32.decode("mzxw1").is_err());
+        assert!(BASE32.decode("m").is_err());
+        assert!(BASE32.decode("mz").is_err());
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_gitoid_base32_and_base64() {
+        let gitoid = GitOid::new(HashAlgorithm::SHA256, b"hello world");
+
+        let base32 = gitoid.to_base32();
+        assert_eq!(52, base32.len());
+        assert_eq!(
+            gitoid,
+            GitOid::from_base32(HashAlgorithm::SHA256, ObjectType::Blob, &base32).unwrap()
+        );
+
+        let base64 = gitoid.to_base64();
+        assert_eq!("_uU6GNMoIGE8BSeqeb5cswFzyCOptEj6SBd2fMhMbwM", base64);
+        assert_eq!(
+            gitoid,
+            GitOid::from_base64(HashAlgorithm::SHA256, ObjectType::Blob, &base64).unwrap()
+        );
+
+        // the digest length has to match the algorithm
+        assert!(GitOid::from_base64(HashAlgorithm::SHA1, ObjectType::Blob, &base64).is_err());
+    }
+}
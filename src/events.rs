@@ -0,0 +1,116 @@
+use crate::GitOid;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Something that happened during a scan or verification, for showing
+/// live status without parsing log text. Paths are relative to the
+/// scanned directory.
+///
+/// Events are passed by reference so the scan can keep its results; to
+/// send them to another thread, e.g. over an `mpsc` channel, copy out the
+/// parts you need
+#[derive(Debug)]
+pub enum ScanEvent {
+    /// The scan of `root` started
+    Started { root: PathBuf },
+    /// A file was hashed, or its gitoid reused from an earlier hard link
+    /// or identical file
+    FileHashed {
+        path: PathBuf,
+        gitoid: GitOid,
+        bytes: u64,
+    },
+    /// A file or directory wasn't scanned
+    Skipped { path: PathBuf, reason: SkipReason },
+    /// A file or directory couldn't be read
+    Error { path: PathBuf, error: Error },
+    /// The scan is over
+    Finished { summary: ScanSummary },
+}
+
+impl ScanEvent {
+    /// The file or directory the event is about, if any
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            ScanEvent::FileHashed { path, .. }
+            | ScanEvent::Skipped { path, .. }
+            | ScanEvent::Error { path, .. } => Some(path),
+            ScanEvent::Started { .. } | ScanEvent::Finished { .. } => None,
+        }
+    }
+}
+
+/// Why a `ScanEvent::Skipped` file or directory wasn't scanned
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SkipReason {
+    /// A `.git` directory, or a hidden file under `skip_hidden`
+    Hidden,
+    /// Left out by the include and exclude patterns
+    Filtered,
+    /// A directory deeper than `max_depth`
+    TooDeep,
+    /// A directory on another filesystem, under `one_file_system`
+    OtherFileSystem,
+    /// A directory already scanned through another symlink
+    AlreadyVisited,
+    /// Neither a regular file nor a directory, e.g. a symlink that isn't
+    /// followed or a socket
+    NotAFile,
+}
+
+/// The totals of a finished scan
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ScanSummary {
+    pub(crate) files: usize,
+    pub(crate) bytes: u64,
+    pub(crate) skipped: usize,
+    pub(crate) errors: usize,
+    pub(crate) retries: usize,
+    pub(crate) elapsed: Duration,
+}
+
+impl ScanSummary {
+    /// How many files were hashed
+    pub fn files(&self) -> usize {
+        self.files
+    }
+
+    /// The total size of the hashed files
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// How many files and directories were skipped
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// How many files and directories couldn't be read
+    pub fn errors(&self) -> usize {
+        self.errors
+    }
+
+    /// How many times hashing a file was retried under the `RetryPolicy`
+    pub fn retries(&self) -> usize {
+        self.retries
+    }
+
+    /// How long the scan took
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// add `event` to the totals
+    pub(crate) fn count(&mut self, event: &ScanEvent) {
+        match event {
+            ScanEvent::FileHashed { bytes, .. } => {
+                self.files += 1;
+                self.bytes += bytes;
+            }
+            ScanEvent::Skipped { .. } => self.skipped += 1,
+            ScanEvent::Error { .. } => self.errors += 1,
+            ScanEvent::Started { .. } | ScanEvent::Finished { .. } => {}
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use crate::context::in_file;
+use crate::{GitOid, ObjectType, RetryPolicy, VerifyingReader};
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{BufReader, Error, ErrorKind, Read, Result as IOResult};
+use std::path::{Path, PathBuf};
+
+/// Somewhere the original bytes of an artifact can be fetched from when
+/// only its `GitOid` is known, e.g. so a verification flow can re-hash
+/// an input listed in a GitBOM document.
+///
+/// Implementations should return an `Err` of kind `NotFound` when they
+/// don't have the artifact, so callers can fall back to another source
+pub trait ArtifactSource {
+    /// Fetch the content of the blob `gitoid`
+    fn fetch(&self, gitoid: &GitOid) -> IOResult<Box<dyn Read>>;
+}
+
+/// An `ArtifactSource` for a local directory of artifacts, each stored
+/// in a file named by the hex hash of its `GitOid`.
+///
+/// The content is checked against the `GitOid` as it's read, so the
+/// reader returns an `Err` at EOF if the file was corrupted or misnamed
+pub struct DirectorySource {
+    root: PathBuf,
+    retry_policy: Option<RetryPolicy>,
+}
+
+impl DirectorySource {
+    /// Fetch artifacts from the directory at `root`
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        DirectorySource {
+            root: root.as_ref().to_path_buf(),
+            retry_policy: None,
+        }
+    }
+
+    /// Retry opening an artifact that fails with a transient error, e.g.
+    /// on a network filesystem, as the policy says. The default is not
+    /// to retry
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// The path the artifact `gitoid` would be stored at
+    pub fn path_for(&self, gitoid: &GitOid) -> PathBuf {
+        self.root.join(gitoid.hex_hash())
+    }
+}
+
+impl ArtifactSource for DirectorySource {
+    fn fetch(&self, gitoid: &GitOid) -> IOResult<Box<dyn Read>> {
+        if gitoid.object_type() != ObjectType::Blob {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Only blobs can be fetched, {} is a {}",
+                    gitoid,
+                    gitoid.object_type()
+                ),
+            ));
+        }
+
+        let path = self.path_for(gitoid);
+        let open = || {
+            let file = File::open(&path)?;
+            let len = file.metadata()?.len() as usize;
+            Ok((file, len))
+        };
+        let (file, len) = match &self.retry_policy {
+            Some(policy) => policy.run(&Cell::new(0), open),
+            None => open(),
+        }
+        .map_err(|error| in_file(error, &path))?;
+        Ok(Box::new(VerifyingReader::new(
+            BufReader::new(file),
+            *gitoid,
+            len,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+    use crate::HashAlgorithm;
+
+    #[test]
+    fn test_directory_fetch() {
+        let source = DirectorySource::new("test/data/artifacts");
+        let mut content = String::new();
+        source
+            .fetch(&GitOid::new(HashAlgorithm::SHA256, b"hello world"))
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+
+        assert_eq!("hello world", content);
+    }
+
+    #[test]
+    fn test_directory_fetch_missing() {
+        let source = DirectorySource::new("test/data/artifacts");
+        let err = source
+            .fetch(&GitOid::new_from_str("missing"))
+            .err()
+            .unwrap();
+
+        assert_eq!(ErrorKind::NotFound, err.kind());
+    }
+
+    #[test]
+    fn test_directory_fetch_corrupt() {
+        let root = TempDir::new("fetch");
+        let gitoid = GitOid::new_from_str("expected");
+        std::fs::write(root.join(gitoid.hex_hash()), "tampered").unwrap();
+
+        let mut content = Vec::new();
+        let res = DirectorySource::new(&root)
+            .fetch(&gitoid)
+            .unwrap()
+            .read_to_end(&mut content);
+
+        assert_eq!(ErrorKind::InvalidData, res.unwrap_err().kind());
+    }
+}
@@ -0,0 +1,135 @@
+//! `#[repr(C)]` types with a stable layout for language bindings, and
+//! conversions to and from the idiomatic Rust types.
+//!
+//! The numbers used for hash algorithms and object types are the same
+//! ones the crate's binary formats use and won't change between crate
+//! versions: SHA1 is 1 and SHA256 is 2, and object types are git's own
+//! numbers (commit 1, tree 2, blob 3, tag 4).
+
+use crate::{HashAlgorithm, ObjectType};
+use std::io::{Error, ErrorKind, Result as IOResult};
+
+/// The largest digest a `GitOid` holds, in bytes
+pub const MAX_DIGEST_LEN: usize = 32;
+
+/// A `GitOid` laid out for C. Only the first `digest_len` bytes of
+/// `digest` are meaningful, the rest are zero
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GitOid {
+    /// 1 for SHA1, 2 for SHA256
+    pub hash_algorithm: u8,
+    /// git's object type number
+    pub object_type: u8,
+    pub digest_len: u8,
+    pub digest: [u8; MAX_DIGEST_LEN],
+}
+
+impl From<&crate::GitOid> for GitOid {
+    fn from(gitoid: &crate::GitOid) -> Self {
+        let mut digest = [0u8; MAX_DIGEST_LEN];
+        digest[..gitoid.hash_len()].copy_from_slice(gitoid.as_bytes());
+        GitOid {
+            hash_algorithm: gitoid.hash_algorithm().tag(),
+            object_type: gitoid.object_type().tag(),
+            digest_len: gitoid.hash_len() as u8,
+            digest,
+        }
+    }
+}
+
+impl TryFrom<&GitOid> for crate::GitOid {
+    type Error = Error;
+
+    /// Will return an `Err` if a tag is unknown or the digest length is
+    /// wrong for the algorithm
+    fn try_from(gitoid: &GitOid) -> IOResult<Self> {
+        let hash_algo = HashAlgorithm::from_tag(gitoid.hash_algorithm).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("Unknown hash algorithm {}", gitoid.hash_algorithm),
+            )
+        })?;
+        let object_type = ObjectType::from_tag(gitoid.object_type).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("Unknown object type {}", gitoid.object_type),
+            )
+        })?;
+        let len = (gitoid.digest_len as usize).min(MAX_DIGEST_LEN);
+
+        crate::GitOid::from_raw(hash_algo, object_type, &gitoid.digest[..len])
+    }
+}
+
+/// The result of an FFI call. `Ok` is zero so callers can test for
+/// failure with `!= 0`
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Status {
+    Ok = 0,
+    /// An argument was invalid, e.g. an unknown algorithm
+    InvalidInput = 1,
+    /// Content or a document was malformed or didn't match its gitoid
+    InvalidData = 2,
+    /// A file or object wasn't found
+    NotFound = 3,
+    /// Any other I/O error
+    Io = 4,
+}
+
+impl From<&Error> for Status {
+    fn from(error: &Error) -> Self {
+        match error.kind() {
+            ErrorKind::InvalidInput => Status::InvalidInput,
+            ErrorKind::InvalidData => Status::InvalidData,
+            ErrorKind::NotFound => Status::NotFound,
+            _ => Status::Io,
+        }
+    }
+}
+
+impl<T> From<&IOResult<T>> for Status {
+    fn from(result: &IOResult<T>) -> Self {
+        match result {
+            Ok(_) => Status::Ok,
+            Err(e) => e.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_round_trip() {
+        let gitoid = crate::GitOid::new(HashAlgorithm::SHA1, b"hello world");
+        let ffi = GitOid::from(&gitoid);
+
+        assert_eq!(1, ffi.hash_algorithm);
+        assert_eq!(3, ffi.object_type);
+        assert_eq!(20, ffi.digest_len);
+        assert_eq!([0u8; 12], ffi.digest[20..]);
+        assert_eq!(gitoid, crate::GitOid::try_from(&ffi).unwrap());
+    }
+
+    #[test]
+    fn test_rejects_bad_tags() {
+        let mut ffi = GitOid::from(&crate::GitOid::new_from_str("hello"));
+        ffi.digest_len = 20;
+        let res = crate::GitOid::try_from(&ffi);
+
+        assert_eq!(Status::InvalidInput, Status::from(&res));
+        ffi.digest_len = 32;
+        ffi.hash_algorithm = 9;
+        assert!(crate::GitOid::try_from(&ffi).is_err());
+    }
+
+    #[test]
+    fn test_layout() {
+        assert_eq!(35, std::mem::size_of::<GitOid>());
+        assert_eq!(4, std::mem::size_of::<Status>());
+    }
+}
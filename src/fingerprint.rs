@@ -0,0 +1,157 @@
+//! Renderings of a `GitOid` meant for humans to compare, e.g. two people
+//! on a call checking they have the same artifact, without reading 64
+//! hex characters aloud.
+
+use crate::GitOid;
+
+const FIELD_WIDTH: usize = 17;
+const FIELD_HEIGHT: usize = 9;
+
+/// How often the bishop visited a square, then the start and end markers
+const SYMBOLS: &[u8] = b" .o+=*BOX@%&#/^SE";
+
+/// Render the hash as a randomart block, using the same "drunken bishop"
+/// walk `ssh-keygen -lv` uses for key fingerprints. The object type is
+/// shown on the top border and the hash algorithm on the bottom one
+pub fn randomart(gitoid: &GitOid) -> String {
+    let mut field = [[0usize; FIELD_HEIGHT]; FIELD_WIDTH];
+    let visit_max = SYMBOLS.len() - 3;
+    let (mut x, mut y) = (FIELD_WIDTH / 2, FIELD_HEIGHT / 2);
+
+    for byte in gitoid.hash_value() {
+        let mut input = *byte;
+        // each byte is four moves, least significant bits first
+        for _ in 0..4 {
+            x = if input & 1 != 0 {
+                (x + 1).min(FIELD_WIDTH - 1)
+            } else {
+                x.saturating_sub(1)
+            };
+            y = if input & 2 != 0 {
+                (y + 1).min(FIELD_HEIGHT - 1)
+            } else {
+                y.saturating_sub(1)
+            };
+            if field[x][y] < visit_max {
+                field[x][y] += 1;
+            }
+            input >>= 2;
+        }
+    }
+
+    field[FIELD_WIDTH / 2][FIELD_HEIGHT / 2] = SYMBOLS.len() - 2;
+    field[x][y] = SYMBOLS.len() - 1;
+
+    let mut ret = border(&gitoid.object_type().to_string());
+    for row in 0..FIELD_HEIGHT {
+        ret.push('|');
+        for column in field.iter() {
+            ret.push(SYMBOLS[column[row]] as char);
+        }
+        ret.push_str("|\n");
+    }
+    ret.push_str(&border(&gitoid.hash_algorithm().to_string()));
+    ret
+}
+
+/// a border line with the title centered in it
+fn border(title: &str) -> String {
+    let title = format!("[{}]", title);
+    let title: String = title.chars().take(FIELD_WIDTH).collect();
+    let left = (FIELD_WIDTH - title.len()) / 2;
+    let right = FIELD_WIDTH - title.len() - left;
+    format!("+{}{}{}+\n", "-".repeat(left), title, "-".repeat(right))
+}
+
+/// Render the hash as words, one per byte. Each byte value has its own
+/// word from a list of 256 short, distinct English nouns
+pub fn words(gitoid: &GitOid) -> Vec<&'static str> {
+    gitoid
+        .hash_value()
+        .iter()
+        .map(|byte| WORDS[*byte as usize])
+        .collect()
+}
+
+const WORDS: [&str; 256] = [
+    "acid", "acorn", "actor", "adobe", "agent", "alarm", "album", "alien", "alley", "amber",
+    "angle", "ankle", "apple", "apron", "arena", "armor", "arrow", "atlas", "attic", "audio",
+    "autumn", "avenue", "axis", "bacon", "badge", "bagel", "baker", "bamboo", "banjo", "barn",
+    "basil", "basket", "beach", "beard", "beaver", "bench", "berry", "bison", "blade", "blanket",
+    "board", "bridge", "bronze", "brush", "bucket", "bugle", "bundle", "butter", "cabin", "cactus",
+    "camel", "candle", "canoe", "canyon", "carbon", "carpet", "castle", "cedar", "cello", "chalk",
+    "cherry", "chess", "cider", "circus", "citrus", "clock", "cloud", "clover", "cobalt", "cocoa",
+    "comet", "copper", "coral", "cotton", "cowboy", "coyote", "crayon", "cricket", "crystal",
+    "dagger", "daisy", "dancer", "delta", "denim", "desert", "diesel", "dinner", "dolphin",
+    "donkey", "dragon", "drum", "eagle", "easel", "echo", "elbow", "ember", "engine", "falcon",
+    "feather", "fence", "ferry", "fiddle", "flame", "flute", "forest", "fossil", "fox", "galaxy",
+    "garden", "garlic", "gecko", "geyser", "ginger", "glacier", "globe", "goblet", "gopher",
+    "granite", "grape", "gravel", "guitar", "hammer", "harbor", "harp", "hazel", "helmet", "heron",
+    "honey", "hornet", "igloo", "indigo", "iris", "island", "ivory", "jacket", "jaguar", "jasmine",
+    "jelly", "jungle", "kayak", "kernel", "kettle", "kiwi", "koala", "ladder", "lagoon", "lantern",
+    "laser", "lemon", "lentil", "lilac", "lime", "linen", "lizard", "lobster", "locket", "lotus",
+    "magnet", "mango", "maple", "marble", "meadow", "melon", "mirror", "mitten", "monkey",
+    "mosaic", "muffin", "napkin", "nectar", "needle", "nickel", "nutmeg", "oasis", "ocean",
+    "olive", "onion", "orbit", "orchid", "otter", "oyster", "paddle", "panda", "parrot", "peach",
+    "pebble", "pencil", "pepper", "piano", "pickle", "pigeon", "pillow", "pine", "planet", "plum",
+    "pocket", "pony", "poppy", "potato", "pumpkin", "puzzle", "quail", "quartz", "quilt", "rabbit",
+    "radar", "radish", "raven", "ribbon", "river", "robin", "rocket", "saddle", "salmon", "sandal",
+    "saturn", "scarf", "shadow", "shell", "silver", "sketch", "sleigh", "spider", "spruce",
+    "squash", "statue", "stone", "sugar", "summit", "sunset", "tablet", "tango", "teapot",
+    "thistle", "tiger", "timber", "toast", "tomato", "trumpet", "tulip", "tunnel", "turtle",
+    "valley", "velvet", "violin", "volcano", "wagon", "walnut", "walrus", "willow", "window",
+    "winter", "wizard", "yogurt", "zebra", "zipper",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HashAlgorithm;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_randomart_shape() {
+        let art = randomart(&GitOid::new(HashAlgorithm::SHA256, b"hello world"));
+        let lines: Vec<&str> = art.lines().collect();
+
+        assert_eq!(FIELD_HEIGHT + 2, lines.len());
+        assert_eq!("+-----[blob]------+", lines[0]);
+        assert_eq!("+----[SHA256]-----+", lines[FIELD_HEIGHT + 1]);
+        for line in &lines {
+            assert_eq!(FIELD_WIDTH + 2, line.len());
+        }
+
+        // the walk always starts in the middle
+        assert_eq!(
+            b'S',
+            lines[1 + FIELD_HEIGHT / 2].as_bytes()[1 + FIELD_WIDTH / 2]
+        );
+    }
+
+    #[test]
+    fn test_randomart_differs() {
+        let hello = randomart(&GitOid::new(HashAlgorithm::SHA256, b"hello world"));
+        let goodbye = randomart(&GitOid::new(HashAlgorithm::SHA256, b"goodbye world"));
+
+        assert_ne!(hello, goodbye);
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_words() {
+        let gitoid = GitOid::new(HashAlgorithm::SHA1, b"hello world");
+        let words = words(&gitoid);
+
+        assert_eq!(20, words.len());
+        // 95d09f2b...
+        assert_eq!(WORDS[0x95], words[0]);
+        assert_eq!(WORDS[0xd0], words[1]);
+    }
+
+    #[test]
+    fn test_word_list_is_unambiguous() {
+        let unique: HashSet<&str> = WORDS.iter().copied().collect();
+
+        assert_eq!(WORDS.len(), unique.len());
+    }
+}
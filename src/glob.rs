@@ -0,0 +1,134 @@
+use std::path::{Component, Path};
+
+/// Does `path`, relative to the scanned directory, match the glob
+/// `pattern`? `*` matches any run of characters within a component, `?`
+/// any one character, `[a-z]` and `[!a-z]` a character in or not in a
+/// set, and a `**` component any number of components. A pattern with
+/// no `/` is matched against the last component, so `*.c` matches C
+/// files at any depth; a trailing `/` only matches directories
+pub(crate) fn glob_matches(pattern: &str, path: &Path, is_dir: bool) -> bool {
+    let pattern = match pattern.strip_suffix('/') {
+        Some(_) if !is_dir => return false,
+        Some(pattern) => pattern,
+        None => pattern,
+    };
+    let names: Vec<String> = path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+
+    if !pattern.contains('/') {
+        return names
+            .last()
+            .is_some_and(|name| matches_component(pattern, name));
+    }
+    let pattern: Vec<&str> = pattern.trim_start_matches('/').split('/').collect();
+    matches_components(&pattern, &names)
+}
+
+fn matches_components(pattern: &[&str], names: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => names.is_empty(),
+        Some((&"**", rest)) => {
+            (0..=names.len()).any(|skip| matches_components(rest, &names[skip..]))
+        }
+        Some((first, rest)) => match names.split_first() {
+            Some((name, names)) => {
+                matches_component(first, name) && matches_components(rest, names)
+            }
+            None => false,
+        },
+    }
+}
+
+fn matches_component(pattern: &str, name: &str) -> bool {
+    let mut chars = pattern.chars();
+    match chars.next() {
+        None => name.is_empty(),
+        Some('*') => {
+            let rest = chars.as_str();
+            name.char_indices()
+                .map(|(i, _)| i)
+                .chain([name.len()])
+                .any(|skip| matches_component(rest, &name[skip..]))
+        }
+        Some('[') => match class_end(chars.as_str()) {
+            Some(end) => {
+                let (class, rest) = chars.as_str().split_at(end);
+                let mut name_chars = name.chars();
+                match name_chars.next() {
+                    Some(c) if in_class(class, c) => {
+                        matches_component(&rest[1..], name_chars.as_str())
+                    }
+                    _ => false,
+                }
+            }
+            // an unclosed `[` is just a character
+            None => matches_literal('[', chars.as_str(), name),
+        },
+        Some('?') => {
+            let mut name_chars = name.chars();
+            name_chars.next().is_some() && matches_component(chars.as_str(), name_chars.as_str())
+        }
+        Some(c) => matches_literal(c, chars.as_str(), name),
+    }
+}
+
+fn matches_literal(c: char, rest: &str, name: &str) -> bool {
+    name.strip_prefix(c)
+        .is_some_and(|name| matches_component(rest, name))
+}
+
+/// the index of the `]` closing a class, skipping a leading `!`
+fn class_end(class: &str) -> Option<usize> {
+    let start = usize::from(class.starts_with('!'));
+    class[start..].find(']').map(|end| start + end)
+}
+
+fn in_class(class: &str, c: char) -> bool {
+    let (negated, class) = match class.strip_prefix('!') {
+        Some(class) => (true, class),
+        None => (false, class),
+    };
+    let chars: Vec<char> = class.chars().collect();
+    let mut found = false;
+    let mut i = 0;
+    while i < chars.len() {
+        if i + 2 < chars.len() && chars[i + 1] == '-' {
+            found |= (chars[i]..=chars[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            found |= chars[i] == c;
+            i += 1;
+        }
+    }
+    found != negated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_matches() {
+        let file = |pattern: &str, path: &str| glob_matches(pattern, Path::new(path), false);
+
+        assert!(file("*.c", "vendor/zlib/inflate.c"));
+        assert!(!file("*.c", "vendor/zlib/inflate.h"));
+        assert!(file("*.[ch]", "inflate.h"));
+        assert!(file("[!a-m]*", "zlib.h"));
+        assert!(!file("[!a-m]*", "inflate.h"));
+        assert!(file("vendor/*/inflate.?", "vendor/zlib/inflate.c"));
+        assert!(!file("vendor/*.c", "vendor/zlib/inflate.c"));
+        assert!(file("vendor/**/*.c", "vendor/zlib/contrib/x.c"));
+        assert!(file("**/inflate.c", "inflate.c"));
+        assert!(file("a[b", "a[b"));
+
+        assert!(glob_matches("target/", Path::new("target"), true));
+        assert!(!file("target/", "target"));
+    }
+}
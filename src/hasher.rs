@@ -0,0 +1,483 @@
+use crate::{GitOid, HashAlgorithm, ObjectType, NUM_HASH_BYTES};
+use sha2::digest::generic_array::GenericArray;
+use std::io::{Error, ErrorKind, Result as IOResult};
+
+/// SHA1 and SHA256 both work on 64 byte blocks
+const BLOCK_LEN: usize = 64;
+
+#[cfg(feature = "sha1")]
+const SHA1_INITIAL_STATE: [u32; 8] = [
+    0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0, 0, 0, 0,
+];
+
+const SHA256_INITIAL_STATE: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// Incrementally computes a `GitOid` from bytes fed to it in pieces.
+///
+/// The git object prefix includes the number of bytes being hashed, so
+/// the length has to be known up front. `finalize` returns an `Err` if
+/// the number of bytes fed in differs from the expected length.
+///
+/// The hasher drives the digest crates' compression functions directly
+/// rather than going through `DynDigest`, which is what lets it `suspend`
+/// to a serializable `HasherState` and `resume` later, possibly in
+/// another process.
+#[derive(Clone)]
+pub struct GitOidHasher {
+    hash_algorithm: HashAlgorithm,
+    object_type: ObjectType,
+    expected_length: u64,
+    amount_hashed: u64,
+    /// the chaining value. SHA1 only uses the first five words
+    state: [u32; 8],
+    /// input that doesn't fill a block yet
+    block: [u8; BLOCK_LEN],
+    block_len: usize,
+    /// everything fed to the compression function, prefix included
+    total_len: u64,
+}
+
+impl GitOidHasher {
+    /// Create a hasher for `expected_length` bytes of blob content
+    pub fn new(hash_algo: HashAlgorithm, expected_length: usize) -> Self {
+        GitOidHasher::new_object(hash_algo, ObjectType::Blob, expected_length)
+    }
+
+    /// Create a hasher for `expected_length` bytes of a git object of
+    /// the given type
+    pub fn new_object(
+        hash_algo: HashAlgorithm,
+        object_type: ObjectType,
+        expected_length: usize,
+    ) -> Self {
+        let state = match hash_algo {
+            #[cfg(feature = "sha1")]
+            HashAlgorithm::SHA1 => SHA1_INITIAL_STATE,
+            HashAlgorithm::SHA256 => SHA256_INITIAL_STATE,
+        };
+        let mut ret = GitOidHasher {
+            hash_algorithm: hash_algo,
+            object_type,
+            expected_length: expected_length as u64,
+            amount_hashed: 0,
+            state,
+            block: [0u8; BLOCK_LEN],
+            block_len: 0,
+            total_len: 0,
+        };
+        ret.absorb(object_prefix(
+            object_type,
+            expected_length as u64,
+            &mut [0; PREFIX_LEN],
+        ));
+        ret
+    }
+
+    /// Feed more content to the hasher
+    pub fn update(&mut self, data: &[u8]) {
+        // saturate rather than wrap; a saturated count never matches in
+        // `finalize`
+        self.amount_hashed = self.amount_hashed.saturating_add(data.len() as u64);
+        self.absorb(data);
+    }
+
+    /// The number of content bytes hashed so far
+    pub fn amount_hashed(&self) -> u64 {
+        self.amount_hashed
+    }
+
+    /// The number of content bytes the hasher was created for
+    pub fn expected_length(&self) -> u64 {
+        self.expected_length
+    }
+
+    /// The hash algorithm this hasher uses
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+
+    /// Stop hashing and capture everything needed to carry on later
+    pub fn suspend(self) -> HasherState {
+        HasherState { hasher: self }
+    }
+
+    /// Carry on hashing from a suspended state
+    pub fn resume(state: HasherState) -> Self {
+        state.hasher
+    }
+
+    /// Finish hashing and return the `GitOid`. Will return an `Err`
+    /// if the amount of content hashed isn't the expected length, or if
+    /// the object is too long for the algorithm (2^61 bytes or more)
+    pub fn finalize(self) -> IOResult<GitOid> {
+        if self.amount_hashed != self.expected_length {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Expected length {} actual length {}",
+                    self.expected_length, self.amount_hashed
+                ),
+            ));
+        }
+
+        if self.total_len.checked_mul(8).is_none() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} object too long to hash", self.object_type),
+            ));
+        }
+
+        Ok(self.finish().unwrap()) // both ways it can fail were checked
+    }
+
+    /// Like `finalize`, but without allocating an error, for code running
+    /// with no allocator (e.g. firmware hashing itself at boot). `None`
+    /// if the amount of content hashed isn't the expected length or the
+    /// object is too long for the algorithm.
+    ///
+    /// Creating, feeding and finishing a hasher never allocates
+    pub fn finish(mut self) -> Option<GitOid> {
+        if self.amount_hashed != self.expected_length {
+            return None;
+        }
+
+        // Merkle–Damgård padding: a 1 bit, zeros, then the bit length
+        let bit_len = self.total_len.checked_mul(8)?;
+        let mut padding = [0u8; BLOCK_LEN];
+        padding[0] = 0x80;
+        let padding_len = if self.block_len < 56 {
+            56 - self.block_len
+        } else {
+            120 - self.block_len
+        };
+        self.absorb(&padding[..padding_len]);
+        self.absorb(&bit_len.to_be_bytes());
+
+        let len = self.hash_algorithm.digest_len();
+        let mut value = [0u8; NUM_HASH_BYTES];
+        for (i, word) in self.state[..len / 4].iter().enumerate() {
+            value[i * 4..i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+        }
+        Some(GitOid {
+            hash_algorithm: self.hash_algorithm,
+            object_type: self.object_type,
+            len,
+            value,
+        })
+    }
+
+    /// buffer `data` into blocks and compress each full one
+    fn absorb(&mut self, mut data: &[u8]) {
+        // saturating, so an overflowing length fails `finalize`
+        self.total_len = self.total_len.saturating_add(data.len() as u64);
+
+        if self.block_len > 0 {
+            let take = data.len().min(BLOCK_LEN - self.block_len);
+            self.block[self.block_len..self.block_len + take].copy_from_slice(&data[..take]);
+            self.block_len += take;
+            data = &data[take..];
+            if self.block_len < BLOCK_LEN {
+                return;
+            }
+            let block = self.block;
+            self.compress(&block);
+            self.block_len = 0;
+        }
+
+        let mut blocks = data.chunks_exact(BLOCK_LEN);
+        for block in &mut blocks {
+            self.compress(block);
+        }
+
+        let rest = blocks.remainder();
+        self.block[..rest.len()].copy_from_slice(rest);
+        self.block_len = rest.len();
+    }
+
+    fn compress(&mut self, block: &[u8]) {
+        let blocks = [*GenericArray::from_slice(block)];
+        match self.hash_algorithm {
+            #[cfg(feature = "sha1")]
+            HashAlgorithm::SHA1 => {
+                let state: &mut [u32; 5] = (&mut self.state[..5]).try_into().unwrap();
+                sha1::compress(state, &blocks)
+            }
+            HashAlgorithm::SHA256 => sha2::compress256(&mut self.state, &blocks),
+        }
+    }
+}
+
+/// room for the longest prefix, `commit <20 digits>\0`
+const PREFIX_LEN: usize = 32;
+
+/// the `<type> <length>\0` git object prefix, formatted into `buf`
+fn object_prefix(object_type: ObjectType, len: u64, buf: &mut [u8; PREFIX_LEN]) -> &[u8] {
+    let name = object_type.document_name().as_bytes();
+    buf[..name.len()].copy_from_slice(name);
+    buf[name.len()] = b' ';
+
+    let mut digits = [0u8; 20];
+    let mut first = digits.len();
+    let mut rest = len;
+    loop {
+        first -= 1;
+        digits[first] = b'0' + (rest % 10) as u8;
+        rest /= 10;
+        if rest == 0 {
+            break;
+        }
+    }
+    let start = name.len() + 1;
+    let end = start + digits.len() - first;
+    buf[start..end].copy_from_slice(&digits[first..]);
+    buf[end] = 0;
+    &buf[..end + 1]
+}
+
+/// The state of a suspended `GitOidHasher`. It can be written out with
+/// `to_bytes` and read back with `from_bytes` so a long-running hash of a
+/// huge artifact can survive a restart or move to another worker.
+///
+/// The serialized form is a fixed 128 bytes and doesn't contain any of
+/// the content beyond the partial last block.
+#[derive(Clone)]
+pub struct HasherState {
+    hasher: GitOidHasher,
+}
+
+/// magic bytes and version of the serialized form
+const STATE_MAGIC: &[u8; 4] = b"GOHS";
+const STATE_VERSION: u8 = 1;
+const STATE_LEN: usize = 128;
+
+impl HasherState {
+    /// Serialize the state
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let h = &self.hasher;
+        let mut ret = Vec::with_capacity(STATE_LEN);
+        ret.extend_from_slice(STATE_MAGIC);
+        ret.push(STATE_VERSION);
+        ret.push(h.hash_algorithm.tag());
+        ret.push(h.object_type.tag());
+        ret.extend_from_slice(&h.expected_length.to_be_bytes());
+        ret.extend_from_slice(&h.amount_hashed.to_be_bytes());
+        ret.extend_from_slice(&h.total_len.to_be_bytes());
+        for word in h.state {
+            ret.extend_from_slice(&word.to_be_bytes());
+        }
+        ret.push(h.block_len as u8);
+        ret.extend_from_slice(&h.block);
+        ret
+    }
+
+    /// Deserialize a state written by `to_bytes`. Will return an `Err`
+    /// if the bytes aren't a valid state
+    pub fn from_bytes(bytes: &[u8]) -> IOResult<HasherState> {
+        if bytes.len() != STATE_LEN || &bytes[..4] != STATE_MAGIC {
+            return Err(bad_state("not a hasher state"));
+        }
+        if bytes[4] != STATE_VERSION {
+            return Err(bad_state("unsupported version"));
+        }
+        let hash_algorithm =
+            HashAlgorithm::from_tag(bytes[5]).ok_or_else(|| bad_state("unknown hash algorithm"))?;
+        let object_type =
+            ObjectType::from_tag(bytes[6]).ok_or_else(|| bad_state("unknown object type"))?;
+
+        let read_u64 = |at: usize| u64::from_be_bytes(bytes[at..at + 8].try_into().unwrap());
+        let expected_length = read_u64(7);
+        let amount_hashed = read_u64(15);
+        let total_len = read_u64(23);
+        if amount_hashed > total_len {
+            return Err(bad_state("amount hashed larger than total length"));
+        }
+
+        let mut state = [0u32; 8];
+        for (i, word) in state.iter_mut().enumerate() {
+            let at = 31 + i * 4;
+            *word = u32::from_be_bytes(bytes[at..at + 4].try_into().unwrap());
+        }
+
+        let block_len = bytes[63] as usize;
+        if block_len >= BLOCK_LEN || total_len % BLOCK_LEN as u64 != block_len as u64 {
+            return Err(bad_state("inconsistent block length"));
+        }
+        let mut block = [0u8; BLOCK_LEN];
+        block.copy_from_slice(&bytes[64..]);
+
+        Ok(HasherState {
+            hasher: GitOidHasher {
+                hash_algorithm,
+                object_type,
+                expected_length,
+                amount_hashed,
+                state,
+                block,
+                block_len,
+                total_len,
+            },
+        })
+    }
+}
+
+fn bad_state(msg: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("Invalid hasher state: {}", msg),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH_ALGORITHMS: &[HashAlgorithm] = &[
+        #[cfg(feature = "sha1")]
+        HashAlgorithm::SHA1,
+        HashAlgorithm::SHA256,
+    ];
+
+    #[test]
+    fn test_incremental_matches_one_shot() {
+        let mut hasher = GitOidHasher::new(HashAlgorithm::SHA256, 11);
+        hasher.update(b"hello");
+        hasher.update(b" ");
+        hasher.update(b"world");
+
+        assert_eq!(
+            GitOid::new(HashAlgorithm::SHA256, b"hello world"),
+            hasher.finalize().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_finish_without_allocating() {
+        let mut hasher = GitOidHasher::new(HashAlgorithm::SHA256, 11);
+        hasher.update(b"hello world");
+        assert_eq!(
+            Some(GitOid::new(HashAlgorithm::SHA256, b"hello world")),
+            hasher.finish()
+        );
+        assert_eq!(None, GitOidHasher::new(HashAlgorithm::SHA256, 1).finish());
+
+        assert_eq!(
+            b"commit 18446744073709551615\0",
+            object_prefix(ObjectType::Commit, u64::MAX, &mut [0; PREFIX_LEN])
+        );
+        assert_eq!(
+            b"blob 0\0",
+            object_prefix(ObjectType::Blob, 0, &mut [0; PREFIX_LEN])
+        );
+    }
+
+    #[test]
+    fn test_matches_digest_crates_across_block_boundaries() {
+        let content: Vec<u8> = (0..300u32).map(|i| (i * 7) as u8).collect();
+        for &hash_algo in HASH_ALGORITHMS {
+            for len in 0..content.len() {
+                let mut hasher = GitOidHasher::new(hash_algo, len);
+                // feed in uneven pieces
+                for piece in content[..len].chunks(13) {
+                    hasher.update(piece);
+                }
+                // the digest crates' own padding, not the hasher's
+                let mut digest = hash_algo.create_digest();
+                digest.update(format!("blob {}\0", len).as_bytes());
+                digest.update(&content[..len]);
+                assert_eq!(
+                    &digest.finalize()[..],
+                    hasher.finalize().unwrap().hash_value()
+                );
+            }
+        }
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_object_type_is_part_of_the_hash() {
+        let blob = GitOid::new_object(HashAlgorithm::SHA1, ObjectType::Blob, b"");
+        let tree = GitOid::new_object(HashAlgorithm::SHA1, ObjectType::Tree, b"");
+
+        assert_eq!("e69de29bb2d1d6434b8b29ae775ad8c2e48c5391", blob.hex_hash());
+        assert_eq!("4b825dc642cb6eb9a060e54bf8d69288fbee4904", tree.hex_hash());
+        assert_eq!(ObjectType::Tree, tree.object_type());
+    }
+
+    #[test]
+    fn test_wrong_length() {
+        let mut hasher = GitOidHasher::new(HashAlgorithm::SHA256, 12);
+        hasher.update(b"hello world");
+
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            hasher.finalize().unwrap_err().kind()
+        );
+    }
+
+    /// a SHA256 empty blob hasher whose counters have been moved to the
+    /// given lengths, keeping the partial block consistent
+    fn hasher_at(amount_hashed: u64, total_len: u64) -> GitOidHasher {
+        let mut bytes = GitOidHasher::new(HashAlgorithm::SHA256, 0)
+            .suspend()
+            .to_bytes();
+        bytes[7..15].copy_from_slice(&amount_hashed.to_be_bytes());
+        bytes[15..23].copy_from_slice(&amount_hashed.to_be_bytes());
+        bytes[23..31].copy_from_slice(&total_len.to_be_bytes());
+        GitOidHasher::resume(HasherState::from_bytes(&bytes).unwrap())
+    }
+
+    #[test]
+    fn test_length_overflow_is_an_error() {
+        // "blob 0\0" leaves 7 bytes in the partial block
+        let largest = (u64::MAX / 8) & !63 | 7;
+        assert!(hasher_at(largest - 7, largest).finalize().is_ok());
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            hasher_at(largest + 57, largest + 64)
+                .finalize()
+                .unwrap_err()
+                .kind()
+        );
+
+        // the content count saturates instead of wrapping back to a match
+        let mut hasher = hasher_at(u64::MAX - 63, u64::MAX - 56);
+        hasher.update(&[0u8; 100]);
+        assert_eq!(u64::MAX, hasher.amount_hashed());
+        assert!(hasher.finalize().is_err());
+    }
+
+    #[test]
+    fn test_suspend_and_resume() {
+        let content = vec![42u8; 1000];
+        for &hash_algo in HASH_ALGORITHMS {
+            let mut hasher = GitOidHasher::new(hash_algo, content.len());
+            hasher.update(&content[..333]);
+
+            let bytes = hasher.suspend().to_bytes();
+            assert_eq!(STATE_LEN, bytes.len());
+
+            let mut hasher = GitOidHasher::resume(HasherState::from_bytes(&bytes).unwrap());
+            assert_eq!(333, hasher.amount_hashed());
+            hasher.update(&content[333..]);
+
+            assert_eq!(GitOid::new(hash_algo, &content), hasher.finalize().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_bad_state() {
+        let mut bytes = GitOidHasher::new(HashAlgorithm::SHA256, 5)
+            .suspend()
+            .to_bytes();
+
+        assert!(HasherState::from_bytes(&bytes[..100]).is_err());
+
+        bytes[63] = 3; // doesn't agree with the total length
+        assert!(HasherState::from_bytes(&bytes).is_err());
+
+        bytes[0] = b'X';
+        assert!(HasherState::from_bytes(&bytes).is_err());
+    }
+}
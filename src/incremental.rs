@@ -0,0 +1,429 @@
+use crate::context::in_file;
+use crate::document::bad_document;
+use crate::scan::{hash_file, path_order};
+use crate::{
+    DirectoryScan, GitBom, GitBomDelta, GitOid, HashAlgorithm, ScanError, ScanEvent, ScanOptions,
+};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fs::{self, Metadata};
+use std::io::Result as IOResult;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// What a `ScanCache` remembers about a file
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct CachedFile {
+    len: u64,
+    /// since the epoch
+    modified: Duration,
+    gitoid: GitOid,
+}
+
+impl CachedFile {
+    fn new(metadata: &Metadata, gitoid: GitOid) -> Option<Self> {
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(CachedFile {
+            len: metadata.len(),
+            modified,
+            gitoid,
+        })
+    }
+
+    fn matches(&self, metadata: &Metadata, hash_algo: HashAlgorithm) -> bool {
+        let modified = metadata.modified().ok();
+        self.gitoid.hash_algorithm() == hash_algo
+            && self.len == metadata.len()
+            && modified == Some(UNIX_EPOCH + self.modified)
+    }
+}
+
+/// The size, modification time and gitoid of each file from a previous
+/// scan, so `GitBom::regenerate` only rehashes files whose size or
+/// modification time changed.
+///
+/// Serialized as text, one line per file sorted by path:
+///
+/// ```text
+/// gitoid:scan-cache
+/// <len> <seconds>.<nanoseconds> <gitoid URI> <path>
+/// ```
+///
+/// Paths that aren't UTF-8 or contain a line break aren't written; those
+/// files are rehashed on the next run. The checkpoints saved by
+/// `ScanOptions::checkpoint` are in the same format
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct ScanCache {
+    files: HashMap<PathBuf, CachedFile>,
+}
+
+impl ScanCache {
+    /// Create an empty cache, which makes `regenerate` hash everything
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of files in the cache
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Is the cache empty?
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// The gitoids of the cached files, e.g. the partial `GitBom` of a
+    /// scan that was interrupted after its last checkpoint
+    pub fn gitbom(&self) -> GitBom {
+        self.files.values().map(|file| file.gitoid).collect()
+    }
+
+    /// the gitoid of the file at `relative`, if it's cached and its size
+    /// and modification time still match
+    pub(crate) fn cached(
+        &self,
+        relative: &Path,
+        metadata: &Metadata,
+        hash_algo: HashAlgorithm,
+    ) -> Option<GitOid> {
+        self.files
+            .get(relative)
+            .filter(|file| file.matches(metadata, hash_algo))
+            .map(|file| file.gitoid)
+    }
+
+    /// remember the file's gitoid, unless the modification time can't be
+    /// read
+    fn insert(&mut self, relative: &Path, metadata: &Metadata, gitoid: GitOid) {
+        if let Some(file) = CachedFile::new(metadata, gitoid) {
+            self.files.insert(relative.to_path_buf(), file);
+        }
+    }
+
+    /// Serialize the cache
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut lines: Vec<String> = self
+            .files
+            .iter()
+            .filter_map(|(path, file)| {
+                let path = path.to_str().filter(|path| !path.contains(['\n', '\r']))?;
+                Some(format!(
+                    "{} {}.{:09} {} {}\n",
+                    file.len,
+                    file.modified.as_secs(),
+                    file.modified.subsec_nanos(),
+                    file.gitoid.uri(),
+                    path
+                ))
+            })
+            .collect();
+        lines.sort_unstable_by(|a, b| a.splitn(4, ' ').nth(3).cmp(&b.splitn(4, ' ').nth(3)));
+        let mut ret = "gitoid:scan-cache\n".to_string();
+        for line in lines {
+            ret.push_str(&line);
+        }
+        ret.into_bytes()
+    }
+
+    /// Parse a cache written by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> IOResult<Self> {
+        let text = std::str::from_utf8(bytes).map_err(|_| bad_document("not UTF-8"))?;
+        let mut lines = text.lines();
+        if lines.next() != Some("gitoid:scan-cache") {
+            return Err(bad_document("missing gitoid:scan-cache header"));
+        }
+
+        let mut ret = ScanCache::new();
+        for line in lines {
+            let bad_line = || bad_document(&format!("bad scan cache line {:?}", line));
+            let mut parts = line.splitn(4, ' ');
+            let (len, modified, uri, path) =
+                match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                    (Some(len), Some(modified), Some(uri), Some(path)) => {
+                        (len, modified, uri, path)
+                    }
+                    _ => return Err(bad_line()),
+                };
+            let (secs, nanos) = modified.split_once('.').ok_or_else(bad_line)?;
+            let nanos: u32 = nanos.parse().map_err(|_| bad_line())?;
+            // `Duration::new` panics if carrying the nanoseconds overflows
+            if nanos >= 1_000_000_000 {
+                return Err(bad_line());
+            }
+            let modified = Duration::new(secs.parse().map_err(|_| bad_line())?, nanos);
+            ret.files.insert(
+                PathBuf::from(path),
+                CachedFile {
+                    len: len.parse().map_err(|_| bad_line())?,
+                    modified,
+                    gitoid: GitOid::from_uri(uri).map_err(|_| bad_line())?,
+                },
+            );
+        }
+        Ok(ret)
+    }
+}
+
+/// The files hashed so far in a scan with `ScanOptions::checkpoint`,
+/// saved every `every` files
+pub(crate) struct Checkpoint<'a> {
+    path: &'a Path,
+    every: usize,
+    cache: ScanCache,
+    unsaved: usize,
+}
+
+impl<'a> Checkpoint<'a> {
+    pub(crate) fn new(path: &'a Path, every: usize) -> Self {
+        Checkpoint {
+            path,
+            every,
+            cache: ScanCache::new(),
+            unsaved: 0,
+        }
+    }
+
+    /// add a hashed file, saving if it's time to
+    pub(crate) fn record(
+        &mut self,
+        relative: &Path,
+        metadata: &Metadata,
+        gitoid: GitOid,
+    ) -> IOResult<()> {
+        self.cache.insert(relative, metadata, gitoid);
+        self.unsaved += 1;
+        if self.unsaved >= self.every {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// write the checkpoint next to its final path and rename it into
+    /// place, so an interruption never leaves half a checkpoint
+    pub(crate) fn save(&mut self) -> IOResult<()> {
+        let mut temp = self.path.as_os_str().to_owned();
+        temp.push(".tmp");
+        let write =
+            fs::write(&temp, self.cache.to_bytes()).and_then(|_| fs::rename(&temp, self.path));
+        self.unsaved = 0;
+        write.map_err(|error| in_file(error, self.path))
+    }
+}
+
+/// The result of `GitBom::regenerate`
+#[derive(Debug)]
+pub struct Regeneration {
+    gitbom: GitBom,
+    document: Vec<u8>,
+    delta: GitBomDelta,
+    cache: ScanCache,
+    errors: Vec<ScanError>,
+    rehashed: usize,
+    retries: usize,
+}
+
+impl Regeneration {
+    /// The new `GitBom`
+    pub fn gitbom(&self) -> &GitBom {
+        &self.gitbom
+    }
+
+    /// The new `GitBom`'s document
+    pub fn document(&self) -> &[u8] {
+        &self.document
+    }
+
+    /// What changed since the previous `GitBom`
+    pub fn delta(&self) -> &GitBomDelta {
+        &self.delta
+    }
+
+    /// The cache to pass to the next run
+    pub fn cache(&self) -> &ScanCache {
+        &self.cache
+    }
+
+    /// Take the cache for the next run, dropping the rest
+    pub fn into_cache(self) -> ScanCache {
+        self.cache
+    }
+
+    /// The files that couldn't be hashed
+    pub fn errors(&self) -> &[ScanError] {
+        &self.errors
+    }
+
+    /// How many files had to be hashed because the cache didn't have
+    /// them or they'd changed
+    pub fn rehashed(&self) -> usize {
+        self.rehashed
+    }
+
+    /// How many times hashing a file was retried under the
+    /// `RetryPolicy`
+    pub fn retries(&self) -> usize {
+        self.retries
+    }
+}
+
+impl GitBom {
+    /// Rescan the directory at `root`, taking the gitoid of each file
+    /// whose size and modification time match `cache` from the cache and
+    /// hashing only the rest, and compare the result with `self`, the
+    /// `GitBom` from the previous run.
+    ///
+    /// A file modified without changing its size, within the timestamp
+    /// resolution of the filesystem, looks unchanged. Will return an
+    /// `Err` only if `root` itself can't be read
+    pub fn regenerate<P: AsRef<Path>>(
+        &self,
+        cache: &ScanCache,
+        root: P,
+        hash_algo: HashAlgorithm,
+        options: &ScanOptions,
+    ) -> IOResult<Regeneration> {
+        let mut new_cache = ScanCache::new();
+        let mut gitoids = Vec::new();
+        let mut errors = Vec::new();
+        let mut rehashed = 0;
+        let retried = Cell::new(0);
+
+        DirectoryScan::walk(
+            root.as_ref(),
+            options,
+            |relative, path, metadata| {
+                let gitoid = match cache.cached(relative, &metadata, hash_algo) {
+                    Some(gitoid) => gitoid,
+                    None => {
+                        rehashed += 1;
+                        hash_file(hash_algo, path, metadata.clone(), options, &retried)?
+                    }
+                };
+                new_cache.insert(relative, &metadata, gitoid);
+                Ok(gitoid)
+            },
+            |event| match event {
+                ScanEvent::FileHashed { gitoid, .. } => gitoids.push(gitoid),
+                ScanEvent::Error { path, error } => errors.push(ScanError::new(path, error)),
+                _ => {}
+            },
+        )?;
+
+        let gitbom = GitBom::new_from_iterator(gitoids);
+        errors.sort_by_cached_key(|error| path_order(error.path()));
+        Ok(Regeneration {
+            document: gitbom.to_document(hash_algo)?,
+            delta: GitBomDelta::between(self, &gitbom, hash_algo)?,
+            gitbom,
+            cache: new_cache,
+            errors,
+            rehashed,
+            retries: retried.get(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+    use crate::ErrorContext;
+    use std::fs;
+
+    #[test]
+    fn test_regenerate_only_rehashes_changes() {
+        let root = TempDir::new("regenerate");
+        fs::write(root.join("a.txt"), "a").unwrap();
+        fs::write(root.join("b.txt"), "b").unwrap();
+        let options = ScanOptions::new();
+        let sha256 = |content: &str| GitOid::new(HashAlgorithm::SHA256, content.as_bytes());
+
+        let first = GitBom::new()
+            .regenerate(&ScanCache::new(), &root, HashAlgorithm::SHA256, &options)
+            .unwrap();
+        let cache = ScanCache::from_bytes(&first.cache().to_bytes()).unwrap();
+        let second = first
+            .gitbom()
+            .regenerate(&cache, &root, HashAlgorithm::SHA256, &options)
+            .unwrap();
+        fs::write(root.join("b.txt"), "longer b").unwrap();
+        let third =
+            second
+                .gitbom()
+                .regenerate(second.cache(), &root, HashAlgorithm::SHA256, &options);
+
+        assert!(ScanCache::from_bytes(
+            format!(
+                "gitoid:scan-cache\n1 18446744073709551615.4000000000 {} a.txt\n",
+                sha256("a").uri()
+            )
+            .as_bytes()
+        )
+        .is_err());
+        assert_eq!(2, first.rehashed());
+        assert_eq!(&cache, first.cache());
+        assert_eq!(0, second.rehashed());
+        assert!(second.delta().added().is_empty());
+
+        let third = third.unwrap();
+        assert_eq!(1, third.rehashed());
+        assert_eq!(&[sha256("longer b")], third.delta().added());
+        assert_eq!(&[sha256("b")], third.delta().removed());
+        assert_eq!(
+            third.document(),
+            &third.gitbom().to_document(HashAlgorithm::SHA256).unwrap()[..]
+        );
+    }
+
+    #[test]
+    fn test_checkpoint_errors_are_separate() {
+        let root = TempDir::new("checkpoint-error");
+        let options = ScanOptions::new().checkpoint(root.join("missing/checkpoint"), 1);
+        let scan = DirectoryScan::new("test/data/tree", HashAlgorithm::SHA256, &options).unwrap();
+
+        assert!(scan.errors().is_empty());
+        assert_eq!(3, scan.files().len());
+        let error = scan.checkpoint_error().unwrap();
+        assert_eq!(
+            Some(root.join("missing/checkpoint").as_path()),
+            ErrorContext::from_io_error(error).unwrap().path()
+        );
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        let root = TempDir::new("checkpoint");
+        fs::create_dir_all(root.join("files")).unwrap();
+        fs::write(root.join("files/a.txt"), "a").unwrap();
+        fs::write(root.join("files/b.txt"), "b").unwrap();
+        let saved = root.join("checkpoint");
+        let options = ScanOptions::new().checkpoint(&saved, 1);
+        let sha256 = |content: &str| GitOid::new(HashAlgorithm::SHA256, content.as_bytes());
+
+        let scan = DirectoryScan::new(root.join("files"), HashAlgorithm::SHA256, &options);
+        let checkpoint = fs::read(&saved).unwrap();
+        // a checkpoint claiming a.txt hashed to something else shows
+        // which files weren't hashed again
+        let forged = String::from_utf8(checkpoint.clone())
+            .unwrap()
+            .replace(&sha256("a").uri(), &sha256("forged").uri());
+        fs::write(root.join("files/b.txt"), "changed b").unwrap();
+        let resumed = DirectoryScan::resume(
+            root.join("files"),
+            HashAlgorithm::SHA256,
+            &ScanOptions::new(),
+            &ScanCache::from_bytes(forged.as_bytes()).unwrap(),
+        );
+
+        let scan = scan.unwrap();
+        assert!(scan.errors().is_empty());
+        let checkpoint = ScanCache::from_bytes(&checkpoint).unwrap();
+        assert_eq!(2, checkpoint.len());
+        assert_eq!(scan.gitbom(), checkpoint.gitbom());
+        assert_eq!(
+            GitBom::new().add(sha256("forged")).add(sha256("changed b")),
+            resumed.unwrap().gitbom()
+        );
+    }
+}
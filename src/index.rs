@@ -0,0 +1,257 @@
+use crate::context::in_file;
+use crate::{GitOid, HashAlgorithm, ObjectType};
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{BufReader, Error, ErrorKind, Read, Result as IOResult, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// magic bytes and version for the index file
+const INDEX_MAGIC: &[u8; 4] = b"GBIX";
+const INDEX_VERSION: u8 = 1;
+const HEADER_LEN: u64 = 16;
+
+/// Builds an immutable on-disk index of a huge known set of gitoids, e.g.
+/// the hashes of a corpus of open-source files, for `IndexReader` to
+/// search without loading it into memory.
+///
+/// The file is a 16 byte header (magic, version, hash algorithm, object
+/// type, entry count) followed by the raw digests sorted and deduplicated,
+/// each the same width, so the reader can binary search it by seeking.
+/// An index holds a single hash algorithm and object type.
+///
+/// The builder keeps the digests in memory until `write` sorts them
+pub struct IndexBuilder {
+    hash_algorithm: HashAlgorithm,
+    object_type: ObjectType,
+    digests: Vec<Vec<u8>>,
+}
+
+impl IndexBuilder {
+    /// Start an index of `object_type` gitoids hashed with `hash_algo`
+    pub fn new(hash_algo: HashAlgorithm, object_type: ObjectType) -> Self {
+        IndexBuilder {
+            hash_algorithm: hash_algo,
+            object_type,
+            digests: Vec::new(),
+        }
+    }
+
+    /// Add a gitoid. Will return an `Err` if it doesn't have the index's
+    /// hash algorithm and object type
+    pub fn add(&mut self, gitoid: &GitOid) -> IOResult<()> {
+        if gitoid.hash_algorithm() != self.hash_algorithm
+            || gitoid.object_type() != self.object_type
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "{} is not a {} {}",
+                    gitoid, self.hash_algorithm, self.object_type
+                ),
+            ));
+        }
+        self.digests.push(gitoid.hash_value().to_vec());
+        Ok(())
+    }
+
+    /// Add many gitoids
+    pub fn add_many<'a, I>(&mut self, gitoids: I) -> IOResult<()>
+    where
+        I: IntoIterator<Item = &'a GitOid>,
+    {
+        for gitoid in gitoids {
+            self.add(gitoid)?;
+        }
+        Ok(())
+    }
+
+    /// Sort the digests and write the index
+    pub fn write<W: Write>(mut self, mut out: W) -> IOResult<()> {
+        self.digests.sort_unstable();
+        self.digests.dedup();
+
+        out.write_all(INDEX_MAGIC)?;
+        out.write_all(&[
+            INDEX_VERSION,
+            self.hash_algorithm.tag(),
+            self.object_type.tag(),
+            0,
+        ])?;
+        out.write_all(&(self.digests.len() as u64).to_be_bytes())?;
+        for digest in &self.digests {
+            out.write_all(digest)?;
+        }
+        out.flush()
+    }
+}
+
+/// Searches an index written by `IndexBuilder`. Each lookup is a binary
+/// search that reads O(log n) entries from the underlying reader, so the
+/// index never has to fit in memory
+pub struct IndexReader<R> {
+    reader: R,
+    hash_algorithm: HashAlgorithm,
+    object_type: ObjectType,
+    len: u64,
+}
+
+impl IndexReader<BufReader<File>> {
+    /// Open the index file at `path`
+    pub fn open<P: AsRef<Path>>(path: P) -> IOResult<Self> {
+        let path = path.as_ref();
+        File::open(path)
+            .and_then(|file| IndexReader::new(BufReader::new(file)))
+            .map_err(|error| in_file(error, path))
+    }
+}
+
+impl<R: Read + Seek> IndexReader<R> {
+    /// Read the index header from `reader`. Will return an `Err` if it
+    /// isn't an index or its size doesn't match the header
+    pub fn new(mut reader: R) -> IOResult<Self> {
+        let mut header = [0u8; HEADER_LEN as usize];
+        reader.seek(SeekFrom::Start(0))?;
+        reader.read_exact(&mut header)?;
+
+        if &header[..4] != INDEX_MAGIC || header[4] != INDEX_VERSION {
+            return Err(bad_index("not a version 1 index"));
+        }
+        let hash_algorithm = HashAlgorithm::from_tag(header[5])
+            .ok_or_else(|| bad_index("unknown hash algorithm"))?;
+        let object_type =
+            ObjectType::from_tag(header[6]).ok_or_else(|| bad_index("unknown object type"))?;
+        let len = u64::from_be_bytes(header[8..].try_into().unwrap());
+
+        let size = reader.seek(SeekFrom::End(0))?;
+        if Some(size)
+            != len
+                .checked_mul(hash_algorithm.digest_len() as u64)
+                .and_then(|n| n.checked_add(HEADER_LEN))
+        {
+            return Err(bad_index("file size doesn't match the entry count"));
+        }
+
+        Ok(IndexReader {
+            reader,
+            hash_algorithm,
+            object_type,
+            len,
+        })
+    }
+
+    /// The hash algorithm of the indexed gitoids
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+
+    /// The object type of the indexed gitoids
+    pub fn object_type(&self) -> ObjectType {
+        self.object_type
+    }
+
+    /// The number of gitoids in the index
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Is the index empty?
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Is `gitoid` in the index? A gitoid with a different hash algorithm
+    /// or object type is never in it
+    pub fn contains(&mut self, gitoid: &GitOid) -> IOResult<bool> {
+        if gitoid.hash_algorithm() != self.hash_algorithm
+            || gitoid.object_type() != self.object_type
+        {
+            return Ok(false);
+        }
+
+        let target = gitoid.hash_value();
+        let width = target.len() as u64;
+        let mut entry = vec![0u8; target.len()];
+        let (mut low, mut high) = (0u64, self.len);
+
+        while low < high {
+            let mid = low + (high - low) / 2;
+            self.reader
+                .seek(SeekFrom::Start(HEADER_LEN + mid * width))?;
+            self.reader.read_exact(&mut entry)?;
+            match entry[..].cmp(target) {
+                Ordering::Equal => return Ok(true),
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+            }
+        }
+        Ok(false)
+    }
+}
+
+fn bad_index(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("Invalid index: {}", msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn build(count: usize) -> (Vec<GitOid>, Cursor<Vec<u8>>) {
+        let gitoids: Vec<GitOid> = (0..count)
+            .map(|i| GitOid::new_from_str(&i.to_string()))
+            .collect();
+        let mut builder = IndexBuilder::new(HashAlgorithm::SHA256, ObjectType::Blob);
+        builder.add_many(&gitoids).unwrap();
+        // duplicates are dropped
+        builder.add_many(gitoids.iter().take(10)).unwrap();
+
+        let mut out = Vec::new();
+        builder.write(&mut out).unwrap();
+        (gitoids, Cursor::new(out))
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_lookup() {
+        let (gitoids, file) = build(1000);
+        let mut index = IndexReader::new(file).unwrap();
+
+        assert_eq!(1000, index.len());
+        for gitoid in &gitoids {
+            assert!(index.contains(gitoid).unwrap());
+        }
+        assert!(!index.contains(&GitOid::new_from_str("not there")).unwrap());
+        assert!(!index
+            .contains(&GitOid::new(HashAlgorithm::SHA1, b"0"))
+            .unwrap());
+    }
+
+    #[test]
+    fn test_empty_index() {
+        let (_, file) = build(0);
+        let mut index = IndexReader::new(file).unwrap();
+
+        assert!(index.is_empty());
+        assert!(!index.contains(&GitOid::new_from_str("0")).unwrap());
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_rejects_mismatched_gitoid() {
+        let mut builder = IndexBuilder::new(HashAlgorithm::SHA256, ObjectType::Blob);
+
+        assert!(builder
+            .add(&GitOid::new(HashAlgorithm::SHA1, b"hello world"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_rejects_truncated_file() {
+        let (_, file) = build(10);
+        let mut bytes = file.into_inner();
+        bytes.pop();
+
+        assert!(IndexReader::new(Cursor::new(bytes)).is_err());
+    }
+}
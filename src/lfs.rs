@@ -0,0 +1,282 @@
+use crate::metrics::{count_error, finalize_content};
+use crate::pool::PooledBuffer;
+use crate::{GitOid, GitOidHasher, HashAlgorithm, RateLimiter};
+use sha2::{Digest, Sha256};
+use std::fmt::{Debug, Formatter};
+use std::fs::File;
+use std::io::{Error, ErrorKind, Read, Result as IOResult};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Pointer files are smaller than this, per the spec
+pub(crate) const MAX_POINTER_LEN: u64 = 1024;
+
+/// The chunk size for hashing LFS objects
+const READ_LEN: usize = 64 * 1024;
+
+/// A [Git LFS](https://git-lfs.com) pointer file, which a working tree has
+/// in place of a large file whose content wasn't fetched or isn't checked
+/// out:
+///
+/// ```text
+/// version https://git-lfs.github.com/spec/v1
+/// oid sha256:<hex>
+/// size <bytes>
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LfsPointer {
+    oid: [u8; 32],
+    size: u64,
+}
+
+impl LfsPointer {
+    /// The pointer in `content`, if it's a pointer file. Extension keys
+    /// are allowed; anything else that isn't exactly the pointer format
+    /// is ordinary content
+    pub fn parse(content: &[u8]) -> Option<LfsPointer> {
+        if content.len() as u64 >= MAX_POINTER_LEN {
+            return None;
+        }
+        let text = std::str::from_utf8(content).ok()?.strip_suffix('\n')?;
+        let mut lines = text.split('\n');
+        if lines.next()? != "version https://git-lfs.github.com/spec/v1" {
+            return None;
+        }
+
+        let (mut oid, mut size) = (None, None);
+        let mut previous = "";
+        for line in lines {
+            let (key, value) = line.split_once(' ')?;
+            // the keys after `version` are sorted
+            if key <= previous {
+                return None;
+            }
+            previous = key;
+            match key {
+                "oid" => {
+                    let hex = value.strip_prefix("sha256:")?;
+                    if hex.len() != 64 || hex.contains(|c: char| c.is_ascii_uppercase()) {
+                        return None;
+                    }
+                    let mut digest = [0u8; 32];
+                    hex::decode_to_slice(hex, &mut digest).ok()?;
+                    oid = Some(digest);
+                }
+                "size" => {
+                    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+                        return None;
+                    }
+                    size = Some(value.parse().ok()?);
+                }
+                _ => {}
+            }
+        }
+        Some(LfsPointer {
+            oid: oid?,
+            size: size?,
+        })
+    }
+
+    /// The SHA256 of the content. This is the plain digest, not a gitoid
+    pub fn oid(&self) -> &[u8] {
+        &self.oid
+    }
+
+    /// The size of the content
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Where the content is in the local LFS cache at `lfs_dir`, usually
+    /// `.git/lfs`
+    pub fn cache_path<P: AsRef<Path>>(&self, lfs_dir: P) -> PathBuf {
+        let hex = hex::encode(self.oid);
+        lfs_dir
+            .as_ref()
+            .join("objects")
+            .join(&hex[..2])
+            .join(&hex[2..4])
+            .join(hex)
+    }
+}
+
+type ResolveFn = dyn Fn(&LfsPointer) -> IOResult<Option<Box<dyn Read>>> + Send + Sync;
+
+/// How a scan gets the content of LFS pointer files
+#[derive(Clone, Default)]
+pub(crate) struct LfsOptions {
+    pub(crate) cache: Option<PathBuf>,
+    pub(crate) resolver: Option<Arc<ResolveFn>>,
+}
+
+impl Debug for LfsOptions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LfsOptions")
+            .field("cache", &self.cache)
+            .field("resolver", &self.resolver.as_ref().map(|_| "<fn>"))
+            .finish()
+    }
+}
+
+impl LfsOptions {
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.cache.is_some() || self.resolver.is_some()
+    }
+
+    /// hash the content `pointer` points to, from the cache or else the
+    /// resolver, checking it against the pointer's oid and size. If
+    /// `plain`, also compute the plain digest. Reads are kept under the
+    /// `limiter`, if there is one
+    pub(crate) fn hash(
+        &self,
+        hash_algo: HashAlgorithm,
+        pointer: &LfsPointer,
+        plain: bool,
+        limiter: Option<&RateLimiter>,
+    ) -> IOResult<(GitOid, Option<Box<[u8]>>)> {
+        let mut reader = match self.open(pointer)? {
+            Some(reader) => reader.take(pointer.size),
+            None => {
+                return count_error(Err(Error::new(
+                    ErrorKind::NotFound,
+                    format!(
+                        "The Git LFS object sha256:{} isn't available",
+                        hex::encode(pointer.oid)
+                    ),
+                )))
+            }
+        };
+
+        let mut buf = PooledBuffer::new(READ_LEN);
+        let mut hasher = GitOidHasher::new(hash_algo, pointer.size as usize);
+        let mut sha256 = Sha256::new();
+        let mut digest = plain.then(|| hash_algo.create_digest());
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(size) => {
+                    if let Some(limiter) = limiter {
+                        limiter.wait_bytes(size as u64);
+                    }
+                    hasher.update(&buf[..size]);
+                    sha256.update(&buf[..size]);
+                    if let Some(digest) = &mut digest {
+                        digest.update(&buf[..size]);
+                    }
+                }
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(error) => return count_error(Err(error)),
+            }
+        }
+        let gitoid = count_error(finalize_content(hasher))?;
+        if sha256.finalize()[..] != pointer.oid {
+            return count_error(Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "The Git LFS object sha256:{} doesn't match its oid",
+                    hex::encode(pointer.oid)
+                ),
+            )));
+        }
+        Ok((gitoid, digest.map(|digest| digest.finalize())))
+    }
+
+    fn open(&self, pointer: &LfsPointer) -> IOResult<Option<Box<dyn Read>>> {
+        if let Some(cache) = &self.cache {
+            match File::open(pointer.cache_path(cache)) {
+                Ok(file) => return Ok(Some(Box::new(file))),
+                Err(error) if error.kind() == ErrorKind::NotFound => {}
+                Err(error) => return Err(error),
+            }
+        }
+        match &self.resolver {
+            Some(resolver) => resolver(pointer),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+    use crate::{DirectoryScan, ScanOptions};
+    use std::fs;
+
+    fn pointer_text(content: &[u8]) -> String {
+        format!(
+            "version https://git-lfs.github.com/spec/v1\noid sha256:{}\nsize {}\n",
+            hex::encode(Sha256::digest(content)),
+            content.len()
+        )
+    }
+
+    #[test]
+    fn test_parse_pointer() {
+        let text = pointer_text(b"large content");
+        let pointer = LfsPointer::parse(text.as_bytes()).unwrap();
+        assert_eq!(13, pointer.size());
+        assert_eq!(&Sha256::digest(b"large content")[..], pointer.oid());
+        let hex = hex::encode(pointer.oid());
+        assert_eq!(
+            Path::new(".git/lfs/objects")
+                .join(&hex[..2])
+                .join(&hex[2..4])
+                .join(&hex),
+            pointer.cache_path(".git/lfs")
+        );
+
+        let extended = text.replace("oid ", "ext-0-foo sha256:00\noid ");
+        assert!(LfsPointer::parse(extended.as_bytes()).is_some());
+        let (version, rest) = text.split_once('\n').unwrap();
+        let (oid, size) = rest.split_once('\n').unwrap();
+        let unsorted = format!("{}\n{}{}\n", version, size, oid);
+        assert!(LfsPointer::parse(unsorted.as_bytes()).is_none());
+        assert!(LfsPointer::parse(text.trim_end().as_bytes()).is_none());
+        assert!(LfsPointer::parse(b"hello world\n").is_none());
+    }
+
+    #[test]
+    fn test_scan_resolves_pointers() {
+        let root = TempDir::new("lfs");
+        let content = b"large content";
+        let pointer = LfsPointer::parse(pointer_text(content).as_bytes()).unwrap();
+        let lfs = root.join("lfs");
+        fs::create_dir_all(pointer.cache_path(&lfs).parent().unwrap()).unwrap();
+        fs::write(pointer.cache_path(&lfs), content).unwrap();
+        fs::create_dir_all(root.join("tree")).unwrap();
+        fs::write(root.join("tree/big.bin"), pointer_text(content)).unwrap();
+        let other = b"other content";
+        fs::write(root.join("tree/other.bin"), pointer_text(other)).unwrap();
+
+        let gitoids = |options: ScanOptions| {
+            let scan = DirectoryScan::new(root.join("tree"), HashAlgorithm::SHA256, &options);
+            let scan = scan.unwrap();
+            let errors: Vec<ErrorKind> = scan
+                .errors()
+                .iter()
+                .map(|error| error.error().kind())
+                .collect();
+            let files: Vec<GitOid> = scan.files().iter().map(|(_, gitoid)| *gitoid).collect();
+            (files, errors)
+        };
+        let plain = gitoids(ScanOptions::new());
+        let cached = gitoids(ScanOptions::new().lfs_cache(&lfs));
+        let resolved = gitoids(
+            ScanOptions::new()
+                .lfs_cache(&lfs)
+                .lfs_resolver(move |_| Ok(Some(Box::new(&other[..]) as Box<dyn Read>))),
+        );
+
+        let sha256 = |content: &[u8]| GitOid::new(HashAlgorithm::SHA256, content);
+        assert_eq!(
+            vec![
+                sha256(pointer_text(content).as_bytes()),
+                sha256(pointer_text(other).as_bytes())
+            ],
+            plain.0
+        );
+        assert_eq!((vec![sha256(content)], vec![ErrorKind::NotFound]), cached);
+        assert_eq!((vec![sha256(content), sha256(other)], vec![]), resolved);
+    }
+}
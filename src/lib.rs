@@ -1,13 +1,104 @@
+use futures::StreamExt;
 use im::{HashSet, Vector};
+use metrics::{count_error, finalize_content, metrics};
 use pin_project::pin_project;
 use sha2::{digest::DynDigest, Digest, Sha256};
-use std::fmt::{Display, Formatter, Result};
+use std::fmt::{Display, Formatter, LowerHex, Result, UpperHex};
 use std::io;
 use std::io::{BufReader, Error, ErrorKind, Read, Result as IOResult};
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
 
+mod advisory;
+mod aliases;
+mod annotations;
+mod backend;
+mod bloom;
+#[cfg(feature = "bytes")]
+mod buf;
+#[cfg(feature = "capi")]
+mod capi;
+mod changed;
+mod checksums;
+mod context;
+mod delta;
+mod document;
+mod document_ref;
+mod duplicates;
+mod encoding;
+mod events;
+mod fetch;
+pub mod ffi;
+pub mod fingerprint;
+mod glob;
+mod hasher;
+mod incremental;
+mod index;
+mod json;
+mod lfs;
+mod manifest;
+mod metrics;
+#[cfg(feature = "multihash")]
+mod multihash;
+mod objects;
+mod paths;
+mod pool;
+mod pretty;
+mod proof;
+mod registry;
+mod report;
+mod resolver;
+mod retry;
+mod scan;
+mod sparse;
+mod sri;
+mod tee;
+#[cfg(test)]
+mod tempdir;
+pub mod testing;
+mod throttle;
+mod tree;
+mod verify;
+mod vex;
+mod writer;
+
+pub use advisory::{match_advisories, Advisory, AdvisoryMatch};
+pub use aliases::AliasTable;
+pub use annotations::Annotations;
+pub use backend::Backend;
+pub use bloom::BloomSet;
+pub use changed::ChangedDuringRead;
+pub use checksums::{Checksum, ChecksumFile};
+pub use context::ErrorContext;
+pub use delta::GitBomDelta;
+pub use document::{DocumentEntries, DocumentReader, ParseOptions, SpecVersion};
+pub use document_ref::{DocumentRef, DocumentRefEntries};
+pub use events::{ScanEvent, ScanSummary, SkipReason};
+pub use fetch::{ArtifactSource, DirectorySource};
+pub use hasher::{GitOidHasher, HasherState};
+pub use incremental::{Regeneration, ScanCache};
+pub use index::{IndexBuilder, IndexReader};
+pub use lfs::LfsPointer;
+pub use manifest::{ManifestReader, ManifestWriter};
+pub use metrics::{set_metrics, Metrics};
+pub use objects::{CommitBuilder, Signature, TagBuilder};
+pub use paths::normalize_path;
+pub use pretty::PrettyOptions;
+pub use proof::{verify_inclusion, InclusionProof};
+pub use registry::HashAlgorithmRegistry;
+pub use report::{Finding, FindingKind, Report, Severity};
+pub use resolver::{Resolver, ResolverRegistry};
+pub use retry::RetryPolicy;
+pub use scan::{DirectoryScan, ScanError, ScanOptions};
+pub use tee::GitOidTeeReader;
+pub use throttle::RateLimiter;
+pub use verify::{
+    verify_directory, verify_directory_with_events, DirectoryReport, VerifyingReader,
+};
+pub use vex::{VexDocument, VexJustification, VexStatus};
+pub use writer::GitOidWriter;
+
 #[pin_project]
 pub struct Source<R> {
     #[pin]
@@ -25,6 +116,10 @@ impl<R> Source<R> {
         self.length
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
     // maybe also from_file ?
 }
 
@@ -39,10 +134,17 @@ impl<R: AsyncRead> AsyncRead for Source<R> {
     }
 }
 
-/// The available algorithms for computing hashes
+/// The available algorithms for computing hashes. Non-exhaustive because
+/// turning on the default `sha1` feature adds a variant, and enabling a
+/// feature must not break crates that match on it
 #[derive(Clone, Copy, PartialOrd, Eq, Ord, Debug, Hash, PartialEq)]
+#[non_exhaustive]
 pub enum HashAlgorithm {
-    /// [SHA1](https://en.wikipedia.org/wiki/SHA-1)
+    /// [SHA1](https://en.wikipedia.org/wiki/SHA-1). Only available with
+    /// the `sha1` feature, which is on by default. Without it, SHA1
+    /// documents, URIs and binary formats are rejected as unknown
+    /// algorithms
+    #[cfg(feature = "sha1")]
     SHA1,
     /// [SHA256](https://en.wikipedia.org/wiki/SHA-2)
     SHA256,
@@ -52,11 +154,45 @@ impl HashAlgorithm {
     /// a digester
     pub fn create_digest(&self) -> Box<dyn DynDigest> {
         let ret: Box<dyn sha2::digest::DynDigest> = match self {
+            #[cfg(feature = "sha1")]
             HashAlgorithm::SHA1 => Box::new(sha1::Sha1::new()),
             HashAlgorithm::SHA256 => Box::new(Sha256::new()),
         };
 
-        return ret;
+        ret
+    }
+
+    /// The number of bytes in a digest computed by this algorithm
+    pub fn digest_len(&self) -> usize {
+        match self {
+            #[cfg(feature = "sha1")]
+            HashAlgorithm::SHA1 => 20,
+            HashAlgorithm::SHA256 => 32,
+        }
+    }
+
+    /// the number identifying the algorithm in the crate's binary formats
+    pub(crate) fn tag(&self) -> u8 {
+        match self {
+            #[cfg(feature = "sha1")]
+            HashAlgorithm::SHA1 => 1,
+            HashAlgorithm::SHA256 => 2,
+        }
+    }
+
+    pub(crate) fn from_tag(tag: u8) -> Option<HashAlgorithm> {
+        match tag {
+            #[cfg(feature = "sha1")]
+            1 => Some(HashAlgorithm::SHA1),
+            2 => Some(HashAlgorithm::SHA256),
+            _ => None,
+        }
+    }
+
+    /// Report which implementation will compute this algorithm's
+    /// hashes on the current CPU
+    pub fn backend(&self) -> Backend {
+        Backend::detect(*self)
     }
 }
 
@@ -67,57 +203,284 @@ const NUM_HASH_BYTES: usize = 32;
 impl Display for HashAlgorithm {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         match self {
+            #[cfg(feature = "sha1")]
             HashAlgorithm::SHA1 => write!(f, "SHA1"),
             HashAlgorithm::SHA256 => write!(f, "SHA256"),
         }
     }
 }
 
+/// The type of [git object](https://git-scm.com/book/en/v2/Git-Internals-Git-Objects)
+/// that was hashed. The type is part of the prefix that git hashes along
+/// with the content, so the same bytes have a different `GitOid` per type
+#[derive(Clone, Copy, PartialOrd, Eq, Ord, Debug, Hash, PartialEq)]
+pub enum ObjectType {
+    /// File content
+    Blob,
+    /// A directory listing
+    Tree,
+    /// A commit
+    Commit,
+    /// An annotated tag
+    Tag,
+}
+
+impl ObjectType {
+    /// the number identifying the type in the crate's binary formats.
+    /// These are git's own object type numbers
+    pub(crate) fn tag(&self) -> u8 {
+        match self {
+            ObjectType::Commit => 1,
+            ObjectType::Tree => 2,
+            ObjectType::Blob => 3,
+            ObjectType::Tag => 4,
+        }
+    }
+
+    pub(crate) fn from_tag(tag: u8) -> Option<ObjectType> {
+        match tag {
+            1 => Some(ObjectType::Commit),
+            2 => Some(ObjectType::Tree),
+            3 => Some(ObjectType::Blob),
+            4 => Some(ObjectType::Tag),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ObjectType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            ObjectType::Blob => write!(f, "blob"),
+            ObjectType::Tree => write!(f, "tree"),
+            ObjectType::Commit => write!(f, "commit"),
+            ObjectType::Tag => write!(f, "tag"),
+        }
+    }
+}
+
 /// A struct that computes [git oids](https://git-scm.com/book/en/v2/Git-Internals-Git-Objects)
 /// based on the selected algorithm
 #[derive(Clone, Copy, PartialOrd, Eq, Ord, Debug, Hash, PartialEq)]
 pub struct GitOid {
     hash_algorithm: HashAlgorithm,
+    object_type: ObjectType,
     len: usize,
     value: [u8; NUM_HASH_BYTES],
 }
 
+/// `{}` is `ALGORITHM:hex`, e.g. `SHA1:95d0...`. The alternate form,
+/// `{:#}`, is the `gitoid:` URI
 impl Display for GitOid {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        write!(f, "{}:{}", self.hash_algorithm, self.hex_hash())
+        if f.alternate() {
+            return write!(
+                f,
+                "gitoid:{}:{}:{:x}",
+                self.object_type,
+                self.hash_algorithm.document_name(),
+                self
+            );
+        }
+        write!(f, "{}:{:x}", self.hash_algorithm, self)
+    }
+}
+
+/// The hash in lowercase hex. Width and precision work as for strings,
+/// so `{:.12x}` is an abbreviated hash
+impl LowerHex for GitOid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.pad(self.hex_hash_into(&mut [0; NUM_HASH_BYTES * 2]))
+    }
+}
+
+/// The hash in uppercase hex, formatted like `LowerHex`
+impl UpperHex for GitOid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let mut buf = [0; NUM_HASH_BYTES * 2];
+        let len = self.len * 2;
+        self.hex_hash_into(&mut buf);
+        buf[..len].make_ascii_uppercase();
+        f.pad(std::str::from_utf8(&buf[..len]).unwrap()) // hex is ASCII
     }
 }
 
 impl GitOid {
+    #[cfg(feature = "sha1")]
+    /// The gitoid of an empty file, in SHA1
+    pub const EMPTY_BLOB_SHA1: GitOid = GitOid::from_hex_const(
+        HashAlgorithm::SHA1,
+        ObjectType::Blob,
+        "e69de29bb2d1d6434b8b29ae775ad8c2e48c5391",
+    );
+
+    /// The gitoid of an empty file, in SHA256
+    pub const EMPTY_BLOB_SHA256: GitOid = GitOid::from_hex_const(
+        HashAlgorithm::SHA256,
+        ObjectType::Blob,
+        "473a0f4c3be8a93681a267e3b1e9a7dcda1185436fe141f7749120a303721813",
+    );
+
+    #[cfg(feature = "sha1")]
+    /// The gitoid of an empty directory listing, in SHA1
+    pub const EMPTY_TREE_SHA1: GitOid = GitOid::from_hex_const(
+        HashAlgorithm::SHA1,
+        ObjectType::Tree,
+        "4b825dc642cb6eb9a060e54bf8d69288fbee4904",
+    );
+
+    /// The gitoid of an empty directory listing, in SHA256
+    pub const EMPTY_TREE_SHA256: GitOid = GitOid::from_hex_const(
+        HashAlgorithm::SHA256,
+        ObjectType::Tree,
+        "6ef19b41225c5369f1c104d45d8d85efa9b057b53b14b4b9b939dd74decc5321",
+    );
+
+    /// build a GitOid from a hex literal at compile time. A bad literal
+    /// fails the build
+    const fn from_hex_const(
+        hash_algo: HashAlgorithm,
+        object_type: ObjectType,
+        hex: &str,
+    ) -> GitOid {
+        const fn nibble(c: u8) -> u8 {
+            match c {
+                b'0'..=b'9' => c - b'0',
+                b'a'..=b'f' => c - b'a' + 10,
+                _ => panic!("not a lowercase hex digit"),
+            }
+        }
+
+        let hex = hex.as_bytes();
+        assert!(hex.len() <= NUM_HASH_BYTES * 2 && hex.len().is_multiple_of(2));
+        let mut value = [0u8; NUM_HASH_BYTES];
+        let mut i = 0;
+        while i < hex.len() / 2 {
+            value[i] = (nibble(hex[2 * i]) << 4) | nibble(hex[2 * i + 1]);
+            i += 1;
+        }
+        GitOid {
+            hash_algorithm: hash_algo,
+            object_type,
+            len: hex.len() / 2,
+            value,
+        }
+    }
+
     /// return the hex value of the hashcode, without the hash type
     pub fn hex_hash(&self) -> String {
         hex::encode(&self.value[0..self.len])
     }
 
+    /// Write the hex value of the hashcode into `buf` and return it, to
+    /// format many gitoids without allocating a `String` for each
+    pub fn hex_hash_into<'a>(&self, buf: &'a mut [u8; NUM_HASH_BYTES * 2]) -> &'a str {
+        self.write_hex(buf).unwrap() // the buffer fits any hash
+    }
+
+    /// Write the hex value of the hashcode to the start of `buf` and
+    /// return it. Will return an `Err` if `buf` is shorter than twice
+    /// `hash_len`
+    pub fn write_hex<'a>(&self, buf: &'a mut [u8]) -> IOResult<&'a str> {
+        let available = buf.len();
+        let hex = buf.get_mut(..self.len * 2).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "{} hex digits don't fit in {} bytes",
+                    self.len * 2,
+                    available
+                ),
+            )
+        })?;
+        hex::encode_to_slice(&self.value[0..self.len], hex).unwrap(); // the lengths match
+        Ok(std::str::from_utf8(hex).unwrap()) // hex is ASCII
+    }
+
     /// get a slice with the hash value. The lifetime of the slice
     /// is the same as the lifetime of the GitOid
-    pub fn hash_value<'a>(&'a self) -> &'a [u8] {
+    pub fn hash_value(&self) -> &[u8] {
         &self.value[0..self.len]
     }
 
+    /// The raw digest bytes, e.g. to use as a database key. The same as
+    /// `hash_value`
+    pub fn as_bytes(&self) -> &[u8] {
+        self.hash_value()
+    }
+
+    /// The digest in lowercase hex, without the hash type. The same as
+    /// `hex_hash`
+    pub fn hex_string(&self) -> String {
+        self.hex_hash()
+    }
+
+    /// The number of bytes in the digest
+    pub fn hash_len(&self) -> usize {
+        self.len
+    }
+
     /// Get the hash algorithm used for this GitOid
     pub fn hash_algorithm(&self) -> HashAlgorithm {
         self.hash_algorithm
     }
 
+    /// Get the type of git object this GitOid identifies
+    pub fn object_type(&self) -> ObjectType {
+        self.object_type
+    }
+
     /// create a new GitOid based on an in-memory array
     pub fn new(hash_algo: HashAlgorithm, content: &[u8]) -> Self {
-        let v = GitOid::generate_git_oid_from_buffer(
-            hash_algo.create_digest(),
-            BufReader::new(content),
-            content.len(),
-        )
-        .unwrap(); // `unwrap` is usually code smell. In this case, we know there will be no I/O errors and the length will be correct
-        GitOid {
-            hash_algorithm: hash_algo,
-            value: v.1,
-            len: v.0,
+        GitOid::new_object(hash_algo, ObjectType::Blob, content)
+    }
+
+    /// create a new GitOid for an in-memory git object of the given type
+    pub fn new_object(hash_algo: HashAlgorithm, object_type: ObjectType, content: &[u8]) -> Self {
+        metrics().bytes_hashed(content.len() as u64);
+        GitOid::hash_object(hash_algo, object_type, content)
+    }
+
+    /// like `new_object`, but not counted in the metrics, for documents,
+    /// trees and other objects the crate builds itself
+    pub(crate) fn hash_object(
+        hash_algo: HashAlgorithm,
+        object_type: ObjectType,
+        content: &[u8],
+    ) -> Self {
+        let mut hasher = GitOidHasher::new_object(hash_algo, object_type, content.len());
+        hasher.update(content);
+        hasher.finalize().unwrap() // the length is always the expected length
+    }
+
+    /// Wrap a digest computed elsewhere (a database, another tool, ...)
+    /// as a GitOid. Will return an `Err` if the digest isn't the right
+    /// length for the algorithm
+    pub fn from_raw(
+        hash_algo: HashAlgorithm,
+        object_type: ObjectType,
+        digest: &[u8],
+    ) -> IOResult<Self> {
+        if digest.len() != hash_algo.digest_len() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Expected a {} byte {} digest but got {} bytes",
+                    hash_algo.digest_len(),
+                    hash_algo,
+                    digest.len()
+                ),
+            ));
         }
+
+        let mut value = [0u8; NUM_HASH_BYTES];
+        value[..digest.len()].copy_from_slice(digest);
+        Ok(GitOid {
+            hash_algorithm: hash_algo,
+            object_type,
+            len: digest.len(),
+            value,
+        })
     }
 
     /// create a GitOid using SHA256 for the string... mostly a helper method
@@ -133,13 +496,10 @@ impl GitOid {
     where
         BufReader<R>: std::io::Read,
     {
-        let digest = hash_algo.create_digest();
-        let v = GitOid::generate_git_oid_from_buffer(digest, content, expected_length)?;
-        Ok(GitOid {
-            hash_algorithm: hash_algo,
-            len: v.0,
-            value: v.1,
-        })
+        count_error(GitOid::generate_git_oid_from_buffer(
+            GitOidHasher::new(hash_algo, expected_length),
+            content,
+        ))
     }
 
     /// generate a bunch of `GitOid`s from a bunch of async
@@ -152,141 +512,103 @@ impl GitOid {
         R: AsyncReadExt + std::marker::Unpin,
         I: IntoIterator<Item = Source<R>>,
     {
-        let digest = hash_algo.create_digest();
-        let mut future_vec = Vec::new();
-
-        // go get the futures for each hash operation
-        for reader in content {
-            let expected_length = reader.len();
-            future_vec.push(GitOid::generate_git_oid_from_async_buffer(
-                digest.clone(),
-                reader,
-                expected_length,
-            ));
-        }
+        GitOid::new_from_async_readers_throttled(hash_algo, content, &RateLimiter::new()).await
+    }
 
-        // create the return vector
-        let mut ret = Vector::new();
+    /// like `new_from_async_readers` but all the readers together are
+    /// kept under the `limiter`'s rates
+    pub async fn new_from_async_readers_throttled<R, I>(
+        hash_algo: HashAlgorithm,
+        content: I,
+        limiter: &RateLimiter,
+    ) -> IOResult<Vector<GitOid>>
+    where
+        R: AsyncReadExt + std::marker::Unpin,
+        I: IntoIterator<Item = Source<R>>,
+    {
+        GitOid::new_from_async_readers_bounded(hash_algo, content, limiter, usize::MAX).await
+    }
 
-        // go through each future and await the response
-        // the cool thing is that this will block on any given future
-        // but other futures may become satisfied so the look effectively
-        // blocks on the longest-to-satisfy future
-        for res in futures::future::join_all(future_vec).await {
-            let (len, bytes) = res?;
-            ret.push_back(GitOid {
-                hash_algorithm: hash_algo,
-                len: len,
-                value: bytes,
-            });
+    /// like `new_from_async_readers_throttled` but with at most
+    /// `max_in_flight` readers being hashed (each with its own buffer) or
+    /// finished and waiting to be collected at once, so a huge batch has
+    /// a predictable memory footprint. `content` is only pulled from as
+    /// slots free up, so a lazy iterator doesn't open every file up front.
+    /// The results are in the same order as `content`
+    pub async fn new_from_async_readers_bounded<R, I>(
+        hash_algo: HashAlgorithm,
+        content: I,
+        limiter: &RateLimiter,
+        max_in_flight: usize,
+    ) -> IOResult<Vector<GitOid>>
+    where
+        R: AsyncReadExt + std::marker::Unpin,
+        I: IntoIterator<Item = Source<R>>,
+    {
+        let mut results = futures::stream::iter(content)
+            .map(|reader| {
+                let hasher = GitOidHasher::new(hash_algo, reader.len());
+                GitOid::generate_git_oid_from_async_buffer(hasher, reader, limiter)
+            })
+            .buffered(max_in_flight.max(1));
+
+        let mut ret = Vector::new();
+        while let Some(res) = results.next().await {
+            ret.push_back(count_error(res)?);
         }
 
         Ok(ret)
     }
 
-    /// the async version of generating a git_oid from a buffer
+    /// the async version of generating a git_oid from a buffer. Only the
+    /// reading differs; the hashing is `GitOidHasher`'s, as for every
+    /// other way of making a `GitOid`
     async fn generate_git_oid_from_async_buffer<R>(
-        mut digest: Box<dyn DynDigest>,
+        mut hasher: GitOidHasher,
         mut reader: R,
-        expected_length: usize,
-    ) -> IOResult<(usize, [u8; NUM_HASH_BYTES])>
+        limiter: &RateLimiter,
+    ) -> IOResult<GitOid>
     where
         R: AsyncReadExt + std::marker::Unpin,
     {
-        let prefix = format!("blob {}\0", expected_length);
+        limiter.wait_file_async().await;
 
         let mut buf = [0u8; 8192]; // the size of a buffer for buffered read
-        let mut amount_read: usize = 0;
-
-        // set the prefix
-        digest.update(prefix.as_bytes());
-
-        // keep reading the input until there is no more
         loop {
             match reader.read(&mut buf).await? {
-                // done
                 0 => break,
-
-                // update the hash and accumulate the count
                 size => {
-                    digest.update(&buf[..size]);
-                    amount_read = amount_read + size;
+                    hasher.update(&buf[..size]);
+                    limiter.wait_bytes_async(size as u64).await;
                 }
             }
         }
 
-        // make sure we got the length we expected
-        if amount_read != expected_length {
-            return Err(Error::new(
-                ErrorKind::InvalidInput,
-                format!(
-                    "Expected length {} actual length {}",
-                    expected_length, amount_read
-                ),
-            ));
-        }
-
-        let hash = digest.finalize();
-        let mut ret = [0u8; NUM_HASH_BYTES];
-
-        let len = NUM_HASH_BYTES.min(hash.len());
-        ret[..len].copy_from_slice(&hash);
-        return Ok((len, ret));
+        finalize_content(hasher)
     }
 
-    /// Take a `BufReader` and generate a hash based on the `GitOid`'s hashing
-    /// algorithm. Will return an `Err` if the `BufReader` generates an `Err`
-    /// or if the `expected_length` is different from the actual length. Why
-    /// the latter `Err`? The prefix string includes the number of bytes
-    /// being hashed and that's the `expected_length`. If the actual bytes
-    /// hashed differs, then something went wrong and the hash is not valid
+    /// Feed everything from a `BufReader` to `hasher`. Will return an
+    /// `Err` if the `BufReader` generates an `Err` or if the amount read
+    /// is different from the hasher's expected length. Why the latter
+    /// `Err`? The prefix string includes the number of bytes being hashed
+    /// and that's the expected length. If the actual bytes hashed
+    /// differs, then something went wrong and the hash is not valid
     fn generate_git_oid_from_buffer<R>(
-        mut digest: Box<dyn DynDigest>,
+        mut hasher: GitOidHasher,
         mut reader: BufReader<R>,
-        expected_length: usize,
-    ) -> IOResult<(usize, [u8; NUM_HASH_BYTES])>
+    ) -> IOResult<GitOid>
     where
         BufReader<R>: std::io::Read,
     {
-        let prefix = format!("blob {}\0", expected_length);
-
         let mut buf = [0; 4096]; // Linux default page size is 4096
-        let mut amount_read: usize = 0;
-
-        // set the prefix
-        digest.update(prefix.as_bytes());
-
-        // keep reading the input until there is no more
         loop {
             match reader.read(&mut buf)? {
-                // done
                 0 => break,
-
-                // update the hash and accumulate the count
-                size => {
-                    digest.update(&buf[..size]);
-                    amount_read = amount_read + size;
-                }
+                size => hasher.update(&buf[..size]),
             }
         }
 
-        // make sure we got the length we expected
-        if amount_read != expected_length {
-            return Err(Error::new(
-                ErrorKind::InvalidInput,
-                format!(
-                    "Expected length {} actual length {}",
-                    expected_length, amount_read
-                ),
-            ));
-        }
-
-        let hash = digest.finalize();
-        let mut ret = [0u8; NUM_HASH_BYTES];
-
-        let len = std::cmp::min(NUM_HASH_BYTES, hash.len());
-        ret[..len].copy_from_slice(&hash);
-        return Ok((len, ret));
+        finalize_content(hasher)
     }
 }
 
@@ -311,6 +633,12 @@ impl FromIterator<GitOid> for GitBom {
     }
 }
 
+impl Default for GitBom {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl GitBom {
     /// Create a new instance
     pub fn new() -> Self {
@@ -359,7 +687,49 @@ impl GitBom {
     pub fn get_sorted_oids(&self) -> Vector<GitOid> {
         let mut ret: Vector<GitOid> = self.git_oids.clone().into_iter().collect();
         ret.sort();
-        return ret;
+        ret
+    }
+
+    /// Is the `gitoid` in this `GitBom`? The oids are stored in a
+    /// hash set, so this is O(1) and doesn't clone anything
+    pub fn contains(&self, gitoid: &GitOid) -> bool {
+        self.git_oids.contains(gitoid)
+    }
+
+    /// The number of git oids in the `GitBom`
+    pub fn len(&self) -> usize {
+        self.git_oids.len()
+    }
+
+    /// Is the `GitBom` empty?
+    pub fn is_empty(&self) -> bool {
+        self.git_oids.is_empty()
+    }
+
+    /// Return a new `GitBom` with the oids that are in either `GitBom`
+    pub fn union(&self, other: &GitBom) -> Self {
+        Self {
+            git_oids: self.git_oids.clone().union(other.git_oids.clone()),
+        }
+    }
+
+    /// Return a new `GitBom` with the oids that are in both `GitBom`s
+    pub fn intersection(&self, other: &GitBom) -> Self {
+        Self {
+            git_oids: self.git_oids.clone().intersection(other.git_oids.clone()),
+        }
+    }
+
+    /// Return a new `GitBom` with the oids in this `GitBom` that aren't in
+    /// `other`, e.g. the artifacts in a build that aren't in an approved set
+    pub fn difference(&self, other: &GitBom) -> Self {
+        Self {
+            // `im`'s `difference` is the symmetric difference
+            git_oids: self
+                .git_oids
+                .clone()
+                .relative_complement(other.git_oids.clone()),
+        }
     }
 }
 
@@ -389,6 +759,105 @@ mod tests {
         assert_eq!(da_bom.get_sorted_oids(), oids);
     }
 
+    #[test]
+    fn test_contains_and_len() {
+        let hello = GitOid::new_from_str("Hello");
+        let da_bom = GitBom::new().add_many(vec![hello, GitOid::new_from_str("Cat")]);
+
+        assert!(da_bom.contains(&hello));
+        assert!(!da_bom.contains(&GitOid::new_from_str("Dog")));
+        assert_eq!(2, da_bom.len());
+        assert!(!da_bom.is_empty());
+        assert!(GitBom::new().is_empty());
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let make =
+            |names: Vec<&str>| -> GitBom { names.into_iter().map(GitOid::new_from_str).collect() };
+        let build = make(vec!["a", "b", "c"]);
+        let approved = make(vec!["b", "c", "d"]);
+
+        assert_eq!(make(vec!["a", "b", "c", "d"]), build.union(&approved));
+        assert_eq!(make(vec!["b", "c"]), build.intersection(&approved));
+        assert_eq!(make(vec!["a"]), build.difference(&approved));
+        assert_eq!(make(vec!["d"]), approved.difference(&build));
+
+        // the originals are unchanged
+        assert_eq!(3, build.len());
+        assert_eq!(3, approved.len());
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_empty_object_constants() {
+        assert_eq!(
+            GitOid::EMPTY_BLOB_SHA1,
+            GitOid::new(HashAlgorithm::SHA1, b"")
+        );
+        assert_eq!(
+            GitOid::EMPTY_TREE_SHA1,
+            GitOid::new_object(HashAlgorithm::SHA1, ObjectType::Tree, b"")
+        );
+    }
+
+    #[test]
+    fn test_empty_object_constants_sha256() {
+        assert_eq!(
+            GitOid::EMPTY_BLOB_SHA256,
+            GitOid::new(HashAlgorithm::SHA256, b"")
+        );
+        assert_eq!(
+            GitOid::EMPTY_TREE_SHA256,
+            GitOid::new_object(HashAlgorithm::SHA256, ObjectType::Tree, b"")
+        );
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_from_raw() {
+        let gitoid = GitOid::new(HashAlgorithm::SHA1, b"hello world");
+
+        assert_eq!(
+            gitoid,
+            GitOid::from_raw(HashAlgorithm::SHA1, ObjectType::Blob, gitoid.hash_value()).unwrap()
+        );
+        assert!(
+            GitOid::from_raw(HashAlgorithm::SHA256, ObjectType::Blob, gitoid.hash_value()).is_err()
+        );
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_byte_and_hex_accessors() {
+        let gitoid = GitOid::new(HashAlgorithm::SHA1, b"hello world");
+
+        assert_eq!(20, gitoid.hash_len());
+        assert_eq!(gitoid.hash_value(), gitoid.as_bytes());
+        assert_eq!(
+            "95d09f2b10159347eece71399a7e2e907ea3df4f",
+            gitoid.hex_string()
+        );
+        assert_eq!(32, GitOid::new_from_str("").hash_len());
+    }
+
+    #[test]
+    fn test_fmt_traits() {
+        let gitoid = GitOid::new(HashAlgorithm::SHA256, b"hello world");
+
+        assert_eq!(gitoid.hex_hash(), format!("{:x}", gitoid));
+        assert_eq!(gitoid.hex_hash().to_uppercase(), format!("{:X}", gitoid));
+        assert_eq!("fee53a18d328", format!("{:.12x}", gitoid));
+        assert_eq!(gitoid.hex_hash(), gitoid.hex_hash_into(&mut [0; 64]));
+        let mut buf = [b'-'; 70];
+        assert_eq!(gitoid.hex_hash(), gitoid.write_hex(&mut buf).unwrap());
+        assert_eq!(b"------", &buf[64..]);
+        assert!(gitoid.write_hex(&mut [0; 63]).is_err());
+        assert_eq!(gitoid.uri(), format!("{:#}", gitoid));
+        assert_eq!(format!("SHA256:{}", gitoid.hex_hash()), gitoid.to_string());
+    }
+
+    #[cfg(feature = "sha1")]
     #[test]
     fn test_generate_sha1_git_oid() {
         let input = "hello world".as_bytes();
@@ -401,6 +870,7 @@ mod tests {
         )
     }
 
+    #[cfg(feature = "sha1")]
     #[test]
     fn test_generate_sha1_git_oid_buffer() {
         let file = File::open("test/data/hello_world.txt").unwrap();
@@ -482,4 +952,28 @@ mod tests {
         // the gitbom should only have one entry
         assert_eq!(1, gitbom.get_oids().len());
     }
+
+    #[cfg(feature = "sha1")]
+    #[tokio::test]
+    async fn test_bounded_async_read_keeps_order() {
+        let contents: Vec<String> = (0..20).map(|i| i.to_string()).collect();
+        let sources = contents
+            .iter()
+            .map(|content| Source::new(content.as_bytes(), content.len()));
+
+        let res = GitOid::new_from_async_readers_bounded(
+            HashAlgorithm::SHA1,
+            sources,
+            &RateLimiter::new(),
+            2,
+        )
+        .await
+        .unwrap();
+
+        let expected: Vector<GitOid> = contents
+            .iter()
+            .map(|content| GitOid::new(HashAlgorithm::SHA1, content.as_bytes()))
+            .collect();
+        assert_eq!(expected, res);
+    }
 }
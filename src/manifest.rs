@@ -0,0 +1,232 @@
+use crate::context::at_line;
+use crate::document::{bad_document, parse_gitoid};
+use crate::scan::path_order;
+use crate::{GitOid, HashAlgorithm};
+use std::io::{BufRead, Error, ErrorKind, Result as IOResult, Write};
+use std::path::{Path, PathBuf};
+
+/// Writes a path manifest: which file each gitoid came from, for build
+/// systems that need to get from a document's entries back to files.
+/// It's kept apart from the canonical document, which has no paths:
+///
+/// ```text
+/// gitoid:manifest:sha256
+/// <type> <hex> <path>
+/// ```
+///
+/// with one line per file, sorted bytewise by a path relative to the
+/// manifest's root with `/` separators. The order `DirectoryScan::files`
+/// returns is the order they have to be written in
+pub struct ManifestWriter<W> {
+    writer: W,
+    hash_algorithm: HashAlgorithm,
+    previous: Option<Vec<u8>>,
+}
+
+impl<W: Write> ManifestWriter<W> {
+    /// Write the header for a manifest of `hash_algo` gitoids
+    pub fn new(mut writer: W, hash_algo: HashAlgorithm) -> IOResult<Self> {
+        writeln!(writer, "gitoid:manifest:{}", hash_algo.document_name())?;
+        Ok(ManifestWriter {
+            writer,
+            hash_algorithm: hash_algo,
+            previous: None,
+        })
+    }
+
+    /// Record that the file at `path` has the gitoid `gitoid`. Will return
+    /// an `Err` of kind `InvalidInput` if the path isn't UTF-8, has a line
+    /// break or doesn't sort after the previous one, or the gitoid uses
+    /// another hash algorithm
+    pub fn write_entry<P: AsRef<Path>>(&mut self, path: P, gitoid: GitOid) -> IOResult<()> {
+        let path = path.as_ref();
+        let bad_entry = |msg: &str| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("Can't add {} to the manifest: {}", path.display(), msg),
+            )
+        };
+        if gitoid.hash_algorithm() != self.hash_algorithm {
+            return Err(bad_entry("the gitoid uses another hash algorithm"));
+        }
+        let key = path_order(path);
+        let text = std::str::from_utf8(&key).map_err(|_| bad_entry("the path isn't UTF-8"))?;
+        if text.is_empty() || text.contains(['\n', '\r']) {
+            return Err(bad_entry("the path is empty or has a line break"));
+        }
+        if self
+            .previous
+            .as_ref()
+            .is_some_and(|previous| key <= *previous)
+        {
+            return Err(bad_entry("the path is out of order or a duplicate"));
+        }
+
+        writeln!(
+            self.writer,
+            "{} {} {}",
+            gitoid.object_type().document_name(),
+            gitoid.hex_hash(),
+            text
+        )?;
+        self.previous = Some(key);
+        Ok(())
+    }
+
+    /// Flush the manifest and return the inner writer
+    pub fn finish(mut self) -> IOResult<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Reads a manifest written by `ManifestWriter`, as an iterator over the
+/// paths and their gitoids. After the first `Err` the iterator ends
+pub struct ManifestReader<R> {
+    reader: R,
+    hash_algorithm: HashAlgorithm,
+    line: String,
+    previous: String,
+    line_number: u64,
+    offset: u64,
+    done: bool,
+}
+
+impl<R: BufRead> ManifestReader<R> {
+    /// Read the header line. Will return an `Err` if it's malformed
+    pub fn new(mut reader: R) -> IOResult<Self> {
+        let mut header = String::new();
+        let size = reader
+            .read_line(&mut header)
+            .map_err(|error| at_line(error, 1, 0))?;
+        let hash_algorithm = header
+            .strip_suffix('\n')
+            .and_then(|header| header.strip_prefix("gitoid:manifest:"))
+            .and_then(HashAlgorithm::from_document_name)
+            .ok_or_else(|| {
+                at_line(
+                    bad_document("missing gitoid:manifest:<algorithm> header"),
+                    1,
+                    0,
+                )
+            })?;
+        Ok(ManifestReader {
+            reader,
+            hash_algorithm,
+            line: String::new(),
+            previous: String::new(),
+            line_number: 1,
+            offset: size as u64,
+            done: false,
+        })
+    }
+
+    /// The hash algorithm from the header
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+
+    fn next_entry(&mut self) -> IOResult<Option<(PathBuf, GitOid)>> {
+        self.line.clear();
+        let size = self.reader.read_line(&mut self.line)?;
+        if size == 0 {
+            return Ok(None);
+        }
+        self.offset += size as u64;
+        let line = self
+            .line
+            .strip_suffix('\n')
+            .ok_or_else(|| bad_document("the last line has no newline"))?;
+        let bad_line = || bad_document(&format!("bad manifest line {:?}", line));
+        let mut parts = line.splitn(3, ' ');
+        let (object_type, hash, path) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(object_type), Some(hash), Some(path)) if !path.is_empty() => {
+                (object_type, hash, path)
+            }
+            _ => return Err(bad_line()),
+        };
+        if path.contains('\r') {
+            return Err(bad_line());
+        }
+        if !self.previous.is_empty() && path <= self.previous.as_str() {
+            return Err(bad_document(&format!(
+                "{:?} is out of order or a duplicate",
+                path
+            )));
+        }
+        let gitoid = parse_gitoid(self.hash_algorithm, &format!("{} {}", object_type, hash))?;
+
+        self.previous.clear();
+        self.previous.push_str(path);
+        Ok(Some((PathBuf::from(path), gitoid)))
+    }
+}
+
+impl<R: BufRead> Iterator for ManifestReader<R> {
+    type Item = IOResult<(PathBuf, GitOid)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        self.line_number += 1;
+        let line_offset = self.offset;
+        let ret = self
+            .next_entry()
+            .map_err(|error| at_line(error, self.line_number, line_offset))
+            .transpose();
+        if !matches!(ret, Some(Ok(_))) {
+            self.done = true;
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DirectoryScan, ErrorContext, ObjectType, ScanOptions};
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let scan = DirectoryScan::new("test/data/tree", HashAlgorithm::SHA256, &ScanOptions::new())
+            .unwrap();
+        let mut writer = ManifestWriter::new(Vec::new(), HashAlgorithm::SHA256).unwrap();
+        for (path, gitoid) in scan.files() {
+            writer.write_entry(path, *gitoid).unwrap();
+        }
+        let tree = GitOid::new_object(HashAlgorithm::SHA256, ObjectType::Tree, b"");
+        writer.write_entry("sub/c d", tree).unwrap();
+        let manifest = writer.finish().unwrap();
+
+        let text = String::from_utf8(manifest.clone()).unwrap();
+        assert!(text.starts_with("gitoid:manifest:sha256\nblob "));
+        assert!(text.ends_with(&format!("tree {} sub/c d\n", tree.hex_hash())));
+
+        let reader = ManifestReader::new(&manifest[..]).unwrap();
+        assert_eq!(HashAlgorithm::SHA256, reader.hash_algorithm());
+        let entries: Vec<(PathBuf, GitOid)> = reader.collect::<IOResult<_>>().unwrap();
+        assert_eq!(scan.files(), &entries[..3]);
+        assert_eq!((PathBuf::from("sub/c d"), tree), entries[3]);
+    }
+
+    #[test]
+    fn test_manifest_order_is_checked() {
+        let gitoid = GitOid::new(HashAlgorithm::SHA256, b"a");
+        let mut writer = ManifestWriter::new(Vec::new(), HashAlgorithm::SHA256).unwrap();
+        writer.write_entry("b", gitoid).unwrap();
+        let error = writer.write_entry("a", gitoid).unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+        assert!(writer.write_entry("line\nbreak", gitoid).is_err());
+
+        let unsorted = format!(
+            "gitoid:manifest:sha256\nblob {hex} b\nblob {hex} a\n",
+            hex = gitoid.hex_hash()
+        );
+        let error = ManifestReader::new(unsorted.as_bytes())
+            .unwrap()
+            .find_map(Result::err)
+            .unwrap();
+        assert_eq!(Some(3), ErrorContext::from_io_error(&error).unwrap().line());
+    }
+}
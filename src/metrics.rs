@@ -0,0 +1,115 @@
+use crate::{GitOid, GitOidHasher};
+use std::io::{Error, ErrorKind, Result as IOResult};
+use std::sync::OnceLock;
+
+/// Counters the crate's major operations report into, so a host
+/// application can wire them to e.g. Prometheus.
+///
+/// Every method has a no-op default, so an implementation only needs
+/// the ones it cares about. Methods are called on the hashing path and
+/// should be cheap; an atomic increment is the expected implementation
+pub trait Metrics: Send + Sync {
+    /// `bytes` of content were hashed into a `GitOid`. Documents, trees
+    /// and other objects the crate builds from gitoids aren't counted
+    fn bytes_hashed(&self, _bytes: u64) {}
+
+    /// A file was read from disk and hashed
+    fn files_hashed(&self, _count: u64) {}
+
+    /// An operation failed, e.g. an I/O error, a length mismatch or a
+    /// failed integrity check
+    fn errors(&self, _count: u64) {}
+}
+
+struct NoMetrics;
+
+impl Metrics for NoMetrics {}
+
+static METRICS: OnceLock<Box<dyn Metrics>> = OnceLock::new();
+
+/// Register the process-wide `Metrics` implementation. This can only be
+/// done once; later calls return an `Err` of kind `AlreadyExists`
+pub fn set_metrics(metrics: Box<dyn Metrics>) -> IOResult<()> {
+    METRICS.set(metrics).map_err(|_| {
+        Error::new(
+            ErrorKind::AlreadyExists,
+            "A Metrics implementation is already registered",
+        )
+    })
+}
+
+/// The registered `Metrics`, or one that ignores everything
+pub(crate) fn metrics() -> &'static dyn Metrics {
+    match METRICS.get() {
+        Some(metrics) => metrics.as_ref(),
+        None => &NoMetrics,
+    }
+}
+
+/// Count the error, if any, and pass the result on
+pub(crate) fn count_error<T>(result: IOResult<T>) -> IOResult<T> {
+    if result.is_err() {
+        metrics().errors(1);
+    }
+    result
+}
+
+/// Finalize `hasher`, counting its content in `bytes_hashed` if that
+/// succeeds. Only the entry points that hash content use this
+pub(crate) fn finalize_content(hasher: GitOidHasher) -> IOResult<GitOid> {
+    let len = hasher.amount_hashed();
+    let gitoid = hasher.finalize()?;
+    metrics().bytes_hashed(len);
+    Ok(gitoid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GitOid, HashAlgorithm};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    #[derive(Default)]
+    struct Counters {
+        bytes: AtomicU64,
+        files: AtomicU64,
+        errors: AtomicU64,
+    }
+
+    impl Metrics for Arc<Counters> {
+        fn bytes_hashed(&self, bytes: u64) {
+            self.bytes.fetch_add(bytes, Ordering::Relaxed);
+        }
+
+        fn files_hashed(&self, count: u64) {
+            self.files.fetch_add(count, Ordering::Relaxed);
+        }
+
+        fn errors(&self, count: u64) {
+            self.errors.fetch_add(count, Ordering::Relaxed);
+        }
+    }
+
+    // metrics are process wide and other tests run concurrently, so this
+    // is the only test that registers them, and it only checks lower bounds
+    #[test]
+    fn test_operations_report_metrics() {
+        let counters = Arc::new(Counters::default());
+        set_metrics(Box::new(counters.clone())).unwrap();
+        assert_eq!(
+            ErrorKind::AlreadyExists,
+            set_metrics(Box::new(NoMetrics)).unwrap_err().kind()
+        );
+
+        GitOid::new(HashAlgorithm::SHA256, b"hello world");
+        assert!(counters.bytes.load(Ordering::Relaxed) >= 11);
+
+        GitOid::tree_from_dir(HashAlgorithm::SHA256, "test/data/tree").unwrap();
+        assert!(counters.files.load(Ordering::Relaxed) >= 3);
+
+        let reader = std::io::BufReader::new("hello".as_bytes());
+        assert!(GitOid::new_from_reader(HashAlgorithm::SHA256, reader, 11).is_err());
+        assert!(counters.errors.load(Ordering::Relaxed) >= 1);
+    }
+}
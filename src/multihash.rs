@@ -0,0 +1,147 @@
+use crate::{GitOid, HashAlgorithm, ObjectType};
+use std::io::{Error, ErrorKind, Result as IOResult};
+
+/// The [multicodec](https://github.com/multiformats/multicodec/blob/master/table.csv)
+/// hash function codes for the algorithms we support
+#[cfg(feature = "sha1")]
+const MULTIHASH_SHA1: u64 = 0x11;
+const MULTIHASH_SHA2_256: u64 = 0x12;
+
+impl HashAlgorithm {
+    /// The multihash function code for this algorithm
+    pub fn multihash_code(&self) -> u64 {
+        match self {
+            #[cfg(feature = "sha1")]
+            HashAlgorithm::SHA1 => MULTIHASH_SHA1,
+            HashAlgorithm::SHA256 => MULTIHASH_SHA2_256,
+        }
+    }
+
+    /// Find the algorithm for a multihash function code
+    pub fn from_multihash_code(code: u64) -> Option<HashAlgorithm> {
+        match code {
+            #[cfg(feature = "sha1")]
+            MULTIHASH_SHA1 => Some(HashAlgorithm::SHA1),
+            MULTIHASH_SHA2_256 => Some(HashAlgorithm::SHA256),
+            _ => None,
+        }
+    }
+}
+
+impl GitOid {
+    /// Encode the hash as a [multihash](https://multiformats.io/multihash/):
+    /// the varint function code, the varint digest length, then the digest.
+    ///
+    /// A multihash doesn't record the git object type, so it isn't included
+    pub fn to_multihash(&self) -> Vec<u8> {
+        let mut ret = Vec::with_capacity(self.len + 2);
+        write_varint(&mut ret, self.hash_algorithm.multihash_code());
+        write_varint(&mut ret, self.len as u64);
+        ret.extend_from_slice(self.hash_value());
+        ret
+    }
+
+    /// Decode a multihash into a `GitOid` of the given object type.
+    /// Will return an `Err` for unsupported hash functions, bad lengths,
+    /// or trailing bytes
+    pub fn from_multihash(object_type: ObjectType, multihash: &[u8]) -> IOResult<GitOid> {
+        let (code, rest) = read_varint(multihash)?;
+        let hash_algo = HashAlgorithm::from_multihash_code(code).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Unsupported multihash function code 0x{:x}", code),
+            )
+        })?;
+
+        let (len, digest) = read_varint(rest)?;
+        if len != digest.len() as u64 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Multihash says {} digest bytes but has {}",
+                    len,
+                    digest.len()
+                ),
+            ));
+        }
+
+        GitOid::from_raw(hash_algo, object_type, digest)
+    }
+}
+
+/// write an [unsigned varint](https://github.com/multiformats/unsigned-varint)
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// read an unsigned varint, returning it and the rest of the input.
+/// The spec caps varints at 9 bytes
+fn read_varint(input: &[u8]) -> IOResult<(u64, &[u8])> {
+    let mut value: u64 = 0;
+    for (i, byte) in input.iter().enumerate().take(9) {
+        value |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((value, &input[i + 1..]));
+        }
+    }
+    Err(Error::new(
+        ErrorKind::InvalidData,
+        "Truncated or overlong multihash varint",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256_round_trip() {
+        let gitoid = GitOid::new(HashAlgorithm::SHA256, b"hello world");
+        let multihash = gitoid.to_multihash();
+
+        assert_eq!(&[0x12, 0x20], &multihash[..2]);
+        assert_eq!(gitoid.hash_value(), &multihash[2..]);
+        assert_eq!(
+            gitoid,
+            GitOid::from_multihash(ObjectType::Blob, &multihash).unwrap()
+        );
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_sha1_round_trip() {
+        let gitoid = GitOid::new(HashAlgorithm::SHA1, b"hello world");
+        let multihash = gitoid.to_multihash();
+
+        assert_eq!(
+            "111495d09f2b10159347eece71399a7e2e907ea3df4f",
+            hex::encode(&multihash)
+        );
+        assert_eq!(
+            gitoid,
+            GitOid::from_multihash(ObjectType::Blob, &multihash).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_rejects_bad_input() {
+        // sha2-512 isn't supported
+        assert!(GitOid::from_multihash(ObjectType::Blob, &[0x13, 0x01, 0x00]).is_err());
+        // length doesn't match
+        assert!(GitOid::from_multihash(ObjectType::Blob, &[0x12, 0x20, 0x00]).is_err());
+        // truncated varint
+        assert!(GitOid::from_multihash(ObjectType::Blob, &[0x92]).is_err());
+    }
+
+    #[test]
+    fn test_varint() {
+        let mut out = Vec::new();
+        write_varint(&mut out, 300);
+        assert_eq!(vec![0xac, 0x02], out);
+        assert_eq!((300, &[][..]), read_varint(&out).unwrap());
+    }
+}
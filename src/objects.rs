@@ -0,0 +1,349 @@
+use crate::{GitOid, HashAlgorithm, ObjectType};
+use std::fmt::{Display, Formatter, Result};
+use std::io::{Error, ErrorKind, Result as IOResult};
+
+/// The identity and time recorded in a commit's author and committer
+/// lines and in a tag's tagger line
+#[derive(Clone, PartialOrd, Eq, Ord, Debug, Hash, PartialEq)]
+pub struct Signature {
+    name: String,
+    email: String,
+    time: i64,
+    utc_offset_minutes: i32,
+}
+
+impl Signature {
+    /// `time` is in seconds since the Unix epoch and the offset is
+    /// the signer's timezone, e.g. `-420` for `-0700`. A name or email
+    /// with a line break, `<` or `>` can't be written, so building a
+    /// payload with it fails
+    pub fn new(name: &str, email: &str, time: i64, utc_offset_minutes: i32) -> Self {
+        Signature {
+            name: name.to_string(),
+            email: email.to_string(),
+            time,
+            utc_offset_minutes,
+        }
+    }
+}
+
+impl Display for Signature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let sign = if self.utc_offset_minutes < 0 {
+            '-'
+        } else {
+            '+'
+        };
+        let offset = self.utc_offset_minutes.unsigned_abs();
+        write!(
+            f,
+            "{} <{}> {} {}{:02}{:02}",
+            self.name,
+            self.email,
+            self.time,
+            sign,
+            offset / 60,
+            offset % 60
+        )
+    }
+}
+
+/// Builds the payload of a git commit object so its `GitOid` can be
+/// computed without a git repository
+#[derive(Clone, Debug)]
+pub struct CommitBuilder {
+    tree: GitOid,
+    parents: Vec<GitOid>,
+    author: Signature,
+    committer: Signature,
+    message: String,
+}
+
+impl CommitBuilder {
+    /// Start a commit of `tree`. The committer defaults to the author
+    pub fn new(tree: GitOid, author: Signature) -> Self {
+        CommitBuilder {
+            tree,
+            parents: Vec::new(),
+            committer: author.clone(),
+            author,
+            message: String::new(),
+        }
+    }
+
+    /// Add a parent commit. Parents are recorded in the order they're added
+    pub fn parent(mut self, parent: GitOid) -> Self {
+        self.parents.push(parent);
+        self
+    }
+
+    /// Set the committer
+    pub fn committer(mut self, committer: Signature) -> Self {
+        self.committer = committer;
+        self
+    }
+
+    /// Set the commit message. git messages usually end with a newline
+    pub fn message(mut self, message: &str) -> Self {
+        self.message = message.to_string();
+        self
+    }
+
+    /// The bytes of the commit object, without the `commit {len}\0` prefix.
+    /// Will return an `Err` if the tree or a parent has the wrong object
+    /// type or they don't all use the same hash algorithm, or if a
+    /// signature's name or email has a line break, `<` or `>`
+    pub fn payload(&self) -> IOResult<Vec<u8>> {
+        let hash_algo = self.tree.hash_algorithm();
+        check_object(&self.tree, ObjectType::Tree, hash_algo)?;
+        check_signature(&self.author)?;
+        check_signature(&self.committer)?;
+
+        let mut ret = format!("tree {}\n", self.tree.hex_hash());
+        for parent in &self.parents {
+            check_object(parent, ObjectType::Commit, hash_algo)?;
+            ret.push_str(&format!("parent {}\n", parent.hex_hash()));
+        }
+        ret.push_str(&format!("author {}\n", self.author));
+        ret.push_str(&format!("committer {}\n", self.committer));
+        ret.push('\n');
+        ret.push_str(&self.message);
+        Ok(ret.into_bytes())
+    }
+
+    /// Hash the commit with the tree's hash algorithm
+    pub fn gitoid(&self) -> IOResult<GitOid> {
+        let payload = self.payload()?;
+        Ok(GitOid::hash_object(
+            self.tree.hash_algorithm(),
+            ObjectType::Commit,
+            &payload,
+        ))
+    }
+}
+
+/// Builds the payload of an annotated git tag object so its `GitOid`
+/// can be computed without a git repository
+#[derive(Clone, Debug)]
+pub struct TagBuilder {
+    object: GitOid,
+    name: String,
+    tagger: Option<Signature>,
+    message: String,
+}
+
+impl TagBuilder {
+    /// Start a tag called `name` pointing at `object`
+    pub fn new(object: GitOid, name: &str) -> Self {
+        TagBuilder {
+            object,
+            name: name.to_string(),
+            tagger: None,
+            message: String::new(),
+        }
+    }
+
+    /// Set the tagger. git has always written one, but some very old
+    /// tags don't have it
+    pub fn tagger(mut self, tagger: Signature) -> Self {
+        self.tagger = Some(tagger);
+        self
+    }
+
+    /// Set the tag message
+    pub fn message(mut self, message: &str) -> Self {
+        self.message = message.to_string();
+        self
+    }
+
+    /// The bytes of the tag object, without the `tag {len}\0` prefix.
+    /// Will return an `Err` if the name has a line break, or the tagger's
+    /// name or email has a line break, `<` or `>`
+    pub fn payload(&self) -> IOResult<Vec<u8>> {
+        check_header_value("tag name", &self.name, &['\n'])?;
+        if let Some(tagger) = &self.tagger {
+            check_signature(tagger)?;
+        }
+
+        let mut ret = format!(
+            "object {}\ntype {}\ntag {}\n",
+            self.object.hex_hash(),
+            self.object.object_type(),
+            self.name
+        );
+        if let Some(tagger) = &self.tagger {
+            ret.push_str(&format!("tagger {}\n", tagger));
+        }
+        ret.push('\n');
+        ret.push_str(&self.message);
+        Ok(ret.into_bytes())
+    }
+
+    /// Hash the tag with the tagged object's hash algorithm
+    pub fn gitoid(&self) -> IOResult<GitOid> {
+        let payload = self.payload()?;
+        Ok(GitOid::hash_object(
+            self.object.hash_algorithm(),
+            ObjectType::Tag,
+            &payload,
+        ))
+    }
+}
+
+fn check_object(
+    gitoid: &GitOid,
+    object_type: ObjectType,
+    hash_algo: HashAlgorithm,
+) -> IOResult<()> {
+    if gitoid.object_type() != object_type {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Expected a {} but {} is a {}",
+                object_type,
+                gitoid,
+                gitoid.object_type()
+            ),
+        ));
+    }
+    if gitoid.hash_algorithm() != hash_algo {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Expected a {} hash but got {}", hash_algo, gitoid),
+        ));
+    }
+    Ok(())
+}
+
+/// a signature that would write extra header lines, or end its name or
+/// email early, would hash to an object git never writes
+fn check_signature(signature: &Signature) -> IOResult<()> {
+    check_header_value("name", &signature.name, &['\n', '<', '>'])?;
+    check_header_value("email", &signature.email, &['\n', '<', '>'])
+}
+
+fn check_header_value(what: &str, value: &str, forbidden: &[char]) -> IOResult<()> {
+    if value.contains(forbidden) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("The {} {:?} can't be written in a git object", what, value),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "sha1"))]
+mod tests {
+    use super::*;
+
+    fn author() -> Signature {
+        Signature::new("A U Thor", "author@example.com", 1112911993, -420)
+    }
+
+    fn committer() -> Signature {
+        Signature::new("C O Mitter", "committer@example.com", 1112912053, 90)
+    }
+
+    fn tree() -> GitOid {
+        GitOid::tree_from_dir(HashAlgorithm::SHA1, "test/data/tree").unwrap()
+    }
+
+    fn commit(parent: Option<GitOid>, message: &str) -> GitOid {
+        let mut builder = CommitBuilder::new(tree(), author())
+            .committer(committer())
+            .message(message);
+        if let Some(parent) = parent {
+            builder = builder.parent(parent);
+        }
+        builder.gitoid().unwrap()
+    }
+
+    // the expected hashes come from `git commit-tree` and `git hash-object -t tag`
+    #[test]
+    fn test_commit_gitoid() {
+        let first = commit(None, "initial\n");
+        assert_eq!("61a3e47f373c08fcd746648a40d0197c2cee6a03", first.hex_hash());
+        assert_eq!(ObjectType::Commit, first.object_type());
+
+        let second = commit(Some(first), "second\n");
+        assert_eq!(
+            "317a4d2b120b369d86b84f8d38c64134da848f88",
+            second.hex_hash()
+        );
+    }
+
+    #[test]
+    fn test_commit_rejects_blob_as_tree() {
+        let blob = GitOid::new_from_str("not a tree");
+
+        assert!(CommitBuilder::new(blob, author()).gitoid().is_err());
+    }
+
+    #[test]
+    fn test_commit_rejects_mixed_algorithms() {
+        let parent = CommitBuilder::new(
+            GitOid::tree_from_dir(HashAlgorithm::SHA256, "test/data/tree").unwrap(),
+            author(),
+        )
+        .gitoid()
+        .unwrap();
+
+        assert!(CommitBuilder::new(tree(), author())
+            .parent(parent)
+            .gitoid()
+            .is_err());
+    }
+
+    #[test]
+    fn test_tag_gitoid() {
+        let second = commit(Some(commit(None, "initial\n")), "second\n");
+
+        let tag = TagBuilder::new(second, "v1.0")
+            .tagger(committer())
+            .message("release 1.0\n")
+            .gitoid()
+            .unwrap();
+
+        assert_eq!("d9f676feac1022158d24b5f25ab4b262f3e3052a", tag.hex_hash());
+        assert_eq!(ObjectType::Tag, tag.object_type());
+    }
+
+    #[test]
+    fn test_header_injection_is_rejected() {
+        let injected = Signature::new(
+            "A U Thor",
+            &format!("a@example.com> 0 +0000\nparent {}\nx <x", tree().hex_hash()),
+            1112911993,
+            -420,
+        );
+        let error = CommitBuilder::new(tree(), injected.clone())
+            .gitoid()
+            .unwrap_err();
+        assert_eq!(ErrorKind::InvalidInput, error.kind());
+        let bad_name = Signature::new("A <U> Thor", "author@example.com", 0, 0);
+        assert!(CommitBuilder::new(tree(), author())
+            .committer(bad_name)
+            .payload()
+            .is_err());
+
+        let commit = commit(None, "initial\n");
+        assert!(TagBuilder::new(commit, "v1.0\ntagger x").payload().is_err());
+        assert!(TagBuilder::new(commit, "v1.0")
+            .tagger(injected)
+            .gitoid()
+            .is_err());
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_signature_display() {
+        assert_eq!(
+            "A U Thor <author@example.com> 1112911993 -0700",
+            author().to_string()
+        );
+        assert_eq!(
+            "C O Mitter <committer@example.com> 1112912053 +0130",
+            committer().to_string()
+        );
+    }
+}
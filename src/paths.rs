@@ -0,0 +1,98 @@
+use crate::{DirectoryScan, GitOid};
+use std::io::{Error, ErrorKind, Result as IOResult};
+use std::path::{Component, Path};
+
+/// Turn `path` into the form recorded in metadata: relative to `root`,
+/// with `/` separators and no `.` or `..` components, so the same tree
+/// gives the same strings on every OS. A relative `path` is taken to be
+/// relative to `root` already.
+///
+/// Will return an `Err` if `path` is outside `root` or isn't UTF-8.
+/// Unicode normalization isn't applied, so names that macOS stored
+/// decomposed stay decomposed
+pub fn normalize_path<P: AsRef<Path>, Q: AsRef<Path>>(root: P, path: Q) -> IOResult<String> {
+    let (root, path) = (root.as_ref(), path.as_ref());
+    let relative = if path.is_absolute() {
+        path.strip_prefix(root).map_err(|_| outside(root, path))?
+    } else {
+        path
+    };
+
+    let mut parts: Vec<&str> = Vec::new();
+    for component in relative.components() {
+        match component {
+            Component::Normal(name) => parts.push(name.to_str().ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("{} isn't UTF-8", path.display()),
+                )
+            })?),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if parts.pop().is_none() {
+                    return Err(outside(root, path));
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return Err(outside(root, path)),
+        }
+    }
+    Ok(parts.join("/"))
+}
+
+fn outside(root: &Path, path: &Path) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!("{} is outside {}", path.display(), root.display()),
+    )
+}
+
+impl DirectoryScan {
+    /// Each file hashed, with its path normalized by `normalize_path`
+    /// and sorted by that string, ready to record as metadata. Will
+    /// return an `Err` if a path isn't UTF-8
+    pub fn normalized_files(&self) -> IOResult<Vec<(String, GitOid)>> {
+        let mut ret = self
+            .files()
+            .iter()
+            .map(|(path, gitoid)| Ok((normalize_path("", path)?, *gitoid)))
+            .collect::<IOResult<Vec<_>>>()?;
+        ret.sort();
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HashAlgorithm, ScanOptions};
+
+    #[test]
+    fn test_normalize_path() {
+        let root = std::env::temp_dir().join("root");
+
+        assert_eq!(
+            "sub/b.txt",
+            normalize_path(&root, root.join("./sub/b.txt")).unwrap()
+        );
+        assert_eq!("b.txt", normalize_path(&root, "sub/../b.txt").unwrap());
+        assert_eq!(
+            ErrorKind::InvalidInput,
+            normalize_path(&root, "../b.txt").unwrap_err().kind()
+        );
+        assert!(normalize_path(&root, std::env::temp_dir().join("other")).is_err());
+    }
+
+    #[test]
+    fn test_normalized_files() {
+        let scan = DirectoryScan::new("test/data/tree", HashAlgorithm::SHA256, &ScanOptions::new())
+            .unwrap();
+        let paths: Vec<String> = scan
+            .normalized_files()
+            .unwrap()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+
+        assert_eq!(vec!["a.txt", "sub.txt", "sub/b.txt"], paths);
+    }
+}
@@ -0,0 +1,81 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+
+/// The most buffers kept for reuse. Enough for one per thread of a
+/// parallel scan without holding on to memory after a burst
+const MAX_POOLED: usize = 64;
+
+/// Buffers with more capacity than this are freed rather than pooled, so
+/// one scan with a large `buffer_size` doesn't pin that much memory per
+/// pooled buffer for the life of the process
+const MAX_POOLED_CAPACITY: usize = 64 * 1024;
+
+/// read buffers given back by finished reads, shared by every thread
+static POOL: Mutex<Vec<Vec<u8>>> = Mutex::new(Vec::new());
+
+/// A read buffer taken from a process-wide pool and given back when
+/// dropped, so hashing many files doesn't allocate a buffer per file
+pub(crate) struct PooledBuffer {
+    buf: Vec<u8>,
+}
+
+impl PooledBuffer {
+    /// Take a buffer of `size` bytes from the pool, allocating one if
+    /// the pool is empty
+    pub(crate) fn new(size: usize) -> Self {
+        let mut buf = POOL
+            .lock()
+            .ok()
+            .and_then(|mut pool| pool.pop())
+            .unwrap_or_default();
+        buf.resize(size, 0);
+        PooledBuffer { buf }
+    }
+}
+
+impl Deref for PooledBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl DerefMut for PooledBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.buf
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        if let Ok(mut pool) = POOL.lock() {
+            if pool.len() < MAX_POOLED && self.buf.capacity() <= MAX_POOLED_CAPACITY {
+                pool.push(std::mem::take(&mut self.buf));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffers_are_resized() {
+        let mut large = PooledBuffer::new(4096);
+        large.fill(0xff);
+        drop(large);
+
+        assert_eq!(16, PooledBuffer::new(16).len());
+        assert_eq!(65536, PooledBuffer::new(65536).len());
+    }
+
+    #[test]
+    fn test_large_buffers_are_not_pooled() {
+        drop(PooledBuffer::new(MAX_POOLED_CAPACITY * 16));
+
+        let pool = POOL.lock().unwrap();
+        assert!(pool.iter().all(|buf| buf.capacity() <= MAX_POOLED_CAPACITY));
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{AliasTable, GitBom, GitOid};
+use std::collections::HashMap;
+use std::io::{Result as IOResult, Write};
+
+/// How `GitBom::pretty` renders oids
+#[derive(Clone, Debug, Default)]
+pub struct PrettyOptions {
+    short_hashes: bool,
+    names: HashMap<GitOid, String>,
+}
+
+impl PrettyOptions {
+    /// The defaults: full hashes and no names
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Show the first 12 hex digits of each hash, like `git log --oneline`
+    pub fn short_hashes(mut self, short: bool) -> Self {
+        self.short_hashes = short;
+        self
+    }
+
+    /// Show `name` (e.g. a file name) next to `gitoid`
+    pub fn name<S: ToString>(mut self, gitoid: GitOid, name: S) -> Self {
+        self.names.insert(gitoid, name.to_string());
+        self
+    }
+
+    /// Show the names from `names` next to their gitoids
+    pub fn names<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = (GitOid, S)>,
+        S: ToString,
+    {
+        for (gitoid, name) in names {
+            self.names.insert(gitoid, name.to_string());
+        }
+        self
+    }
+
+    /// Show each gitoid's names from `aliases`, comma-separated, next to
+    /// it
+    pub fn aliases(mut self, aliases: &AliasTable) -> Self {
+        for (gitoid, names) in aliases.iter() {
+            self.names.insert(gitoid, names.join(", "));
+        }
+        self
+    }
+}
+
+impl GitBom {
+    /// Write a summary of the `GitBom` for people to read: a count line,
+    /// then one line per oid, sorted by name and then hash.
+    ///
+    /// This output is for inspection only and may change between
+    /// versions; use `to_document` for the canonical form
+    pub fn pretty<W: Write>(&self, mut out: W, options: &PrettyOptions) -> IOResult<()> {
+        let mut rows: Vec<(Option<&str>, GitOid)> = self
+            .get_sorted_oids()
+            .into_iter()
+            .map(|gitoid| (options.names.get(&gitoid).map(String::as_str), gitoid))
+            .collect();
+        // named oids first
+        rows.sort_by(|(a_name, a), (b_name, b)| {
+            (a_name.is_none(), a_name, a).cmp(&(b_name.is_none(), b_name, b))
+        });
+
+        writeln!(
+            out,
+            "GitBom with {} oid{}",
+            rows.len(),
+            if rows.len() == 1 { "" } else { "s" }
+        )?;
+        let mut hex_buf = [0; 64];
+        for (name, gitoid) in rows {
+            // Display for ObjectType ignores padding, so pad its name
+            let object_type = gitoid.object_type().document_name();
+            let hex = gitoid.hex_hash_into(&mut hex_buf);
+            let hex = if options.short_hashes {
+                &hex[..12]
+            } else {
+                hex
+            };
+            match name {
+                Some(name) => writeln!(
+                    out,
+                    "  {} {:<6} {}  {}",
+                    gitoid.hash_algorithm(),
+                    object_type,
+                    hex,
+                    name
+                )?,
+                None => writeln!(
+                    out,
+                    "  {} {:<6} {}",
+                    gitoid.hash_algorithm(),
+                    object_type,
+                    hex
+                )?,
+            }
+        }
+        out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HashAlgorithm;
+
+    #[test]
+    fn test_pretty() {
+        let hello = GitOid::new(HashAlgorithm::SHA256, b"hello world");
+        let empty = GitOid::new(HashAlgorithm::SHA256, b"");
+        let gitbom = GitBom::new().add(hello).add(empty);
+
+        let mut out = Vec::new();
+        gitbom
+            .pretty(
+                &mut out,
+                &PrettyOptions::new()
+                    .short_hashes(true)
+                    .name(hello, "hello.txt"),
+            )
+            .unwrap();
+
+        assert_eq!(
+            "GitBom with 2 oids\n  SHA256 blob   fee53a18d328  hello.txt\n  SHA256 blob   473a0f4c3be8\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_pretty_aliases() {
+        let hello = GitOid::new(HashAlgorithm::SHA256, b"hello world");
+        let empty = GitOid::new(HashAlgorithm::SHA256, b"");
+        let gitbom = GitBom::new().add(hello).add(empty);
+
+        let mut aliases = AliasTable::new(HashAlgorithm::SHA256);
+        aliases.insert(hello, "hello.txt").unwrap();
+        aliases.insert(hello, "greeting.txt").unwrap();
+
+        let mut out = Vec::new();
+        gitbom
+            .pretty(
+                &mut out,
+                &PrettyOptions::new().short_hashes(true).aliases(&aliases),
+            )
+            .unwrap();
+
+        assert_eq!(
+            "GitBom with 2 oids\n  SHA256 blob   fee53a18d328  greeting.txt, hello.txt\n  SHA256 blob   473a0f4c3be8\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+}
@@ -0,0 +1,306 @@
+use crate::document::bad_document;
+use crate::{ArtifactSource, GitBom, GitOid, HashAlgorithm, ObjectType};
+use std::collections::{HashSet, VecDeque};
+use std::io::{BufRead, BufReader, Error, ErrorKind, Read, Result as IOResult};
+
+/// The chain of documents from a root document down to one that lists
+/// an artifact, so someone who trusts the root's gitoid can check that
+/// the artifact is in its graph without fetching the rest of it.
+///
+/// Each document lists the gitoid of the next; the last lists the
+/// artifact. Serialized as the documents themselves, root first, each
+/// preceded by its length:
+///
+/// ```text
+/// gitoid:proof:sha256
+/// document <len>
+/// <document bytes>
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct InclusionProof {
+    hash_algo: HashAlgorithm,
+    documents: Vec<Vec<u8>>,
+}
+
+impl InclusionProof {
+    /// Find the shortest chain of documents from `root` to a document
+    /// listing `artifact`, fetching each from `source` and searching the
+    /// graph breadth first. Entries that `source` doesn't have or that
+    /// aren't documents are leaves.
+    ///
+    /// Will return an `Err` of kind `NotFound` if `artifact` isn't in the
+    /// graph, or any error fetching or parsing `root` itself
+    pub fn generate<S: ArtifactSource + ?Sized>(
+        root: &GitOid,
+        artifact: &GitOid,
+        source: &S,
+    ) -> IOResult<Self> {
+        let document = fetch_document(source, root)?.ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("{} isn't a GitBOM document", root),
+            )
+        })?;
+
+        // every document fetched, with the index of the one listing it
+        let mut found: Vec<(Vec<u8>, Option<usize>)> = vec![(document, None)];
+        let mut seen = HashSet::from([*root]);
+        let mut queue = VecDeque::from([0]);
+        while let Some(index) = queue.pop_front() {
+            let gitbom = match GitBom::from_document(&found[index].0) {
+                Ok(gitbom) => gitbom,
+                Err(error) if index == 0 => return Err(error),
+                // a blob that only starts like a document is a leaf
+                Err(_) => continue,
+            };
+            if gitbom.contains(artifact) {
+                return Ok(InclusionProof {
+                    hash_algo: root.hash_algorithm(),
+                    documents: chain(found, index),
+                });
+            }
+
+            for gitoid in gitbom.get_sorted_oids() {
+                if gitoid.object_type() != ObjectType::Blob || !seen.insert(gitoid) {
+                    continue;
+                }
+                match fetch_document(source, &gitoid) {
+                    Ok(Some(document)) => {
+                        found.push((document, Some(index)));
+                        queue.push_back(found.len() - 1);
+                    }
+                    Ok(None) => {}
+                    Err(error) if error.kind() == ErrorKind::NotFound => {}
+                    Err(error) => return Err(error),
+                }
+            }
+        }
+
+        Err(Error::new(
+            ErrorKind::NotFound,
+            format!("{} isn't in the graph of {}", artifact, root),
+        ))
+    }
+
+    /// The documents in the chain, root first
+    pub fn documents(&self) -> &[Vec<u8>] {
+        &self.documents
+    }
+
+    /// Serialize the proof
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut ret = format!("gitoid:proof:{}\n", self.hash_algo.document_name()).into_bytes();
+        for document in &self.documents {
+            ret.extend_from_slice(format!("document {}\n", document.len()).as_bytes());
+            ret.extend_from_slice(document);
+        }
+        ret
+    }
+
+    /// Parse a proof written by `to_bytes`. The documents aren't checked
+    /// against each other until the proof is verified
+    pub fn from_bytes(bytes: &[u8]) -> IOResult<Self> {
+        let (header, mut rest) = split_line(bytes)
+            .ok_or_else(|| bad_document("missing gitoid:proof:<algorithm> header"))?;
+        let hash_algo = header
+            .strip_prefix("gitoid:proof:")
+            .and_then(HashAlgorithm::from_document_name)
+            .ok_or_else(|| bad_document("missing gitoid:proof:<algorithm> header"))?;
+
+        let mut documents = Vec::new();
+        while !rest.is_empty() {
+            let (line, after) = split_line(rest).ok_or_else(|| bad_document("bad proof line"))?;
+            let len: usize = line
+                .strip_prefix("document ")
+                .and_then(|len| len.parse().ok())
+                .ok_or_else(|| bad_document(&format!("bad proof line {:?}", line)))?;
+            if after.len() < len {
+                return Err(bad_document("truncated proof"));
+            }
+            documents.push(after[..len].to_vec());
+            rest = &after[len..];
+        }
+
+        Ok(InclusionProof {
+            hash_algo,
+            documents,
+        })
+    }
+}
+
+/// Check that `proof` shows `artifact` is in the graph of the document
+/// `root`, using nothing but the proof: each document is rehashed, the
+/// first must be `root` and each must list the next, and the last must
+/// list `artifact`. Consumers that only trust the root's gitoid can run
+/// this offline.
+///
+/// Will return an `Err` of kind `InvalidData` naming the first link that
+/// doesn't hold
+pub fn verify_inclusion(root: &GitOid, artifact: &GitOid, proof: &InclusionProof) -> IOResult<()> {
+    let broken = |msg: String| Err(Error::new(ErrorKind::InvalidData, msg));
+    let hash_algo = root.hash_algorithm();
+    if proof.hash_algo != hash_algo || proof.documents.is_empty() {
+        return broken(format!("Proof isn't a {} proof for {}", hash_algo, root));
+    }
+
+    let mut expected = *root;
+    for (index, document) in proof.documents.iter().enumerate() {
+        let actual = GitOid::hash_object(hash_algo, ObjectType::Blob, document);
+        if actual != expected {
+            return broken(format!("Proof has {} where {} should be", actual, expected));
+        }
+        // each document links to the next, and the last to the artifact
+        expected = match proof.documents.get(index + 1) {
+            Some(next) => GitOid::hash_object(hash_algo, ObjectType::Blob, next),
+            None => *artifact,
+        };
+        if !GitBom::from_document(document)?.contains(&expected) {
+            return broken(format!("{} doesn't list {}", actual, expected));
+        }
+    }
+    Ok(())
+}
+
+/// the documents from the root down to `found[index]`
+fn chain(mut found: Vec<(Vec<u8>, Option<usize>)>, mut index: usize) -> Vec<Vec<u8>> {
+    let mut ret = Vec::new();
+    loop {
+        let (document, parent) = std::mem::take(&mut found[index]);
+        ret.push(document);
+        match parent {
+            Some(parent) => index = parent,
+            None => break,
+        }
+    }
+    ret.reverse();
+    ret
+}
+
+/// the first line of `bytes`, without its newline, and what follows it
+fn split_line(bytes: &[u8]) -> Option<(&str, &[u8])> {
+    let end = bytes.iter().position(|b| *b == b'\n')?;
+    Some((std::str::from_utf8(&bytes[..end]).ok()?, &bytes[end + 1..]))
+}
+
+/// fetch `gitoid`, returning `None` without reading the rest if it
+/// doesn't start like a document
+fn fetch_document<S: ArtifactSource + ?Sized>(
+    source: &S,
+    gitoid: &GitOid,
+) -> IOResult<Option<Vec<u8>>> {
+    let mut reader = BufReader::new(source.fetch(gitoid)?);
+    if !reader.fill_buf()?.starts_with(b"gitoid:blob:") {
+        return Ok(None);
+    }
+    let mut ret = Vec::new();
+    reader.read_to_end(&mut ret)?;
+    Ok(Some(ret))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct MemorySource(HashMap<GitOid, Vec<u8>>);
+
+    impl MemorySource {
+        fn add(&mut self, content: Vec<u8>) -> GitOid {
+            let gitoid = GitOid::new(HashAlgorithm::SHA256, &content);
+            self.0.insert(gitoid, content);
+            gitoid
+        }
+    }
+
+    impl ArtifactSource for MemorySource {
+        fn fetch(&self, gitoid: &GitOid) -> IOResult<Box<dyn Read>> {
+            match self.0.get(gitoid) {
+                Some(content) => Ok(Box::new(std::io::Cursor::new(content.clone()))),
+                None => Err(Error::new(ErrorKind::NotFound, "missing")),
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_proof() {
+        let mut source = MemorySource(HashMap::new());
+        let artifact = source.add(b"artifact".to_vec());
+        let other = source.add(b"other".to_vec());
+        let leaf = GitBom::new().add(artifact);
+        let leaf = source.add(leaf.to_document(HashAlgorithm::SHA256).unwrap());
+        let sibling = GitBom::new().add(other);
+        let sibling = source.add(sibling.to_document(HashAlgorithm::SHA256).unwrap());
+        let missing = GitOid::new(HashAlgorithm::SHA256, b"not fetchable");
+        let root = GitBom::new().add(sibling).add(leaf).add(missing);
+        let root = source.add(root.to_document(HashAlgorithm::SHA256).unwrap());
+
+        let proof = InclusionProof::generate(&root, &artifact, &source).unwrap();
+        let gitoids: Vec<GitOid> = proof
+            .documents()
+            .iter()
+            .map(|document| GitOid::new(HashAlgorithm::SHA256, document))
+            .collect();
+        assert_eq!(vec![root, leaf], gitoids);
+        assert!(verify_inclusion(&root, &artifact, &proof).is_ok());
+        let parsed = InclusionProof::from_bytes(&proof.to_bytes()).unwrap();
+        assert_eq!(proof, parsed);
+        assert!(verify_inclusion(&root, &artifact, &parsed).is_ok());
+
+        let unrelated = GitOid::new(HashAlgorithm::SHA256, b"unrelated");
+        assert_eq!(
+            ErrorKind::NotFound,
+            InclusionProof::generate(&root, &unrelated, &source)
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    #[test]
+    fn test_malformed_documents_are_leaves() {
+        let mut source = MemorySource(HashMap::new());
+        let artifact = source.add(b"artifact".to_vec());
+        let leaf = GitBom::new().add(artifact);
+        let leaf = source.add(leaf.to_document(HashAlgorithm::SHA256).unwrap());
+        let middle = GitBom::new().add(leaf);
+        let middle = source.add(middle.to_document(HashAlgorithm::SHA256).unwrap());
+        let malformed = source.add(b"gitoid:blob:sha256\nnot a document\n".to_vec());
+        let root = GitBom::new().add(middle).add(malformed);
+        let root = source.add(root.to_document(HashAlgorithm::SHA256).unwrap());
+
+        let proof = InclusionProof::generate(&root, &artifact, &source).unwrap();
+        assert_eq!(3, proof.documents().len());
+        assert!(verify_inclusion(&root, &artifact, &proof).is_ok());
+        assert_eq!(
+            ErrorKind::InvalidData,
+            InclusionProof::generate(&malformed, &artifact, &source)
+                .unwrap_err()
+                .kind()
+        );
+    }
+
+    #[test]
+    fn test_verify_rejects_broken_chains() {
+        let artifact = GitOid::new(HashAlgorithm::SHA256, b"artifact");
+        let leaf = GitBom::new().add(artifact);
+        let leaf_document = leaf.to_document(HashAlgorithm::SHA256).unwrap();
+        let root = GitBom::new().add(GitOid::new(HashAlgorithm::SHA256, b"other"));
+        let root_document = root.to_document(HashAlgorithm::SHA256).unwrap();
+        let root = GitOid::new(HashAlgorithm::SHA256, &root_document);
+        let proof = |documents: Vec<&Vec<u8>>| InclusionProof {
+            hash_algo: HashAlgorithm::SHA256,
+            documents: documents.into_iter().cloned().collect(),
+        };
+
+        // the root doesn't list the leaf
+        let err = verify_inclusion(
+            &root,
+            &artifact,
+            &proof(vec![&root_document, &leaf_document]),
+        )
+        .unwrap_err();
+        assert_eq!(ErrorKind::InvalidData, err.kind());
+        // the chain doesn't start at the root
+        assert!(verify_inclusion(&root, &artifact, &proof(vec![&leaf_document])).is_err());
+        assert!(verify_inclusion(&root, &artifact, &proof(vec![])).is_err());
+    }
+}
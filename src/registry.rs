@@ -0,0 +1,154 @@
+use crate::{HashAlgorithm, ObjectType};
+use sha2::digest::DynDigest;
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Read, Result as IOResult};
+
+type DigestFactory = Box<dyn Fn() -> Box<dyn DynDigest> + Send + Sync>;
+
+/// Maps algorithm names to digest factories, so plugins can add hash
+/// algorithms at runtime without a new `HashAlgorithm` variant.
+///
+/// This goes through `DynDigest` and returns plain digest bytes, since a
+/// `GitOid` only holds the built-in algorithms. Use `GitOid` and
+/// `GitOidHasher` for those; they're faster
+pub struct HashAlgorithmRegistry {
+    factories: HashMap<String, DigestFactory>,
+}
+
+impl Default for HashAlgorithmRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HashAlgorithmRegistry {
+    /// Create a registry with the built-in algorithms under their GitBOM
+    /// document names (`sha1`, `sha256`)
+    pub fn new() -> Self {
+        let mut ret = HashAlgorithmRegistry {
+            factories: HashMap::new(),
+        };
+        #[cfg(feature = "sha1")]
+        ret.register(HashAlgorithm::SHA1.document_name(), || {
+            HashAlgorithm::SHA1.create_digest()
+        });
+        ret.register(HashAlgorithm::SHA256.document_name(), || {
+            HashAlgorithm::SHA256.create_digest()
+        });
+        ret
+    }
+
+    /// Use `factory` to create digests for the algorithm `name`, replacing
+    /// any factory already registered for it
+    pub fn register<S, F>(&mut self, name: S, factory: F)
+    where
+        S: ToString,
+        F: Fn() -> Box<dyn DynDigest> + Send + Sync + 'static,
+    {
+        self.factories.insert(name.to_string(), Box::new(factory));
+    }
+
+    /// Is there an algorithm called `name`?
+    pub fn has_algorithm(&self, name: &str) -> bool {
+        self.factories.contains_key(name)
+    }
+
+    /// The registered algorithm names, sorted
+    pub fn names(&self) -> Vec<&str> {
+        let mut ret: Vec<&str> = self.factories.keys().map(String::as_str).collect();
+        ret.sort_unstable();
+        ret
+    }
+
+    /// Create a fresh digest for `name`. Will return an `Err` of kind
+    /// `Unsupported` if no algorithm has that name
+    pub fn create_digest(&self, name: &str) -> IOResult<Box<dyn DynDigest>> {
+        match self.factories.get(name) {
+            Some(factory) => Ok(factory()),
+            None => Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("No hash algorithm called {:?}", name),
+            )),
+        }
+    }
+
+    /// Hash `expected_length` bytes from `reader` as a git object of the
+    /// given type with the algorithm `name`, and return the digest. Will
+    /// return an `Err` if the algorithm is unknown, the reader fails or
+    /// the amount read isn't `expected_length`
+    pub fn hash_object<R: Read>(
+        &self,
+        name: &str,
+        object_type: ObjectType,
+        mut reader: R,
+        expected_length: usize,
+    ) -> IOResult<Box<[u8]>> {
+        let mut digest = self.create_digest(name)?;
+        digest.update(format!("{} {}\0", object_type, expected_length).as_bytes());
+
+        let mut buf = [0u8; 4096];
+        let mut amount_read: u64 = 0;
+        loop {
+            match reader.read(&mut buf)? {
+                0 => break,
+                size => {
+                    digest.update(&buf[..size]);
+                    amount_read = amount_read
+                        .checked_add(size as u64)
+                        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Length overflowed"))?;
+                }
+            }
+        }
+
+        if amount_read != expected_length as u64 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Expected length {} actual length {}",
+                    expected_length, amount_read
+                ),
+            ));
+        }
+        Ok(digest.finalize())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GitOid;
+    use sha2::{Digest, Sha512};
+
+    #[test]
+    fn test_builtins_match_gitoid() {
+        let registry = HashAlgorithmRegistry::new();
+        let digest = registry
+            .hash_object("sha256", ObjectType::Blob, &b"hello world"[..], 11)
+            .unwrap();
+
+        assert_eq!(
+            GitOid::new(HashAlgorithm::SHA256, b"hello world").hash_value(),
+            &digest[..]
+        );
+        assert!(registry
+            .hash_object("sha256", ObjectType::Blob, &b"hello"[..], 11)
+            .is_err());
+    }
+
+    #[test]
+    fn test_register_at_runtime() {
+        let mut registry = HashAlgorithmRegistry::new();
+        assert_eq!(
+            ErrorKind::Unsupported,
+            registry.create_digest("sha512").err().unwrap().kind()
+        );
+
+        registry.register("sha512", || Box::new(Sha512::new()));
+        let digest = registry
+            .hash_object("sha512", ObjectType::Tree, &b""[..], 0)
+            .unwrap();
+
+        assert!(registry.has_algorithm("sha512"));
+        assert_eq!(&Sha512::digest(b"tree 0\0")[..], &digest[..]);
+    }
+}
@@ -0,0 +1,275 @@
+use crate::json::json_string;
+use crate::{AdvisoryMatch, DirectoryReport, GitOid};
+use std::fmt::{Display, Formatter, Result};
+use std::path::{Path, PathBuf};
+
+/// How serious a `Finding` is
+#[derive(Clone, Copy, PartialOrd, Eq, Ord, Debug, Hash, PartialEq)]
+pub enum Severity {
+    /// Worth knowing, nothing is wrong
+    Info,
+    /// Needs a look, e.g. an advisory match
+    Warning,
+    /// A check failed
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// What a `Finding` is about
+#[derive(Clone, Copy, PartialOrd, Eq, Ord, Debug, Hash, PartialEq)]
+pub enum FindingKind {
+    /// An artifact listed in a `GitBom` wasn't found
+    Missing,
+    /// A file wasn't listed in the `GitBom`
+    Extra,
+    /// A file couldn't be read
+    Unreadable,
+    /// A BOM root is affected by an advisory
+    Advisory,
+    /// A document isn't in canonical form
+    Noncanonical,
+}
+
+impl Display for FindingKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            FindingKind::Missing => write!(f, "missing"),
+            FindingKind::Extra => write!(f, "extra"),
+            FindingKind::Unreadable => write!(f, "unreadable"),
+            FindingKind::Advisory => write!(f, "advisory"),
+            FindingKind::Noncanonical => write!(f, "noncanonical"),
+        }
+    }
+}
+
+/// One result of a verification or matching operation
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Finding {
+    severity: Severity,
+    kind: FindingKind,
+    gitoid: Option<GitOid>,
+    path: Option<PathBuf>,
+    message: String,
+}
+
+impl Finding {
+    /// Create a finding about no particular artifact or path
+    pub fn new<S: ToString>(severity: Severity, kind: FindingKind, message: S) -> Self {
+        Finding {
+            severity,
+            kind,
+            gitoid: None,
+            path: None,
+            message: message.to_string(),
+        }
+    }
+
+    /// Set the artifact the finding is about
+    pub fn with_gitoid(mut self, gitoid: GitOid) -> Self {
+        self.gitoid = Some(gitoid);
+        self
+    }
+
+    /// Set the path the finding is about
+    pub fn with_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// How serious the finding is
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// What the finding is about
+    pub fn kind(&self) -> FindingKind {
+        self.kind
+    }
+
+    /// The artifact the finding is about, if any
+    pub fn gitoid(&self) -> Option<GitOid> {
+        self.gitoid
+    }
+
+    /// The path the finding is about, if any
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// A human readable description
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn to_json(&self) -> String {
+        let mut ret = format!(
+            "{{\"severity\": \"{}\", \"kind\": \"{}\"",
+            self.severity, self.kind
+        );
+        if let Some(gitoid) = &self.gitoid {
+            ret.push_str(&format!(", \"gitoid\": {}", json_string(&gitoid.uri())));
+        }
+        if let Some(path) = &self.path {
+            ret.push_str(&format!(
+                ", \"path\": {}",
+                json_string(&path.to_string_lossy())
+            ));
+        }
+        ret.push_str(&format!(", \"message\": {}}}", json_string(&self.message)));
+        ret
+    }
+}
+
+/// The findings of an operation, in one shape whatever the operation
+/// was, so tools can render or forward results uniformly
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Report {
+    findings: Vec<Finding>,
+}
+
+impl Report {
+    /// Create an empty report
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a finding
+    pub fn push(&mut self, finding: Finding) {
+        self.findings.push(finding);
+    }
+
+    /// The findings, in the order they were added
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings
+    }
+
+    /// The most serious finding's severity, or `None` if there are none
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.findings.iter().map(|finding| finding.severity).max()
+    }
+
+    /// Render the report as JSON: `{"findings": [...]}`, with gitoids as
+    /// `gitoid:` URIs
+    pub fn to_json(&self) -> String {
+        let findings: Vec<String> = self
+            .findings
+            .iter()
+            .map(|finding| format!("    {}", finding.to_json()))
+            .collect();
+        if findings.is_empty() {
+            return "{\"findings\": []}\n".to_string();
+        }
+        format!("{{\"findings\": [\n{}\n]}}\n", findings.join(",\n"))
+    }
+}
+
+impl From<&DirectoryReport> for Report {
+    fn from(directory: &DirectoryReport) -> Self {
+        let mut ret = Report::new();
+        for gitoid in directory.missing() {
+            ret.push(
+                Finding::new(
+                    Severity::Error,
+                    FindingKind::Missing,
+                    "No file has this gitoid",
+                )
+                .with_gitoid(*gitoid),
+            );
+        }
+        for (path, gitoid) in directory.extra() {
+            ret.push(
+                Finding::new(
+                    Severity::Error,
+                    FindingKind::Extra,
+                    "The file isn't in the GitBom",
+                )
+                .with_gitoid(*gitoid)
+                .with_path(path),
+            );
+        }
+        for error in directory.errors() {
+            ret.push(
+                Finding::new(Severity::Error, FindingKind::Unreadable, error.error())
+                    .with_path(error.path()),
+            );
+        }
+        ret
+    }
+}
+
+impl From<&[AdvisoryMatch]> for Report {
+    fn from(matches: &[AdvisoryMatch]) -> Self {
+        let mut ret = Report::new();
+        for m in matches {
+            ret.push(
+                Finding::new(
+                    Severity::Warning,
+                    FindingKind::Advisory,
+                    format!(
+                        "Affected by {} through {} artifact(s)",
+                        m.advisory(),
+                        m.artifacts().len()
+                    ),
+                )
+                .with_gitoid(m.root()),
+            );
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{match_advisories, verify_directory, Advisory, GitBom, HashAlgorithm};
+
+    #[test]
+    fn test_report_from_directory() {
+        let extra = GitOid::new(HashAlgorithm::SHA256, b"a\n");
+        let missing = GitOid::new(HashAlgorithm::SHA256, b"gone");
+        let gitbom: GitBom = vec![
+            missing,
+            GitOid::new(HashAlgorithm::SHA256, b"b\n"),
+            GitOid::new(HashAlgorithm::SHA256, b"sub\n"),
+        ]
+        .into_iter()
+        .collect();
+        let report = Report::from(&verify_directory("test/data/tree", &gitbom).unwrap());
+
+        assert_eq!(Some(Severity::Error), report.max_severity());
+        assert_eq!(2, report.findings().len());
+        assert_eq!(Some(missing), report.findings()[0].gitoid());
+        assert_eq!(FindingKind::Extra, report.findings()[1].kind());
+        assert_eq!(Some(extra), report.findings()[1].gitoid());
+        assert_eq!(Some(Path::new("a.txt")), report.findings()[1].path());
+    }
+
+    #[test]
+    fn test_report_json() {
+        let root = GitOid::new_from_str("app");
+        let openssl = GitOid::new_from_str("openssl.c");
+        let matches = match_advisories(
+            &[Advisory::new("CVE-1", GitBom::new().add(openssl))],
+            &[(root, GitBom::new().add(openssl))],
+        );
+        let json = Report::from(&matches[..]).to_json();
+
+        assert_eq!(
+            format!(
+                "{{\"findings\": [\n    {{\"severity\": \"warning\", \"kind\": \"advisory\", \"gitoid\": \"{}\", \"message\": \"Affected by CVE-1 through 1 artifact(s)\"}}\n]}}\n",
+                root.uri()
+            ),
+            json
+        );
+        assert_eq!("{\"findings\": []}\n", Report::new().to_json());
+    }
+}
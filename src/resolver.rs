@@ -0,0 +1,126 @@
+use crate::GitOid;
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Result as IOResult};
+
+/// Turns an identifier from some other system (a URL, a purl, an
+/// internal asset id, ...) into the `GitOid` of the artifact it names.
+///
+/// Implementations should return an `Err` of kind `NotFound` when the
+/// identifier is well formed but they don't know the artifact
+pub trait Resolver: Send + Sync {
+    /// Resolve `identifier`, including its scheme, e.g.
+    /// `pkg:cargo/gitbom@0.1.0`
+    fn resolve(&self, identifier: &str) -> IOResult<GitOid>;
+}
+
+impl<F> Resolver for F
+where
+    F: Fn(&str) -> IOResult<GitOid> + Send + Sync,
+{
+    fn resolve(&self, identifier: &str) -> IOResult<GitOid> {
+        self(identifier)
+    }
+}
+
+/// Dispatches identifiers to registered `Resolver`s by their scheme, i.e.
+/// the part before the first `:` (`https`, `pkg`, `asset`, ...), so an
+/// organization can hook its own systems into lookups.
+///
+/// `gitoid:` URIs are resolved without registering anything
+pub struct ResolverRegistry {
+    resolvers: HashMap<String, Box<dyn Resolver>>,
+}
+
+impl Default for ResolverRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResolverRegistry {
+    /// Create a registry that only knows `gitoid:` URIs
+    pub fn new() -> Self {
+        let mut ret = ResolverRegistry {
+            resolvers: HashMap::new(),
+        };
+        ret.register("gitoid", Box::new(GitOid::from_uri));
+        ret
+    }
+
+    /// Use `resolver` for identifiers with the given scheme, replacing
+    /// any resolver already registered for it
+    pub fn register<S: ToString>(&mut self, scheme: S, resolver: Box<dyn Resolver>) {
+        self.resolvers.insert(scheme.to_string(), resolver);
+    }
+
+    /// Is there a resolver for `scheme`?
+    pub fn has_scheme(&self, scheme: &str) -> bool {
+        self.resolvers.contains_key(scheme)
+    }
+
+    /// Resolve `identifier` with the resolver for its scheme. Will return
+    /// an `Err` of kind `Unsupported` if there isn't one
+    pub fn resolve(&self, identifier: &str) -> IOResult<GitOid> {
+        let scheme = identifier
+            .split_once(':')
+            .map(|(scheme, _)| scheme)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("{:?} has no scheme", identifier),
+                )
+            })?;
+
+        match self.resolvers.get(scheme) {
+            Some(resolver) => resolver.resolve(identifier),
+            None => Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("No resolver for {:?} identifiers", scheme),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct AssetDatabase;
+
+    impl Resolver for AssetDatabase {
+        fn resolve(&self, identifier: &str) -> IOResult<GitOid> {
+            match identifier {
+                "asset:42" => Ok(GitOid::new_from_str("forty two")),
+                _ => Err(Error::new(ErrorKind::NotFound, "unknown asset")),
+            }
+        }
+    }
+
+    #[test]
+    fn test_registry_dispatches_by_scheme() {
+        let mut registry = ResolverRegistry::new();
+        registry.register("asset", Box::new(AssetDatabase));
+
+        assert_eq!(
+            GitOid::new_from_str("forty two"),
+            registry.resolve("asset:42").unwrap()
+        );
+        assert_eq!(
+            ErrorKind::NotFound,
+            registry.resolve("asset:7").unwrap_err().kind()
+        );
+        assert_eq!(
+            ErrorKind::Unsupported,
+            registry.resolve("pkg:cargo/gitbom").unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn test_registry_resolves_gitoid_uris() {
+        let registry = ResolverRegistry::new();
+        let gitoid = GitOid::new_from_str("hello");
+
+        assert!(registry.has_scheme("gitoid"));
+        assert_eq!(gitoid, registry.resolve(&gitoid.uri()).unwrap());
+    }
+}
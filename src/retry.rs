@@ -0,0 +1,149 @@
+use crate::ErrorContext;
+use std::cell::Cell;
+use std::io::{Error, ErrorKind, Result as IOResult};
+use std::time::Duration;
+
+/// `EIO`, which network filesystems return for transient failures
+#[cfg(unix)]
+const EIO: i32 = 5;
+
+/// `ESTALE`, a stale NFS file handle
+#[cfg(target_os = "linux")]
+const ESTALE: i32 = 116;
+#[cfg(all(unix, not(target_os = "linux")))]
+const ESTALE: i32 = 70;
+
+/// When and how often to retry an operation that failed with an error
+/// that's likely to go away, such as the `EIO` and `ESTALE` errors that
+/// network filesystems throw during big scans.
+///
+/// The delay before each retry doubles, starting from the backoff
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    attempts: usize,
+    backoff: Duration,
+    kinds: Vec<ErrorKind>,
+    os_errors: Vec<i32>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 3,
+            backoff: Duration::from_millis(100),
+            kinds: vec![ErrorKind::Interrupted, ErrorKind::TimedOut],
+            #[cfg(unix)]
+            os_errors: vec![EIO, ESTALE],
+            #[cfg(not(unix))]
+            os_errors: Vec::new(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The defaults: up to 3 attempts, 100ms before the first retry, and
+    /// retry `Interrupted` and `TimedOut` errors, and on Unix `EIO` and
+    /// `ESTALE`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Try an operation at most `attempts` times in all
+    pub fn attempts(mut self, attempts: usize) -> Self {
+        self.attempts = attempts.max(1);
+        self
+    }
+
+    /// Wait `backoff` before the first retry, twice that before the
+    /// second, and so on
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Also retry errors of this kind
+    pub fn retry_kind(mut self, kind: ErrorKind) -> Self {
+        self.kinds.push(kind);
+        self
+    }
+
+    /// Also retry errors with this raw OS error code
+    pub fn retry_os_error(mut self, code: i32) -> Self {
+        self.os_errors.push(code);
+        self
+    }
+
+    /// Would `error` be retried? An error with an `ErrorContext` is
+    /// judged by the error inside it
+    pub fn is_transient(&self, error: &Error) -> bool {
+        let error = ErrorContext::from_io_error(error).map_or(error, ErrorContext::error);
+        self.kinds.contains(&error.kind())
+            || error
+                .raw_os_error()
+                .is_some_and(|code| self.os_errors.contains(&code))
+    }
+
+    /// run `operation` until it succeeds, fails with an error that isn't
+    /// transient or runs out of attempts, adding the retries to `retried`
+    pub(crate) fn run<T, F>(&self, retried: &Cell<usize>, mut operation: F) -> IOResult<T>
+    where
+        F: FnMut() -> IOResult<T>,
+    {
+        let mut delay = self.backoff;
+        for _ in 1..self.attempts {
+            match operation() {
+                Err(error) if self.is_transient(&error) => {
+                    retried.set(retried.get() + 1);
+                    std::thread::sleep(delay);
+                    delay = delay.saturating_mul(2);
+                }
+                result => return result,
+            }
+        }
+        operation()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retries_transient_errors() {
+        let policy = RetryPolicy::new().backoff(Duration::ZERO);
+        let retried = Cell::new(0);
+        let mut failures = 2;
+        let result = policy.run(&retried, || {
+            if failures == 0 {
+                return Ok("done");
+            }
+            failures -= 1;
+            Err(Error::new(ErrorKind::TimedOut, "slow server"))
+        });
+        assert_eq!("done", result.unwrap());
+        assert_eq!(2, retried.get());
+
+        let calls = Cell::new(0);
+        let result: IOResult<()> = policy.run(&retried, || {
+            calls.set(calls.get() + 1);
+            Err(Error::new(ErrorKind::NotFound, "gone"))
+        });
+        assert_eq!(ErrorKind::NotFound, result.unwrap_err().kind());
+        assert_eq!(1, calls.get());
+
+        #[cfg(unix)]
+        {
+            let result: IOResult<()> = policy
+                .attempts(2)
+                .run(&retried, || Err(Error::from_raw_os_error(EIO)));
+            assert!(result.is_err());
+            assert_eq!(3, retried.get());
+
+            let wrapped = crate::context::in_file(
+                Error::from_raw_os_error(ESTALE),
+                std::path::Path::new("objects/ab"),
+            );
+            assert!(RetryPolicy::new().is_transient(&wrapped));
+        }
+    }
+}
@@ -0,0 +1,1001 @@
+use crate::changed::check_unchanged;
+use crate::context::in_file;
+use crate::duplicates::DuplicateFilter;
+use crate::events::{ScanEvent, ScanSummary, SkipReason};
+use crate::glob::glob_matches;
+use crate::incremental::Checkpoint;
+use crate::lfs::{LfsOptions, MAX_POINTER_LEN};
+use crate::metrics::{count_error, finalize_content, metrics};
+use crate::pool::PooledBuffer;
+use crate::sparse::hash_regions;
+use crate::{
+    ChangedDuringRead, GitBom, GitOid, GitOidHasher, HashAlgorithm, LfsPointer, RateLimiter,
+    RetryPolicy, ScanCache,
+};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, Metadata};
+use std::io::{Error, ErrorKind, Read, Result as IOResult};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Options for scanning a directory of artifacts
+#[derive(Clone, Debug, Default)]
+pub struct ScanOptions {
+    skip_hidden: bool,
+    follow_symlinks: bool,
+    change_retries: usize,
+    buffer_size: Option<usize>,
+    retry_policy: Option<RetryPolicy>,
+    dedup_contents: bool,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    max_depth: Option<usize>,
+    one_file_system: bool,
+    checkpoint: Option<(PathBuf, usize)>,
+    lfs: LfsOptions,
+    sparse: bool,
+    rate_limiter: Option<Arc<RateLimiter>>,
+}
+
+/// The read buffer size used unless `ScanOptions::buffer_size` says
+/// otherwise
+const DEFAULT_BUFFER_SIZE: usize = 64 * 1024;
+
+impl ScanOptions {
+    /// The default options: hash every regular file except under `.git`,
+    /// and don't follow symlinks
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Skip files and directories whose names start with `.`
+    pub fn skip_hidden(mut self, skip: bool) -> Self {
+        self.skip_hidden = skip;
+        self
+    }
+
+    /// Hash the files symlinks point to and descend into symlinked
+    /// directories, rather than skipping symlinks. Each directory is only
+    /// visited once, so symlink loops are harmless
+    pub fn follow_symlinks(mut self, follow: bool) -> Self {
+        self.follow_symlinks = follow;
+        self
+    }
+
+    /// Hash a file up to `retries` more times if it changes while it's
+    /// being hashed. A file that is still changing after that is
+    /// reported with a `ChangedDuringRead` error. The default is no
+    /// retries
+    pub fn change_retries(mut self, retries: usize) -> Self {
+        self.change_retries = retries;
+        self
+    }
+
+    /// Read files in chunks of `size` bytes. The buffers are pooled and
+    /// reused from file to file, and across threads, rather than
+    /// allocated per file. The default is 64 KiB
+    pub fn buffer_size(mut self, size: usize) -> Self {
+        self.buffer_size = Some(size.max(1));
+        self
+    }
+
+    /// Retry hashing a file that fails with a transient error, as the
+    /// policy says. The default is not to retry
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Hash only one of each set of byte-identical files, giving the
+    /// others its gitoid after comparing them with it. Files are only
+    /// compared with files of the same size whose first 4 KiB hash the
+    /// same, which makes this a big win on trees full of duplicated
+    /// files, like `node_modules`. The default is to hash every file
+    pub fn dedup_contents(mut self, dedup: bool) -> Self {
+        self.dedup_contents = dedup;
+        self
+    }
+
+    /// Only hash files matching the glob `pattern`, or any other
+    /// included pattern. `*` and `?` don't match `/`, `**` matches any
+    /// number of directories, and a pattern with no `/`, like `*.c`, is
+    /// matched against the file name. The default is every file
+    pub fn include<S: ToString>(mut self, pattern: S) -> Self {
+        self.include.push(pattern.to_string());
+        self
+    }
+
+    /// Only hash files with the extension `extension`, e.g. `"h"`; the
+    /// same as including `*.h`
+    pub fn extension(self, extension: &str) -> Self {
+        self.include(format!("*.{}", extension))
+    }
+
+    /// Skip files and directories matching the glob `pattern`, written as
+    /// for `include`. A trailing `/` only matches directories, so
+    /// `target/` skips `target` directories at any depth
+    pub fn exclude<S: ToString>(mut self, pattern: S) -> Self {
+        self.exclude.push(pattern.to_string());
+        self
+    }
+
+    /// Descend at most `depth` directories below the scanned one, so
+    /// `max_depth(0)` only hashes the files directly in it. The default
+    /// is no limit
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Don't descend into directories on a different filesystem from the
+    /// scanned one, e.g. bind-mounted network shares. Only has an effect
+    /// on Unix
+    pub fn one_file_system(mut self, one: bool) -> Self {
+        self.one_file_system = one;
+        self
+    }
+
+    /// Every `every` files, save the files hashed so far to `path` as a
+    /// `ScanCache`, so a scan that's interrupted can be picked up with
+    /// `DirectoryScan::resume`. The checkpoint is replaced atomically, and
+    /// written once more when the scan finishes. A failure to save it is
+    /// reported by `DirectoryScan::checkpoint_error`
+    pub fn checkpoint<P: Into<PathBuf>>(mut self, path: P, every: usize) -> Self {
+        self.checkpoint = Some((path.into(), every.max(1)));
+        self
+    }
+
+    /// Hash the content of each Git LFS pointer file, read from the local
+    /// LFS cache at `lfs_dir`, usually `.git/lfs`, rather than the
+    /// pointer text. The content is checked against the pointer, and a
+    /// pointer whose content isn't available is an error
+    pub fn lfs_cache<P: Into<PathBuf>>(mut self, lfs_dir: P) -> Self {
+        self.lfs.cache = Some(lfs_dir.into());
+        self
+    }
+
+    /// Get the content of Git LFS pointer files that aren't in the
+    /// `lfs_cache` from `resolver`, e.g. by fetching them from the LFS
+    /// server. Returning `Ok(None)` means the content isn't available
+    pub fn lfs_resolver<F>(mut self, resolver: F) -> Self
+    where
+        F: Fn(&LfsPointer) -> IOResult<Option<Box<dyn Read>>> + Send + Sync + 'static,
+    {
+        self.lfs.resolver = Some(Arc::new(resolver));
+        self
+    }
+
+    /// Hash files without reading their holes, as
+    /// `GitOid::new_from_sparse_file` does, which is much faster for disk
+    /// and firmware images. Only makes a difference on Linux. The default
+    /// is to read every byte
+    pub fn sparse(mut self, sparse: bool) -> Self {
+        self.sparse = sparse;
+        self
+    }
+
+    /// Keep the scan's reads under the `limiter`'s rates, so a background
+    /// job doesn't starve interactive work of disk bandwidth. The default
+    /// is no limit
+    pub fn rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(Arc::new(limiter));
+        self
+    }
+
+    /// should `relative` be skipped, as the include and exclude patterns
+    /// say?
+    fn filtered_out(&self, relative: &Path, is_dir: bool) -> bool {
+        self.exclude
+            .iter()
+            .any(|pattern| glob_matches(pattern, relative, is_dir))
+            || (!is_dir
+                && !self.include.is_empty()
+                && !self
+                    .include
+                    .iter()
+                    .any(|pattern| glob_matches(pattern, relative, false)))
+    }
+}
+
+/// A file that couldn't be scanned, and why
+#[derive(Debug)]
+pub struct ScanError {
+    path: PathBuf,
+    error: Error,
+}
+
+impl ScanError {
+    pub(crate) fn new(path: PathBuf, error: Error) -> Self {
+        ScanError { path, error }
+    }
+
+    /// The path, relative to the scanned directory
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// What went wrong
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+}
+
+/// The result of scanning a directory: the gitoid of every file that
+/// could be hashed, and an error for each one that couldn't
+#[derive(Debug)]
+pub struct DirectoryScan {
+    files: Vec<(PathBuf, GitOid)>,
+    errors: Vec<ScanError>,
+    retries: usize,
+    checkpoint_error: Option<Error>,
+}
+
+impl DirectoryScan {
+    /// Scan the directory at `root`. Will return an `Err` only if `root`
+    /// itself can't be read; problems with anything inside it are
+    /// collected in `errors`
+    pub fn new<P: AsRef<Path>>(
+        root: P,
+        hash_algo: HashAlgorithm,
+        options: &ScanOptions,
+    ) -> IOResult<Self> {
+        DirectoryScan::new_with_events(root, hash_algo, options, |_| {})
+    }
+
+    /// Scan the directory at `root` like `new`, taking the gitoid of each
+    /// file whose size and modification time match `checkpoint` rather
+    /// than hashing it again. `checkpoint` is usually one saved by
+    /// `ScanOptions::checkpoint` before an interruption
+    pub fn resume<P: AsRef<Path>>(
+        root: P,
+        hash_algo: HashAlgorithm,
+        options: &ScanOptions,
+        checkpoint: &ScanCache,
+    ) -> IOResult<Self> {
+        DirectoryScan::scan(root.as_ref(), hash_algo, options, Some(checkpoint), |_| {})
+    }
+
+    /// Scan the directory at `root` like `new`, passing a `ScanEvent` to
+    /// `on_event` as each file is hashed, skipped or fails, so a GUI can
+    /// show progress. `Started` comes first, and `Finished` last unless
+    /// `root` itself can't be read
+    pub fn new_with_events<P, F>(
+        root: P,
+        hash_algo: HashAlgorithm,
+        options: &ScanOptions,
+        on_event: F,
+    ) -> IOResult<Self>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&ScanEvent),
+    {
+        DirectoryScan::scan(root.as_ref(), hash_algo, options, None, on_event)
+    }
+
+    fn scan<F>(
+        root: &Path,
+        hash_algo: HashAlgorithm,
+        options: &ScanOptions,
+        resume: Option<&ScanCache>,
+        mut on_event: F,
+    ) -> IOResult<Self>
+    where
+        F: FnMut(&ScanEvent),
+    {
+        let start = Instant::now();
+        on_event(&ScanEvent::Started {
+            root: root.to_path_buf(),
+        });
+        let mut summary = ScanSummary::default();
+        let mut ret = DirectoryScan {
+            files: Vec::new(),
+            errors: Vec::new(),
+            retries: 0,
+            checkpoint_error: None,
+        };
+        let retried = Cell::new(0);
+        let mut duplicates = options.dedup_contents.then(DuplicateFilter::new);
+        let mut checkpoint = options
+            .checkpoint
+            .as_ref()
+            .map(|(path, every)| Checkpoint::new(path, *every));
+        let mut checkpoint_error = None;
+        DirectoryScan::walk(
+            root,
+            options,
+            |relative, path, metadata| {
+                let recorded = checkpoint.is_some().then(|| metadata.clone());
+                let resumed = resume.and_then(|cache| cache.cached(relative, &metadata, hash_algo));
+                let gitoid = match (resumed, &mut duplicates) {
+                    (Some(gitoid), _) => gitoid,
+                    (None, Some(duplicates)) => {
+                        duplicates.hash_file(hash_algo, path, metadata, options, &retried)?
+                    }
+                    (None, None) => hash_file(hash_algo, path, metadata, options, &retried)?,
+                };
+                if let (Some(checkpoint), Some(metadata), None) =
+                    (&mut checkpoint, recorded, &checkpoint_error)
+                {
+                    // keep scanning if the checkpoint can't be saved
+                    if let Err(error) = checkpoint.record(relative, &metadata, gitoid) {
+                        checkpoint_error = Some(error);
+                    }
+                }
+                Ok(gitoid)
+            },
+            |event| {
+                on_event(&event);
+                summary.count(&event);
+                match event {
+                    ScanEvent::FileHashed { path, gitoid, .. } => ret.files.push((path, gitoid)),
+                    ScanEvent::Error { path, error } => ret.errors.push(ScanError { path, error }),
+                    _ => {}
+                }
+            },
+        )?;
+        if let Some(checkpoint) = &mut checkpoint {
+            if checkpoint_error.is_none() {
+                checkpoint_error = checkpoint.save().err();
+            }
+        }
+        ret.checkpoint_error = checkpoint_error;
+
+        ret.retries = retried.get();
+        summary.retries = ret.retries;
+        summary.elapsed = start.elapsed();
+        on_event(&ScanEvent::Finished { summary });
+        ret.files
+            .sort_by_cached_key(|(path, gitoid)| (path_order(path), *gitoid));
+        ret.errors
+            .sort_by_cached_key(|error| path_order(&error.path));
+        Ok(ret)
+    }
+
+    /// Scan the directory at `root`, passing each file's path (relative to
+    /// `root`) and gitoid or error to `visit` as soon as it's hashed,
+    /// rather than collecting them. Memory use depends on the directories
+    /// still waiting to be scanned, not on the number of files, so this
+    /// suits huge trees on small machines. Files are visited in no
+    /// particular order.
+    ///
+    /// On Unix, files with several hard links are hashed once and the
+    /// gitoid reported for every link; only those gitoids are kept.
+    /// `dedup_contents` is the exception to the memory bound: it keeps
+    /// the path, metadata and gitoid of every file it hashes, so memory
+    /// grows with the number of files.
+    ///
+    /// Will return an `Err` only if `root` itself can't be read
+    pub fn visit<P, F>(
+        root: P,
+        hash_algo: HashAlgorithm,
+        options: &ScanOptions,
+        mut visit: F,
+    ) -> IOResult<()>
+    where
+        P: AsRef<Path>,
+        F: FnMut(PathBuf, IOResult<GitOid>),
+    {
+        let retried = Cell::new(0);
+        let mut duplicates = options.dedup_contents.then(DuplicateFilter::new);
+        DirectoryScan::walk(
+            root.as_ref(),
+            options,
+            |_, path, metadata| match &mut duplicates {
+                Some(duplicates) => {
+                    duplicates.hash_file(hash_algo, path, metadata, options, &retried)
+                }
+                None => hash_file(hash_algo, path, metadata, options, &retried),
+            },
+            |event| match event {
+                ScanEvent::FileHashed { path, gitoid, .. } => visit(path, Ok(gitoid)),
+                ScanEvent::Error { path, error } => visit(path, Err(error)),
+                _ => {}
+            },
+        )
+    }
+
+    /// walk the tree, getting each regular file's gitoid from `hash`,
+    /// which is given the relative path, the full path and the metadata,
+    /// and passing everything but `Started` and `Finished` to `on_event`.
+    /// Later links to a hard-linked file reuse the first one's gitoid
+    pub(crate) fn walk<H, F>(
+        root: &Path,
+        options: &ScanOptions,
+        mut hash: H,
+        mut on_event: F,
+    ) -> IOResult<()>
+    where
+        H: FnMut(&Path, &Path, Metadata) -> IOResult<GitOid>,
+        F: FnMut(ScanEvent),
+    {
+        let mut emit = |event: ScanEvent| {
+            if let ScanEvent::Error { .. } = event {
+                metrics().errors(1);
+            }
+            on_event(event);
+        };
+        let mut visited = HashSet::new();
+        // the gitoids of files with several hard links, so each is only
+        // hashed once
+        let mut linked = HashMap::new();
+        if options.follow_symlinks {
+            visited.insert(fs::canonicalize(root).map_err(|error| in_file(error, root))?);
+        }
+        let root_device = if options.one_file_system {
+            device(&fs::metadata(root).map_err(|error| in_file(error, root))?)
+        } else {
+            None
+        };
+
+        // directories are opened when they're popped, so only one is open
+        // at a time however wide the tree is
+        let mut pending = vec![(root.to_path_buf(), PathBuf::new())];
+        while let Some((dir_path, relative_dir)) = pending.pop() {
+            let dir = match fs::read_dir(&dir_path) {
+                Ok(dir) => dir,
+                Err(error) if relative_dir.as_os_str().is_empty() => {
+                    return Err(in_file(error, root))
+                }
+                Err(error) => {
+                    emit(ScanEvent::Error {
+                        path: relative_dir,
+                        error,
+                    });
+                    continue;
+                }
+            };
+            for dir_entry in dir {
+                let dir_entry = match dir_entry {
+                    Ok(dir_entry) => dir_entry,
+                    Err(error) => {
+                        emit(ScanEvent::Error {
+                            path: relative_dir.clone(),
+                            error,
+                        });
+                        continue;
+                    }
+                };
+                let name = dir_entry.file_name();
+                let relative = relative_dir.join(&name);
+                if name == ".git"
+                    || (options.skip_hidden && name.to_string_lossy().starts_with('.'))
+                {
+                    emit(ScanEvent::Skipped {
+                        path: relative,
+                        reason: SkipReason::Hidden,
+                    });
+                    continue;
+                }
+
+                let path = dir_entry.path();
+                let metadata = if options.follow_symlinks {
+                    fs::metadata(&path)
+                } else {
+                    fs::symlink_metadata(&path)
+                };
+                let metadata = match metadata {
+                    Ok(metadata) => metadata,
+                    Err(error) => {
+                        emit(ScanEvent::Error {
+                            path: relative,
+                            error,
+                        });
+                        continue;
+                    }
+                };
+                let skipped = if options.filtered_out(&relative, metadata.is_dir()) {
+                    Some(SkipReason::Filtered)
+                } else if !metadata.is_dir() && !metadata.is_file() {
+                    Some(SkipReason::NotAFile)
+                } else if !metadata.is_dir() {
+                    None
+                } else if options
+                    .max_depth
+                    .is_some_and(|max| relative.components().count() > max)
+                {
+                    Some(SkipReason::TooDeep)
+                } else if root_device.is_some() && device(&metadata) != root_device {
+                    Some(SkipReason::OtherFileSystem)
+                } else {
+                    None
+                };
+                if let Some(reason) = skipped {
+                    emit(ScanEvent::Skipped {
+                        path: relative,
+                        reason,
+                    });
+                    continue;
+                }
+
+                if metadata.is_dir() {
+                    if options.follow_symlinks {
+                        match fs::canonicalize(&path) {
+                            Ok(canonical) => {
+                                if !visited.insert(canonical) {
+                                    emit(ScanEvent::Skipped {
+                                        path: relative,
+                                        reason: SkipReason::AlreadyVisited,
+                                    });
+                                    continue;
+                                }
+                            }
+                            Err(error) => {
+                                emit(ScanEvent::Error {
+                                    path: relative,
+                                    error,
+                                });
+                                continue;
+                            }
+                        }
+                    }
+                    pending.push((path, relative));
+                    continue;
+                }
+
+                let bytes = metadata.len();
+                let id = linked_file_id(&metadata);
+                let result = match id.and_then(|id| linked.get(&id)) {
+                    Some(gitoid) => Ok(*gitoid),
+                    None => hash(&relative, &path, metadata),
+                };
+                match result {
+                    Ok(gitoid) => {
+                        if let Some(id) = id {
+                            linked.insert(id, gitoid);
+                        }
+                        emit(ScanEvent::FileHashed {
+                            path: relative,
+                            gitoid,
+                            bytes,
+                        });
+                    }
+                    Err(error) => emit(ScanEvent::Error {
+                        path: relative,
+                        error,
+                    }),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Each file hashed, by path relative to the scanned directory,
+    /// sorted bytewise by the path with `/` separators, so the order is
+    /// the same on every platform: `sub.txt` sorts before `sub/b.txt`
+    pub fn files(&self) -> &[(PathBuf, GitOid)] {
+        &self.files
+    }
+
+    /// The files that couldn't be hashed, sorted like `files`
+    pub fn errors(&self) -> &[ScanError] {
+        &self.errors
+    }
+
+    /// Take the errors, dropping the rest of the scan
+    pub fn into_errors(self) -> Vec<ScanError> {
+        self.errors
+    }
+
+    /// How many times hashing a file was retried under the
+    /// `RetryPolicy`
+    pub fn retries(&self) -> usize {
+        self.retries
+    }
+
+    /// Why the `ScanOptions::checkpoint` file couldn't be saved, if it
+    /// couldn't. The scan carries on without checkpointing after the
+    /// first failure, so its results are complete either way
+    pub fn checkpoint_error(&self) -> Option<&Error> {
+        self.checkpoint_error.as_ref()
+    }
+
+    /// A `GitBom` of the hashed files
+    pub fn gitbom(&self) -> GitBom {
+        self.files.iter().map(|(_, gitoid)| *gitoid).collect()
+    }
+}
+
+/// the key scan results are sorted by: the path's components joined with
+/// `/`, compared as bytes
+pub(crate) fn path_order(path: &Path) -> Vec<u8> {
+    let mut ret = Vec::new();
+    for (i, component) in path.components().enumerate() {
+        if i > 0 {
+            ret.push(b'/');
+        }
+        #[cfg(unix)]
+        ret.extend_from_slice(std::os::unix::ffi::OsStrExt::as_bytes(
+            component.as_os_str(),
+        ));
+        #[cfg(not(unix))]
+        ret.extend_from_slice(component.as_os_str().to_string_lossy().as_bytes());
+    }
+    ret
+}
+
+/// the (device, inode) of a file with more than one hard link
+#[cfg(unix)]
+fn linked_file_id(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    if metadata.nlink() > 1 {
+        Some((metadata.dev(), metadata.ino()))
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+fn linked_file_id(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// the device a file is on
+#[cfg(unix)]
+fn device(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some(metadata.dev())
+}
+
+#[cfg(not(unix))]
+fn device(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+/// hash the file, checking it didn't change against the metadata taken
+/// before it was opened, and retrying transient errors as the options
+/// say, counting them in `retried`
+pub(crate) fn hash_file(
+    hash_algo: HashAlgorithm,
+    path: &Path,
+    metadata: Metadata,
+    options: &ScanOptions,
+    retried: &Cell<usize>,
+) -> IOResult<GitOid> {
+    hash_file_digest(hash_algo, path, metadata, options, retried, false).map(|(gitoid, _)| gitoid)
+}
+
+/// hash the file like `hash_file`, and if `plain`, also compute the plain
+/// digest of its content in the same read
+pub(crate) fn hash_file_digest(
+    hash_algo: HashAlgorithm,
+    path: &Path,
+    metadata: Metadata,
+    options: &ScanOptions,
+    retried: &Cell<usize>,
+    plain: bool,
+) -> IOResult<(GitOid, Option<Box<[u8]>>)> {
+    with_retries(options, retried, || {
+        hash_changing_file(hash_algo, path, metadata.clone(), options, plain)
+    })
+}
+
+/// run `operation` under the options' `RetryPolicy`, if there is one
+pub(crate) fn with_retries<T, F>(
+    options: &ScanOptions,
+    retried: &Cell<usize>,
+    mut operation: F,
+) -> IOResult<T>
+where
+    F: FnMut() -> IOResult<T>,
+{
+    match &options.retry_policy {
+        Some(policy) => policy.run(retried, operation),
+        None => operation(),
+    }
+}
+
+/// hash the file, rehashing it if it changes as the options say
+fn hash_changing_file(
+    hash_algo: HashAlgorithm,
+    path: &Path,
+    mut metadata: Metadata,
+    options: &ScanOptions,
+    plain: bool,
+) -> IOResult<(GitOid, Option<Box<[u8]>>)> {
+    for _ in 0..options.change_retries {
+        match hash_file_once(hash_algo, path, &metadata, options, plain) {
+            Err(error) if ChangedDuringRead::from_io_error(&error).is_some() => {
+                metadata = fs::metadata(path)?;
+            }
+            result => return result,
+        }
+    }
+    hash_file_once(hash_algo, path, &metadata, options, plain)
+}
+
+fn hash_file_once(
+    hash_algo: HashAlgorithm,
+    path: &Path,
+    metadata: &Metadata,
+    options: &ScanOptions,
+    plain: bool,
+) -> IOResult<(GitOid, Option<Box<[u8]>>)> {
+    let limiter = options.rate_limiter.as_deref();
+    if let Some(limiter) = limiter {
+        limiter.wait_file();
+    }
+    let mut file = File::open(path)?;
+    metrics().files_hashed(1);
+    if options.lfs.is_enabled() && metadata.len() < MAX_POINTER_LEN {
+        let mut content = Vec::new();
+        count_error(file.read_to_end(&mut content))?;
+        if let Some(limiter) = limiter {
+            limiter.wait_bytes(content.len() as u64);
+        }
+        check_unchanged(path, metadata)?;
+        if let Some(pointer) = LfsPointer::parse(&content) {
+            return options.lfs.hash(hash_algo, &pointer, plain, limiter);
+        }
+        let gitoid = GitOid::new(hash_algo, &content);
+        let digest = plain.then(|| {
+            let mut digest = hash_algo.create_digest();
+            digest.update(&content);
+            digest.finalize()
+        });
+        return Ok((gitoid, digest));
+    }
+
+    let mut buf = PooledBuffer::new(options.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE));
+    let mut hasher = GitOidHasher::new(hash_algo, metadata.len() as usize);
+    let mut digest = plain.then(|| hash_algo.create_digest());
+    let mut feed = |data: &[u8]| {
+        hasher.update(data);
+        if let Some(digest) = &mut digest {
+            digest.update(data);
+        }
+    };
+    if options.sparse {
+        count_error(hash_regions(
+            &mut file,
+            path,
+            metadata.len(),
+            &mut buf,
+            limiter,
+            feed,
+        ))?;
+    } else {
+        loop {
+            match file.read(&mut buf) {
+                Ok(0) => break,
+                Ok(size) => {
+                    if let Some(limiter) = limiter {
+                        limiter.wait_bytes(size as u64);
+                    }
+                    feed(&buf[..size])
+                }
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(error) => return count_error(Err(error)),
+            }
+        }
+    }
+    check_unchanged(path, metadata)?;
+    let gitoid = count_error(finalize_content(hasher))?;
+    Ok((gitoid, digest.map(|digest| digest.finalize())))
+}
+
+impl GitBom {
+    /// Hash every file under the directory at `path` and return the
+    /// resulting `GitBom`, along with an error for each file that
+    /// couldn't be hashed. Will return an `Err` only if `path` itself
+    /// can't be read. Use `DirectoryScan` to also get each file's path
+    pub fn from_directory<P: AsRef<Path>>(
+        path: P,
+        hash_algo: HashAlgorithm,
+        options: ScanOptions,
+    ) -> IOResult<(GitBom, Vec<ScanError>)> {
+        let scan = DirectoryScan::new(path, hash_algo, &options)?;
+        Ok((scan.gitbom(), scan.into_errors()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_from_directory() {
+        let (gitbom, errors) =
+            GitBom::from_directory("test/data/tree", HashAlgorithm::SHA1, ScanOptions::new())
+                .unwrap();
+
+        assert!(errors.is_empty());
+        let expected: GitBom = vec!["a\n", "b\n", "sub\n"]
+            .into_iter()
+            .map(|content| GitOid::new(HashAlgorithm::SHA1, content.as_bytes()))
+            .collect();
+        assert_eq!(expected, gitbom);
+    }
+
+    #[test]
+    fn test_scan_paths() {
+        let scan = DirectoryScan::new("test/data/tree", HashAlgorithm::SHA256, &ScanOptions::new())
+            .unwrap();
+        let paths: Vec<&Path> = scan
+            .files()
+            .iter()
+            .map(|(path, _)| path.as_path())
+            .collect();
+
+        assert_eq!(
+            vec![
+                Path::new("a.txt"),
+                Path::new("sub.txt"),
+                Path::new("sub/b.txt")
+            ],
+            paths
+        );
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_visit_streams_results() {
+        let mut seen = Vec::new();
+        DirectoryScan::visit(
+            "test/data/tree",
+            HashAlgorithm::SHA1,
+            &ScanOptions::new(),
+            |path, result| seen.push((path, result.unwrap())),
+        )
+        .unwrap();
+        seen.sort_by_key(|(path, _)| path_order(path));
+
+        let scan =
+            DirectoryScan::new("test/data/tree", HashAlgorithm::SHA1, &ScanOptions::new()).unwrap();
+        assert_eq!(scan.files(), &seen[..]);
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_missing_directory() {
+        assert!(GitBom::from_directory(
+            "test/data/missing",
+            HashAlgorithm::SHA1,
+            ScanOptions::new()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_hard_links_share_a_gitoid() {
+        let root = TempDir::new("links");
+        fs::write(root.join("a.txt"), "linked").unwrap();
+        fs::hard_link(root.join("a.txt"), root.join("b.txt")).unwrap();
+
+        let scan = DirectoryScan::new(&root, HashAlgorithm::SHA256, &ScanOptions::new());
+
+        let gitoid = GitOid::new(HashAlgorithm::SHA256, b"linked");
+        assert_eq!(
+            &[
+                (PathBuf::from("a.txt"), gitoid),
+                (PathBuf::from("b.txt"), gitoid)
+            ],
+            scan.unwrap().files()
+        );
+    }
+
+    #[test]
+    fn test_include_and_exclude() {
+        let scan = |options: ScanOptions| {
+            let scan = DirectoryScan::new("test/data/tree", HashAlgorithm::SHA256, &options);
+            scan.unwrap()
+                .files()
+                .iter()
+                .map(|(path, _)| path.to_string_lossy().replace('\\', "/"))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            vec!["a.txt", "sub.txt"],
+            scan(ScanOptions::new().exclude("sub/"))
+        );
+        assert_eq!(vec!["sub/b.txt"], scan(ScanOptions::new().include("sub/*")));
+        assert_eq!(
+            vec!["a.txt", "sub/b.txt"],
+            scan(ScanOptions::new().extension("txt").exclude("sub.*"))
+        );
+        assert!(scan(ScanOptions::new().extension("c")).is_empty());
+    }
+
+    #[test]
+    fn test_scan_events() {
+        let mut events = Vec::new();
+        let options = ScanOptions::new().exclude("sub/");
+        let scan = DirectoryScan::new_with_events(
+            "test/data/tree",
+            HashAlgorithm::SHA256,
+            &options,
+            |event| match event {
+                ScanEvent::Skipped { path, reason } => {
+                    events.push(format!("skipped {} {:?}", path.display(), reason))
+                }
+                ScanEvent::FileHashed { path, bytes, .. } => {
+                    events.push(format!("hashed {} {}", path.display(), bytes))
+                }
+                ScanEvent::Finished { summary } => events.push(format!(
+                    "finished {} {}",
+                    summary.files(),
+                    summary.skipped()
+                )),
+                _ => events.push(format!("{:?}", event)),
+            },
+        )
+        .unwrap();
+
+        assert_eq!(r#"Started { root: "test/data/tree" }"#, events[0]);
+        assert_eq!("finished 2 1", events[events.len() - 1]);
+        events.sort();
+        assert_eq!(
+            &[
+                "Started { root: \"test/data/tree\" }",
+                "finished 2 1",
+                "hashed a.txt 2",
+                "hashed sub.txt 4",
+                "skipped sub Filtered",
+            ],
+            &events[..]
+        );
+        assert_eq!(2, scan.files().len());
+    }
+
+    #[test]
+    fn test_max_depth_and_one_file_system() {
+        let scan = |options: ScanOptions| {
+            DirectoryScan::new("test/data/tree", HashAlgorithm::SHA256, &options)
+                .unwrap()
+                .files()
+                .len()
+        };
+
+        assert_eq!(2, scan(ScanOptions::new().max_depth(0)));
+        assert_eq!(3, scan(ScanOptions::new().max_depth(1)));
+        assert_eq!(3, scan(ScanOptions::new().one_file_system(true)));
+    }
+
+    #[test]
+    fn test_dedup_contents() {
+        let root = TempDir::new("dedup");
+        fs::create_dir_all(root.join("copy")).unwrap();
+        let content: Vec<u8> = (0..10000u32).map(|i| i as u8).collect();
+        let mut late_change = content.clone();
+        late_change[9000] ^= 1;
+        fs::write(root.join("a.bin"), &content).unwrap();
+        fs::write(root.join("copy/a.bin"), &content).unwrap();
+        fs::write(root.join("b.bin"), &late_change).unwrap();
+
+        let options = ScanOptions::new().dedup_contents(true);
+        let deduped = DirectoryScan::new(&root, HashAlgorithm::SHA256, &options);
+        let plain = DirectoryScan::new(&root, HashAlgorithm::SHA256, &ScanOptions::new());
+
+        let deduped = deduped.unwrap();
+        assert_eq!(plain.unwrap().files(), deduped.files());
+        assert_eq!(
+            GitOid::new(HashAlgorithm::SHA256, &late_change),
+            deduped.files()[1].1
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_loops() {
+        let root = TempDir::new("scan");
+        fs::create_dir_all(root.join("dir")).unwrap();
+        fs::write(root.join("dir/file.txt"), "file").unwrap();
+        std::os::unix::fs::symlink("..", root.join("dir/up")).unwrap();
+
+        let skipped = DirectoryScan::new(&root, HashAlgorithm::SHA256, &ScanOptions::new());
+        let followed = DirectoryScan::new(
+            &root,
+            HashAlgorithm::SHA256,
+            &ScanOptions::new().follow_symlinks(true),
+        );
+
+        assert_eq!(1, skipped.unwrap().files().len());
+        assert_eq!(1, followed.unwrap().files().len());
+    }
+}
@@ -0,0 +1,213 @@
+use crate::changed::{changed_during_read, check_unchanged};
+use crate::context::in_file;
+use crate::metrics::{count_error, finalize_content, metrics};
+use crate::pool::PooledBuffer;
+use crate::{GitOid, GitOidHasher, HashAlgorithm, RateLimiter};
+use std::fs::File;
+use std::io::{ErrorKind, Read, Result as IOResult, Seek, SeekFrom};
+use std::path::Path;
+
+/// zeros fed to the hasher in place of a hole
+static ZEROS: [u8; 65536] = [0u8; 65536];
+
+impl GitOid {
+    /// Compute the blob gitoid of the file at `path` without reading its
+    /// holes. On Linux the file's data regions are found with
+    /// `SEEK_DATA`/`SEEK_HOLE` and the holes are hashed as zeros straight
+    /// from memory, which makes mostly-empty disk and firmware images much
+    /// faster to hash. The gitoid is the same as reading the whole file.
+    ///
+    /// Elsewhere, and on filesystems that don't report holes, the whole
+    /// file is read. Will return a `ChangedDuringRead` error if the file
+    /// changes while it's hashed
+    pub fn new_from_sparse_file<P: AsRef<Path>>(
+        hash_algo: HashAlgorithm,
+        path: P,
+    ) -> IOResult<GitOid> {
+        let path = path.as_ref();
+        count_error(hash_sparse_file(hash_algo, path).map_err(|error| in_file(error, path)))
+    }
+}
+
+fn hash_sparse_file(hash_algo: HashAlgorithm, path: &Path) -> IOResult<GitOid> {
+    let mut file = File::open(path)?;
+    let metadata = file.metadata()?;
+    let len = metadata.len();
+    metrics().files_hashed(1);
+
+    let mut hasher = GitOidHasher::new(hash_algo, len as usize);
+    let mut buf = PooledBuffer::new(65536);
+    hash_regions(&mut file, path, len, &mut buf, None, |data| {
+        hasher.update(data)
+    })?;
+    check_unchanged(path, &metadata)?;
+    finalize_content(hasher)
+}
+
+/// pass the first `len` bytes of `file` to `feed`, reading its data
+/// regions into `buf`, under the `limiter` if there is one, and passing
+/// zeros for its holes. Will return a `ChangedDuringRead` error if the
+/// file ends early
+pub(crate) fn hash_regions<F: FnMut(&[u8])>(
+    file: &mut File,
+    path: &Path,
+    len: u64,
+    buf: &mut [u8],
+    limiter: Option<&RateLimiter>,
+    mut feed: F,
+) -> IOResult<()> {
+    let mut pos = 0;
+    while pos < len {
+        let (data, hole) = next_data(file, pos, len)?;
+        feed_zeros(&mut feed, data - pos);
+        read_data(file, path, data, hole, buf, limiter, &mut feed)?;
+        pos = hole;
+    }
+    Ok(())
+}
+
+/// pass the bytes from `start` to `end` of `file` to `feed`
+fn read_data<F: FnMut(&[u8])>(
+    file: &mut File,
+    path: &Path,
+    start: u64,
+    end: u64,
+    buf: &mut [u8],
+    limiter: Option<&RateLimiter>,
+    feed: &mut F,
+) -> IOResult<()> {
+    file.seek(SeekFrom::Start(start))?;
+    let mut remaining = end - start;
+    while remaining > 0 {
+        let want = remaining.min(buf.len() as u64) as usize;
+        match file.read_exact(&mut buf[..want]) {
+            Ok(()) => {}
+            // it was shorter than its length when hashing started
+            Err(error) if error.kind() == ErrorKind::UnexpectedEof => {
+                return Err(changed_during_read(path))
+            }
+            Err(error) => return Err(error),
+        }
+        if let Some(limiter) = limiter {
+            limiter.wait_bytes(want as u64);
+        }
+        feed(&buf[..want]);
+        remaining -= want as u64;
+    }
+    Ok(())
+}
+
+fn feed_zeros<F: FnMut(&[u8])>(feed: &mut F, mut count: u64) {
+    while count > 0 {
+        let size = count.min(ZEROS.len() as u64) as usize;
+        feed(&ZEROS[..size]);
+        count -= size as u64;
+    }
+}
+
+/// the next data region at or after `pos`, as `(start, end)`. A file
+/// with no data left is one hole up to `len`
+#[cfg(all(target_os = "linux", target_pointer_width = "64"))]
+fn next_data(file: &File, pos: u64, len: u64) -> IOResult<(u64, u64)> {
+    use std::io::Error;
+    use std::os::unix::io::AsRawFd;
+
+    const SEEK_DATA: i32 = 3;
+    const SEEK_HOLE: i32 = 4;
+    const ENXIO: i32 = 6;
+    const EINVAL: i32 = 22;
+
+    extern "C" {
+        fn lseek(fd: i32, offset: i64, whence: i32) -> i64;
+    }
+
+    let fd = file.as_raw_fd();
+    // Safety: lseek only moves the file offset of a descriptor we own,
+    // and the caller seeks explicitly before every read
+    let data = unsafe { lseek(fd, pos as i64, SEEK_DATA) };
+    if data < 0 {
+        let error = Error::last_os_error();
+        return match error.raw_os_error() {
+            Some(ENXIO) => Ok((len, len)),
+            // the filesystem doesn't support it, so it's all data
+            Some(EINVAL) => Ok((pos, len)),
+            _ => Err(error),
+        };
+    }
+    let hole = unsafe { lseek(fd, data, SEEK_HOLE) };
+    if hole < 0 {
+        return Err(Error::last_os_error());
+    }
+    // the file may have changed size since its length was taken
+    Ok(((data as u64).min(len), (hole as u64).min(len)))
+}
+
+#[cfg(not(all(target_os = "linux", target_pointer_width = "64")))]
+fn next_data(_file: &File, pos: u64, len: u64) -> IOResult<(u64, u64)> {
+    Ok((pos, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+    use crate::{ChangedDuringRead, DirectoryScan, ScanOptions};
+    use std::io::Write;
+
+    #[test]
+    fn test_sparse_file_matches_reading_it() {
+        let dir = TempDir::new("sparse");
+        let path = dir.join("sparse.bin");
+        let len = 3 << 20;
+        let mut file = File::create(&path).unwrap();
+        file.write_all(b"start").unwrap();
+        file.seek(SeekFrom::Start(len - 3)).unwrap();
+        file.write_all(b"end").unwrap();
+        drop(file);
+
+        let sparse = GitOid::new_from_sparse_file(HashAlgorithm::SHA256, &path);
+        let content = std::fs::read(&path).unwrap();
+
+        assert_eq!(len as usize, content.len());
+        assert_eq!(
+            GitOid::new(HashAlgorithm::SHA256, &content),
+            sparse.unwrap()
+        );
+    }
+
+    #[test]
+    fn test_shrunk_file_changed_during_read() {
+        let dir = TempDir::new("sparse-shrunk");
+        let path = dir.join("short.bin");
+        std::fs::write(&path, b"shorter than expected").unwrap();
+
+        let mut file = File::open(&path).unwrap();
+        let error =
+            read_data(&mut file, &path, 0, 100, &mut [0; 16], None, &mut |_| {}).unwrap_err();
+        assert_eq!(
+            path,
+            ChangedDuringRead::from_io_error(&error).unwrap().path()
+        );
+    }
+
+    #[test]
+    fn test_sparse_scan_option() {
+        let dir = TempDir::new("sparse-scan");
+        let mut file = File::create(dir.join("image.bin")).unwrap();
+        file.write_all(b"boot").unwrap();
+        file.seek(SeekFrom::Start(1 << 20)).unwrap();
+        file.write_all(b"end").unwrap();
+        drop(file);
+        std::fs::write(dir.join("small.txt"), "small").unwrap();
+
+        let scan = |options: ScanOptions| {
+            DirectoryScan::new(&dir, HashAlgorithm::SHA256, &options)
+                .unwrap()
+                .files()
+                .to_vec()
+        };
+        let plain = scan(ScanOptions::new());
+        assert_eq!(2, plain.len());
+        assert_eq!(plain, scan(ScanOptions::new().sparse(true)));
+    }
+}
@@ -0,0 +1,127 @@
+use crate::encoding::{base64_decode, base64_encode};
+use crate::{GitOid, HashAlgorithm, ObjectType};
+use sha2::{Digest, Sha256};
+use std::io::{Error, ErrorKind, Result as IOResult};
+
+impl GitOid {
+    /// Render the hash as a [Subresource Integrity](https://www.w3.org/TR/SRI/)
+    /// string, e.g. `sha256-<base64>`.
+    ///
+    /// Note the digest is the gitoid's, which includes the git object
+    /// prefix, so a browser checking the resource against it will fail.
+    /// Use `sri_for_content` for that. SRI has no SHA1, so SHA1 gitoids
+    /// are an `Err`
+    pub fn to_sri(&self) -> IOResult<String> {
+        match self.hash_algorithm {
+            HashAlgorithm::SHA256 => Ok(format!("sha256-{}", base64_encode(self.hash_value()))),
+            #[cfg(feature = "sha1")]
+            HashAlgorithm::SHA1 => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("SRI has no equivalent of {}", self.hash_algorithm),
+            )),
+        }
+    }
+
+    /// Parse an SRI string whose digest was computed with the git blob
+    /// prefix, e.g. one made by `to_sri`. A string can list several hashes;
+    /// the first `sha256` one is used. Use `from_sri_checked` if the
+    /// content is available, since a plain SRI digest parses fine here but
+    /// is not a gitoid
+    pub fn from_sri(sri: &str) -> IOResult<GitOid> {
+        let digest = sri_sha256_digest(sri)?;
+        GitOid::from_raw(HashAlgorithm::SHA256, ObjectType::Blob, &digest)
+    }
+
+    /// Parse an SRI string and check it against the content it describes.
+    /// Will return an `Err` explaining the problem if the SRI hash was
+    /// computed over the raw content without the git object prefix, which
+    /// is how browsers and most tooling compute SRI
+    pub fn from_sri_checked(sri: &str, content: &[u8]) -> IOResult<GitOid> {
+        let digest = sri_sha256_digest(sri)?;
+        let gitoid = GitOid::new(HashAlgorithm::SHA256, content);
+
+        if digest == gitoid.hash_value() {
+            Ok(gitoid)
+        } else if digest[..] == Sha256::digest(content)[..] {
+            Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "The SRI hash was computed without the git object prefix; the gitoid of the content is {}",
+                    gitoid
+                ),
+            ))
+        } else {
+            Err(Error::new(
+                ErrorKind::InvalidData,
+                "The SRI hash does not match the content",
+            ))
+        }
+    }
+
+    /// The standard SRI string for `content`, as a browser would compute it
+    /// (without the git object prefix)
+    pub fn sri_for_content(content: &[u8]) -> String {
+        format!("sha256-{}", base64_encode(&Sha256::digest(content)))
+    }
+}
+
+/// find the first `sha256-` entry in an SRI string and decode it,
+/// ignoring any `?options`
+fn sri_sha256_digest(sri: &str) -> IOResult<Vec<u8>> {
+    for token in sri.split_ascii_whitespace() {
+        if let Some(value) = token.strip_prefix("sha256-") {
+            let value = value.split('?').next().unwrap_or(value);
+            return base64_decode(value);
+        }
+    }
+    Err(Error::new(
+        ErrorKind::InvalidData,
+        format!("No sha256 hash in SRI string '{}'", sri),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sri_round_trip() {
+        let gitoid = GitOid::new(HashAlgorithm::SHA256, b"hello world");
+        let sri = gitoid.to_sri().unwrap();
+
+        assert_eq!("sha256-/uU6GNMoIGE8BSeqeb5cswFzyCOptEj6SBd2fMhMbwM=", sri);
+        assert_eq!(gitoid, GitOid::from_sri(&sri).unwrap());
+    }
+
+    #[test]
+    fn test_sri_picks_sha256_entry() {
+        let gitoid = GitOid::new(HashAlgorithm::SHA256, b"hello world");
+        let sri = format!("sha384-AAAA {}?ct=text/plain", gitoid.to_sri().unwrap());
+
+        assert_eq!(gitoid, GitOid::from_sri(&sri).unwrap());
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_sha1_has_no_sri() {
+        assert!(GitOid::new(HashAlgorithm::SHA1, b"hello world")
+            .to_sri()
+            .is_err());
+    }
+
+    #[test]
+    fn test_checked_detects_plain_hash() {
+        let plain = GitOid::sri_for_content(b"hello world");
+        assert_eq!("sha256-uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek=", plain);
+
+        let err = GitOid::from_sri_checked(&plain, b"hello world").unwrap_err();
+        assert!(err.to_string().contains("without the git object prefix"));
+
+        let gitoid = GitOid::new(HashAlgorithm::SHA256, b"hello world");
+        assert_eq!(
+            gitoid,
+            GitOid::from_sri_checked(&gitoid.to_sri().unwrap(), b"hello world").unwrap()
+        );
+        assert!(GitOid::from_sri_checked(&plain, b"goodbye world").is_err());
+    }
+}
@@ -0,0 +1,74 @@
+use crate::metrics::{count_error, finalize_content};
+use crate::{GitOid, GitOidHasher, HashAlgorithm};
+use std::io::{Read, Result as IOResult};
+
+/// A `Read` adapter that passes the inner reader's data through unchanged
+/// and computes its `GitOid` as a side effect, so a pipeline that consumes
+/// the data (e.g. extracting an archive) doesn't have to read it twice.
+///
+/// The git object prefix includes the content length, which is why the
+/// length has to be given when the reader is created rather than at the end.
+pub struct GitOidTeeReader<R> {
+    reader: R,
+    hasher: GitOidHasher,
+}
+
+impl<R: Read> GitOidTeeReader<R> {
+    /// Wrap `reader`, which is expected to produce `expected_length` bytes
+    pub fn new(reader: R, hash_algo: HashAlgorithm, expected_length: usize) -> Self {
+        GitOidTeeReader {
+            reader,
+            hasher: GitOidHasher::new(hash_algo, expected_length),
+        }
+    }
+
+    /// Get a reference to the wrapped reader
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Finish and return the `GitOid` of everything read. Will return
+    /// an `Err` if the amount read isn't the expected length
+    pub fn into_gitoid(self) -> IOResult<GitOid> {
+        count_error(finalize_content(self.hasher))
+    }
+}
+
+impl<R: Read> Read for GitOidTeeReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> IOResult<usize> {
+        let size = self.reader.read(buf)?;
+        self.hasher.update(&buf[..size]);
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_tee_passes_data_and_hashes() {
+        let file = File::open("test/data/hello_world.txt").unwrap();
+        let mut tee = GitOidTeeReader::new(file, HashAlgorithm::SHA256, 11);
+
+        let mut out = String::new();
+        tee.read_to_string(&mut out).unwrap();
+
+        assert_eq!("hello world", out);
+        assert_eq!(
+            "fee53a18d32820613c0527aa79be5cb30173c823a9b448fa4817767cc84c6f03",
+            tee.into_gitoid().unwrap().hex_hash()
+        );
+    }
+
+    #[test]
+    fn test_tee_partial_read_is_an_error() {
+        let mut tee = GitOidTeeReader::new("hello world".as_bytes(), HashAlgorithm::SHA256, 11);
+
+        let mut buf = [0u8; 5];
+        tee.read_exact(&mut buf).unwrap();
+
+        assert!(tee.into_gitoid().is_err());
+    }
+}
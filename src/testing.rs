@@ -0,0 +1,193 @@
+//! Conformance test vectors for gitoids and GitBOM documents, with
+//! assertion helpers, so other implementations and language bindings can
+//! run the same checks as this crate.
+//!
+//! The expected hashes are the ones git itself computes for the same
+//! objects (`git hash-object`, `git mktree`) in SHA-1 and SHA-256
+//! repositories. The vectors were made for this crate that way; they
+//! aren't the OmniBOR project's official test suite.
+
+use crate::{HashAlgorithm, ObjectType};
+
+/// The content of a git object and its expected gitoid
+#[derive(Clone, Copy, Debug)]
+pub struct GitOidVector {
+    /// A short description of the case
+    pub name: &'static str,
+    pub hash_algorithm: HashAlgorithm,
+    pub object_type: ObjectType,
+    /// The object's content, without the git prefix
+    pub content: &'static [u8],
+    /// The expected hash, in lowercase hex
+    pub hex: &'static str,
+}
+
+/// A set of input blobs and the GitBOM document they produce
+#[derive(Clone, Copy, Debug)]
+pub struct DocumentVector {
+    /// A short description of the case
+    pub name: &'static str,
+    pub hash_algorithm: HashAlgorithm,
+    /// The content of each input blob
+    pub inputs: &'static [&'static [u8]],
+    /// The expected document, byte for byte
+    pub document: &'static str,
+    /// The expected gitoid of the document, in lowercase hex
+    pub hex: &'static str,
+}
+
+pub const GITOID_VECTORS: &[GitOidVector] = &[
+    #[cfg(feature = "sha1")]
+    GitOidVector {
+        name: "empty blob, SHA-1",
+        hash_algorithm: HashAlgorithm::SHA1,
+        object_type: ObjectType::Blob,
+        content: b"",
+        hex: "e69de29bb2d1d6434b8b29ae775ad8c2e48c5391",
+    },
+    GitOidVector {
+        name: "empty blob, SHA-256",
+        hash_algorithm: HashAlgorithm::SHA256,
+        object_type: ObjectType::Blob,
+        content: b"",
+        hex: "473a0f4c3be8a93681a267e3b1e9a7dcda1185436fe141f7749120a303721813",
+    },
+    #[cfg(feature = "sha1")]
+    GitOidVector {
+        name: "hello world blob, SHA-1",
+        hash_algorithm: HashAlgorithm::SHA1,
+        object_type: ObjectType::Blob,
+        content: b"hello world",
+        hex: "95d09f2b10159347eece71399a7e2e907ea3df4f",
+    },
+    GitOidVector {
+        name: "hello world blob, SHA-256",
+        hash_algorithm: HashAlgorithm::SHA256,
+        object_type: ObjectType::Blob,
+        content: b"hello world",
+        hex: "fee53a18d32820613c0527aa79be5cb30173c823a9b448fa4817767cc84c6f03",
+    },
+    // with the 8 byte `blob 50\0` prefix this is 58 bytes, too many to
+    // fit the length in the first block, so the padding takes a second
+    #[cfg(feature = "sha1")]
+    GitOidVector {
+        name: "50 byte blob, SHA-1",
+        hash_algorithm: HashAlgorithm::SHA1,
+        object_type: ObjectType::Blob,
+        content: b"01234567890123456789012345678901234567890123456789",
+        hex: "be74f81b0b3765c2e14a16e446d5744345c4af83",
+    },
+    GitOidVector {
+        name: "50 byte blob, SHA-256",
+        hash_algorithm: HashAlgorithm::SHA256,
+        object_type: ObjectType::Blob,
+        content: b"01234567890123456789012345678901234567890123456789",
+        hex: "96985afd8490731784578b0b507e677f47336a8e4036bf94d6520f0ac6a42d4a",
+    },
+    #[cfg(feature = "sha1")]
+    GitOidVector {
+        name: "empty tree, SHA-1",
+        hash_algorithm: HashAlgorithm::SHA1,
+        object_type: ObjectType::Tree,
+        content: b"",
+        hex: "4b825dc642cb6eb9a060e54bf8d69288fbee4904",
+    },
+    GitOidVector {
+        name: "empty tree, SHA-256",
+        hash_algorithm: HashAlgorithm::SHA256,
+        object_type: ObjectType::Tree,
+        content: b"",
+        hex: "6ef19b41225c5369f1c104d45d8d85efa9b057b53b14b4b9b939dd74decc5321",
+    },
+];
+
+pub const DOCUMENT_VECTORS: &[DocumentVector] = &[
+    DocumentVector {
+        name: "empty document, SHA-256",
+        hash_algorithm: HashAlgorithm::SHA256,
+        inputs: &[],
+        document: "gitoid:blob:sha256\n",
+        hex: "5780f55500bd30463e4d9616559421fc512bd8debcdec4a23662763753f82895",
+    },
+    #[cfg(feature = "sha1")]
+    DocumentVector {
+        name: "hello world document, SHA-1",
+        hash_algorithm: HashAlgorithm::SHA1,
+        inputs: &[b"hello world"],
+        document: "gitoid:blob:sha1\nblob 95d09f2b10159347eece71399a7e2e907ea3df4f\n",
+        hex: "e484e66f90435adb0c8594ba2ab60df24d7ae27d",
+    },
+    DocumentVector {
+        name: "hello world document, SHA-256",
+        hash_algorithm: HashAlgorithm::SHA256,
+        inputs: &[b"hello world", b"hello world"],
+        document: "gitoid:blob:sha256\nblob fee53a18d32820613c0527aa79be5cb30173c823a9b448fa4817767cc84c6f03\n",
+        hex: "64706140d63a2c76cd6c1b678e5f2b1859620ca898777439b043117ba5cd9762",
+    },
+];
+
+/// Check an implementation against `GITOID_VECTORS`. `compute` is given
+/// each vector's algorithm, object type and content and should return the
+/// hex hash. Panics naming the first vector that doesn't match
+pub fn assert_gitoid_vectors<F>(compute: F)
+where
+    F: Fn(HashAlgorithm, ObjectType, &[u8]) -> String,
+{
+    for vector in GITOID_VECTORS {
+        let actual = compute(vector.hash_algorithm, vector.object_type, vector.content);
+        assert_eq!(vector.hex, actual, "gitoid vector {:?}", vector.name);
+    }
+}
+
+/// Check an implementation against `DOCUMENT_VECTORS`. `compute` is given
+/// each vector's algorithm and input blobs and should return the document
+/// bytes and the hex hash of the document. Panics naming the first vector
+/// that doesn't match
+pub fn assert_document_vectors<F>(compute: F)
+where
+    F: Fn(HashAlgorithm, &[&[u8]]) -> (Vec<u8>, String),
+{
+    for vector in DOCUMENT_VECTORS {
+        let (document, hex) = compute(vector.hash_algorithm, vector.inputs);
+        assert_eq!(
+            vector.document.as_bytes(),
+            &document[..],
+            "document vector {:?}",
+            vector.name
+        );
+        assert_eq!(vector.hex, hex, "document vector {:?}", vector.name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GitBom, GitOid};
+
+    #[test]
+    fn test_gitoid_vectors() {
+        assert_gitoid_vectors(|hash_algo, object_type, content| {
+            GitOid::new_object(hash_algo, object_type, content).hex_hash()
+        });
+    }
+
+    #[test]
+    fn test_document_vectors() {
+        assert_document_vectors(|hash_algo, inputs| {
+            let gitbom: GitBom = inputs
+                .iter()
+                .map(|content| GitOid::new(hash_algo, content))
+                .collect();
+            (
+                gitbom.to_document(hash_algo).unwrap(),
+                gitbom.document_gitoid(hash_algo).unwrap().hex_hash(),
+            )
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "gitoid vector \"empty blob")]
+    fn test_mismatch_names_the_vector() {
+        assert_gitoid_vectors(|_, _, _| "0".repeat(40));
+    }
+}
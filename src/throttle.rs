@@ -0,0 +1,214 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Limits how fast files are hashed, in bytes and/or files per second, so
+/// a background job (e.g. a BOM-refresh daemon) doesn't starve interactive
+/// work of disk bandwidth.
+///
+/// A limiter can be shared between concurrent hashing operations, and the
+/// limits apply to all of them together. Pacing is by reservation: each
+/// read is scheduled after the ones before it, so the first read goes at
+/// once and later ones wait as needed to keep the average under the limit.
+///
+/// `ScanOptions::rate_limiter` applies a limiter to directory scans, and
+/// `GitOid::new_from_async_readers_throttled` to batch hashing
+#[derive(Debug)]
+pub struct RateLimiter {
+    bytes_per_sec: Option<u64>,
+    files_per_sec: Option<u64>,
+    schedule: Mutex<Schedule>,
+}
+
+#[derive(Debug)]
+struct Schedule {
+    next_bytes: Instant,
+    next_file: Instant,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RateLimiter {
+    /// Create a limiter with no limits
+    pub fn new() -> Self {
+        let now = Instant::now();
+        RateLimiter {
+            bytes_per_sec: None,
+            files_per_sec: None,
+            schedule: Mutex::new(Schedule {
+                next_bytes: now,
+                next_file: now,
+            }),
+        }
+    }
+
+    /// Limit the number of bytes read per second
+    pub fn bytes_per_sec(mut self, rate: u64) -> Self {
+        self.bytes_per_sec = Some(rate.max(1));
+        self
+    }
+
+    /// Limit the number of files started per second
+    pub fn files_per_sec(mut self, rate: u64) -> Self {
+        self.files_per_sec = Some(rate.max(1));
+        self
+    }
+
+    /// Is there any limit at all?
+    pub fn is_limited(&self) -> bool {
+        self.bytes_per_sec.is_some() || self.files_per_sec.is_some()
+    }
+
+    /// Block the current thread until `bytes` more may be read
+    pub fn wait_bytes(&self, bytes: u64) {
+        std::thread::sleep(self.reserve_bytes(bytes));
+    }
+
+    /// Block the current thread until another file may be started
+    pub fn wait_file(&self) {
+        std::thread::sleep(self.reserve_file());
+    }
+
+    /// Wait without blocking the executor until `bytes` more may be read
+    pub async fn wait_bytes_async(&self, bytes: u64) {
+        let delay = self.reserve_bytes(bytes);
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Wait without blocking the executor until another file may be started
+    pub async fn wait_file_async(&self) {
+        let delay = self.reserve_file();
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Reserve a slot for `bytes` and return how long to wait for it
+    pub(crate) fn reserve_bytes(&self, bytes: u64) -> Duration {
+        match self.bytes_per_sec {
+            Some(rate) => {
+                let mut schedule = self.schedule.lock().unwrap();
+                reserve(&mut schedule.next_bytes, bytes, rate)
+            }
+            None => Duration::ZERO,
+        }
+    }
+
+    /// Reserve a slot for a file and return how long to wait for it
+    pub(crate) fn reserve_file(&self) -> Duration {
+        match self.files_per_sec {
+            Some(rate) => {
+                let mut schedule = self.schedule.lock().unwrap();
+                reserve(&mut schedule.next_file, 1, rate)
+            }
+            None => Duration::ZERO,
+        }
+    }
+}
+
+/// The longest a single reservation is scheduled for. Reservations too
+/// long to represent as a `Duration` or an `Instant` saturate to this
+const MAX_RESERVATION: Duration = Duration::from_secs(100 * 365 * 24 * 60 * 60);
+
+/// schedule `amount` at `rate` per second after whatever is already
+/// scheduled, returning the wait until this reservation's turn
+fn reserve(next: &mut Instant, amount: u64, rate: u64) -> Duration {
+    let now = Instant::now();
+    let start = (*next).max(now);
+    let period = Duration::try_from_secs_f64(amount as f64 / rate as f64)
+        .map_or(MAX_RESERVATION, |period| period.min(MAX_RESERVATION));
+    *next = start.checked_add(period).unwrap_or(start);
+    start - now
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DirectoryScan, GitOid, HashAlgorithm, ScanOptions, Source};
+
+    #[test]
+    fn test_unlimited_never_waits() {
+        let limiter = RateLimiter::new();
+
+        assert!(!limiter.is_limited());
+        assert_eq!(Duration::ZERO, limiter.reserve_bytes(u64::MAX));
+        assert_eq!(Duration::ZERO, limiter.reserve_file());
+    }
+
+    #[test]
+    fn test_reservations_are_paced() {
+        let limiter = RateLimiter::new().bytes_per_sec(1000);
+
+        // the first read goes straight away, the next one waits for it
+        assert_eq!(Duration::ZERO, limiter.reserve_bytes(500));
+        let wait = limiter.reserve_bytes(500);
+        assert!(wait > Duration::from_millis(400));
+        assert!(wait <= Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_file_limit_is_separate() {
+        let limiter = RateLimiter::new().bytes_per_sec(1).files_per_sec(1000);
+        limiter.reserve_bytes(1_000_000);
+
+        // lots of bytes are scheduled but files aren't held up by them
+        assert!(limiter.reserve_file() < Duration::from_millis(1));
+    }
+
+    #[test]
+    fn test_huge_reservations_saturate() {
+        let limiter = RateLimiter::new().bytes_per_sec(1);
+
+        assert_eq!(Duration::ZERO, limiter.reserve_bytes(u64::MAX));
+        let wait = limiter.reserve_bytes(u64::MAX);
+        assert!(wait > Duration::from_secs(365 * 24 * 60 * 60));
+        assert!(wait <= MAX_RESERVATION);
+    }
+
+    #[test]
+    fn test_throttled_scan() {
+        let options = ScanOptions::new().rate_limiter(RateLimiter::new().files_per_sec(20));
+        let start = Instant::now();
+        let scan = DirectoryScan::new("test/data/tree", HashAlgorithm::SHA256, &options).unwrap();
+
+        // three files at 20 per second is at least 100ms after the first
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert_eq!(3, scan.files().len());
+
+        let options = ScanOptions::new().rate_limiter(RateLimiter::new().bytes_per_sec(100));
+        let start = Instant::now();
+        DirectoryScan::new("test/data/tree", HashAlgorithm::SHA256, &options).unwrap();
+        // 8 bytes at 100 per second, and the last read goes at once
+        assert!(start.elapsed() >= Duration::from_millis(40));
+    }
+
+    #[tokio::test]
+    async fn test_throttled_async_readers() {
+        let mut to_read = Vec::new();
+        for _ in 0..5 {
+            to_read.push(Source::new(
+                tokio::fs::File::open("test/data/hello_world.txt")
+                    .await
+                    .unwrap(),
+                11,
+            ));
+        }
+
+        let limiter = RateLimiter::new().files_per_sec(100);
+        let start = Instant::now();
+        let res =
+            GitOid::new_from_async_readers_throttled(HashAlgorithm::SHA256, to_read, &limiter)
+                .await
+                .unwrap();
+
+        // five files at 100 per second is at least 40ms after the first
+        assert!(start.elapsed() >= Duration::from_millis(40));
+        assert_eq!(5, res.len());
+        assert_eq!(GitOid::new(HashAlgorithm::SHA256, b"hello world"), res[4]);
+    }
+}
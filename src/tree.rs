@@ -0,0 +1,189 @@
+use crate::context::in_file;
+use crate::metrics::{count_error, metrics};
+use crate::{GitOid, HashAlgorithm, ObjectType};
+use std::fs::{self, File, Metadata};
+use std::io::{BufReader, Result as IOResult};
+use std::path::Path;
+
+/// The file modes git records in tree entries
+const MODE_FILE: &str = "100644";
+const MODE_EXECUTABLE: &str = "100755";
+const MODE_SYMLINK: &str = "120000";
+const MODE_TREE: &str = "40000";
+
+/// One entry of a git tree object
+struct TreeEntry {
+    mode: &'static str,
+    name: Vec<u8>,
+    gitoid: GitOid,
+}
+
+impl TreeEntry {
+    /// git sorts tree entries by name, comparing directory names as
+    /// if they had a trailing `/`
+    fn sort_key(&self) -> Vec<u8> {
+        let mut key = self.name.clone();
+        if self.mode == MODE_TREE {
+            key.push(b'/');
+        }
+        key
+    }
+}
+
+impl GitOid {
+    /// Build the canonical git tree object for the directory at `path`
+    /// and hash it, giving the same id `git write-tree` would for the
+    /// same content.
+    ///
+    /// Like git, this skips `.git` directories, empty subdirectories and
+    /// anything that isn't a regular file, directory, or symlink
+    pub fn tree_from_dir<P: AsRef<Path>>(hash_algo: HashAlgorithm, path: P) -> IOResult<GitOid> {
+        match count_error(GitOid::tree_entries(hash_algo, path.as_ref()))? {
+            Some(tree) => Ok(tree),
+            None => Ok(GitOid::hash_object(hash_algo, ObjectType::Tree, &[])),
+        }
+    }
+
+    /// hash the directory, returning `None` if it has no entries
+    fn tree_entries(hash_algo: HashAlgorithm, path: &Path) -> IOResult<Option<GitOid>> {
+        let mut entries = Vec::new();
+
+        for dir_entry in fs::read_dir(path).map_err(|error| in_file(error, path))? {
+            let dir_entry = dir_entry?;
+            let name = name_bytes(&dir_entry.file_name())?;
+            if name == b".git" {
+                continue;
+            }
+
+            let child = dir_entry.path();
+            let metadata = fs::symlink_metadata(&child)?;
+            let file_type = metadata.file_type();
+
+            let entry = if file_type.is_dir() {
+                match GitOid::tree_entries(hash_algo, &child)? {
+                    Some(gitoid) => TreeEntry {
+                        mode: MODE_TREE,
+                        name,
+                        gitoid,
+                    },
+                    None => continue,
+                }
+            } else if file_type.is_symlink() {
+                let target = name_bytes(fs::read_link(&child)?.as_os_str())?;
+                TreeEntry {
+                    mode: MODE_SYMLINK,
+                    name,
+                    gitoid: GitOid::new(hash_algo, &target),
+                }
+            } else if file_type.is_file() {
+                let gitoid = File::open(&child)
+                    .and_then(|file| {
+                        metrics().files_hashed(1);
+                        let reader = BufReader::new(file);
+                        GitOid::new_from_reader(hash_algo, reader, metadata.len() as usize)
+                    })
+                    .map_err(|error| in_file(error, &child))?;
+                TreeEntry {
+                    mode: file_mode(&metadata),
+                    name,
+                    gitoid,
+                }
+            } else {
+                continue;
+            };
+            entries.push(entry);
+        }
+
+        if entries.is_empty() {
+            return Ok(None);
+        }
+
+        entries.sort_by_key(TreeEntry::sort_key);
+
+        let mut content = Vec::new();
+        for entry in &entries {
+            content.extend_from_slice(entry.mode.as_bytes());
+            content.push(b' ');
+            content.extend_from_slice(&entry.name);
+            content.push(0);
+            content.extend_from_slice(entry.gitoid.hash_value());
+        }
+
+        Ok(Some(GitOid::hash_object(
+            hash_algo,
+            ObjectType::Tree,
+            &content,
+        )))
+    }
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &Metadata) -> &'static str {
+    use std::os::unix::fs::PermissionsExt;
+
+    // git only looks at the owner's execute bit
+    if metadata.permissions().mode() & 0o100 != 0 {
+        MODE_EXECUTABLE
+    } else {
+        MODE_FILE
+    }
+}
+
+#[cfg(not(unix))]
+fn file_mode(_metadata: &Metadata) -> &'static str {
+    MODE_FILE
+}
+
+#[cfg(unix)]
+fn name_bytes(name: &std::ffi::OsStr) -> IOResult<Vec<u8>> {
+    use std::os::unix::ffi::OsStrExt;
+
+    Ok(name.as_bytes().to_vec())
+}
+
+#[cfg(not(unix))]
+fn name_bytes(name: &std::ffi::OsStr) -> IOResult<Vec<u8>> {
+    use std::io::{Error, ErrorKind};
+
+    match name.to_str() {
+        Some(name) => Ok(name.as_bytes().to_vec()),
+        None => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("{:?} is not valid UTF-8", name),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::ErrorKind;
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_tree_from_dir_sha1() {
+        let tree = GitOid::tree_from_dir(HashAlgorithm::SHA1, "test/data/tree").unwrap();
+
+        // `git write-tree` for the same directory
+        assert_eq!("36d7f712b216416e590da2054f874e2f95d94445", tree.hex_hash());
+        assert_eq!(ObjectType::Tree, tree.object_type());
+    }
+
+    #[test]
+    fn test_tree_from_dir_sha256() {
+        let tree = GitOid::tree_from_dir(HashAlgorithm::SHA256, "test/data/tree").unwrap();
+
+        assert_eq!(
+            "f33ee5af56ef76b3bd752aa40dff9d2cdb43733881cc0143c4e0864030235287",
+            tree.hex_hash()
+        );
+    }
+
+    #[test]
+    fn test_missing_dir() {
+        let err =
+            GitOid::tree_from_dir(HashAlgorithm::SHA256, "test/data/no_such_dir").unwrap_err();
+
+        assert_eq!(ErrorKind::NotFound, err.kind());
+    }
+}
@@ -0,0 +1,240 @@
+use crate::metrics::{finalize_content, metrics};
+use crate::{
+    DirectoryScan, GitBom, GitOid, GitOidHasher, HashAlgorithm, ScanError, ScanEvent, ScanOptions,
+};
+use std::io::{Error, ErrorKind, Read, Result as IOResult};
+use std::path::{Path, PathBuf};
+
+/// A `Read` adapter that hashes the bytes as they pass through and,
+/// on EOF, checks them against an expected `GitOid`.
+///
+/// If the content doesn't match, the `read` that hit EOF returns an
+/// `Err` of kind `InvalidData` rather than `Ok(0)`, so a consumer copying
+/// a download to disk finds out before treating the copy as complete.
+pub struct VerifyingReader<R> {
+    reader: R,
+    expected: GitOid,
+    hasher: Option<GitOidHasher>,
+    verified: bool,
+}
+
+impl<R: Read> VerifyingReader<R> {
+    /// Wrap `reader`, expecting `expected_length` bytes hashing to `expected`
+    pub fn new(reader: R, expected: GitOid, expected_length: usize) -> Self {
+        VerifyingReader {
+            reader,
+            expected,
+            hasher: Some(GitOidHasher::new(
+                expected.hash_algorithm(),
+                expected_length,
+            )),
+            verified: false,
+        }
+    }
+
+    /// Has EOF been reached and the content matched the expected `GitOid`?
+    pub fn is_verified(&self) -> bool {
+        self.verified
+    }
+
+    /// Return the wrapped reader
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn integrity_error(&self, msg: String) -> Error {
+        metrics().errors(1);
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("Integrity check against {} failed: {}", self.expected, msg),
+        )
+    }
+}
+
+impl<R: Read> Read for VerifyingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> IOResult<usize> {
+        let size = self.reader.read(buf)?;
+
+        if size > 0 {
+            match self.hasher.as_mut() {
+                Some(hasher) => hasher.update(&buf[..size]),
+                // data after a failed check or after EOF was verified
+                None => {
+                    return Err(self.integrity_error("data past the end".to_string()));
+                }
+            }
+            return Ok(size);
+        }
+
+        match self.hasher.take() {
+            Some(hasher) => match finalize_content(hasher) {
+                Ok(actual) if actual == self.expected => {
+                    self.verified = true;
+                    Ok(0)
+                }
+                Ok(actual) => Err(self.integrity_error(format!("got {}", actual))),
+                Err(e) => Err(self.integrity_error(e.to_string())),
+            },
+            None if self.verified => Ok(0),
+            None => Err(self.integrity_error("content did not match".to_string())),
+        }
+    }
+}
+
+/// The result of checking a directory against a `GitBom` with
+/// `verify_directory`
+#[derive(Debug)]
+pub struct DirectoryReport {
+    missing: Vec<GitOid>,
+    extra: Vec<(PathBuf, GitOid)>,
+    errors: Vec<ScanError>,
+}
+
+impl DirectoryReport {
+    /// The gitoids in the `GitBom` that no file in the directory has, sorted
+    pub fn missing(&self) -> &[GitOid] {
+        &self.missing
+    }
+
+    /// The files whose gitoid isn't in the `GitBom`, by path relative to
+    /// the directory. A `GitBom` doesn't record paths, so a file whose
+    /// content changed shows up here, with its old gitoid in `missing`
+    pub fn extra(&self) -> &[(PathBuf, GitOid)] {
+        &self.extra
+    }
+
+    /// The files that couldn't be read
+    pub fn errors(&self) -> &[ScanError] {
+        &self.errors
+    }
+
+    /// Did the directory match the `GitBom` exactly?
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.errors.is_empty()
+    }
+}
+
+/// Rehash every file under the directory at `path` and check the results
+/// against `gitbom`, the core of an attestation check. The hash algorithm
+/// is the one the `GitBom`'s oids use.
+///
+/// Will return an `Err` if `path` can't be read or the `GitBom` mixes
+/// hash algorithms; problems with individual files are in the report
+pub fn verify_directory<P: AsRef<Path>>(path: P, gitbom: &GitBom) -> IOResult<DirectoryReport> {
+    verify_directory_with_events(path, gitbom, |_| {})
+}
+
+/// Check the directory at `path` against `gitbom` like
+/// `verify_directory`, passing a `ScanEvent` to `on_event` as each file
+/// is rehashed
+pub fn verify_directory_with_events<P, F>(
+    path: P,
+    gitbom: &GitBom,
+    on_event: F,
+) -> IOResult<DirectoryReport>
+where
+    P: AsRef<Path>,
+    F: FnMut(&ScanEvent),
+{
+    let oids = gitbom.get_sorted_oids();
+    let hash_algo = oids
+        .front()
+        .map(|gitoid| gitoid.hash_algorithm())
+        .unwrap_or(HashAlgorithm::SHA256);
+    if oids
+        .iter()
+        .any(|gitoid| gitoid.hash_algorithm() != hash_algo)
+    {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Can't verify a GitBom that mixes hash algorithms",
+        ));
+    }
+
+    let scan = DirectoryScan::new_with_events(path, hash_algo, &ScanOptions::new(), on_event)?;
+    let found = scan.gitbom();
+    let extra = scan
+        .files()
+        .iter()
+        .filter(|(_, gitoid)| !gitbom.contains(gitoid))
+        .cloned()
+        .collect();
+    let report = DirectoryReport {
+        missing: oids
+            .into_iter()
+            .filter(|gitoid| !found.contains(gitoid))
+            .collect(),
+        extra,
+        errors: scan.into_errors(),
+    };
+    if !report.is_ok() {
+        metrics().errors(1);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HashAlgorithm;
+    use std::fs::File;
+
+    #[test]
+    fn test_verifies_matching_content() {
+        let expected = GitOid::new(HashAlgorithm::SHA256, b"hello world");
+        let file = File::open("test/data/hello_world.txt").unwrap();
+        let mut reader = VerifyingReader::new(file, expected, 11);
+
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+
+        assert_eq!(b"hello world".to_vec(), out);
+        assert!(reader.is_verified());
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_rejects_mismatched_content() {
+        let expected = GitOid::new(HashAlgorithm::SHA1, b"goodbye world");
+        let mut reader = VerifyingReader::new("hello world".as_bytes(), expected, 11);
+
+        let mut out = Vec::new();
+        let err = reader.read_to_end(&mut out).unwrap_err();
+
+        assert_eq!(ErrorKind::InvalidData, err.kind());
+        assert!(!reader.is_verified());
+
+        // the error sticks on later reads
+        assert!(reader.read(&mut [0u8; 16]).is_err());
+    }
+
+    #[test]
+    fn test_rejects_truncated_content() {
+        let expected = GitOid::new(HashAlgorithm::SHA256, b"hello world");
+        let mut reader = VerifyingReader::new("hello".as_bytes(), expected, 11);
+
+        let mut out = Vec::new();
+        let err = reader.read_to_end(&mut out).unwrap_err();
+
+        assert_eq!(ErrorKind::InvalidData, err.kind());
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_verify_directory() {
+        let sha1 = |content: &str| GitOid::new(HashAlgorithm::SHA1, content.as_bytes());
+        let gitbom: GitBom = vec![sha1("a\n"), sha1("b\n"), sha1("sub\n")]
+            .into_iter()
+            .collect();
+
+        assert!(verify_directory("test/data/tree", &gitbom).unwrap().is_ok());
+
+        let stale = gitbom
+            .difference(&GitBom::new().add(sha1("b\n")))
+            .add(sha1("old b\n"));
+        let report = verify_directory("test/data/tree", &stale).unwrap();
+        assert!(!report.is_ok());
+        assert_eq!(&[sha1("old b\n")], report.missing());
+        assert_eq!(&[(PathBuf::from("sub/b.txt"), sha1("b\n"))], report.extra());
+    }
+}
@@ -0,0 +1,193 @@
+use crate::advisory::match_advisories;
+use crate::json::json_string;
+use crate::{Advisory, GitBom, GitOid};
+use std::fmt::{Display, Formatter, Result};
+
+/// The status of a product with respect to a vulnerability, per
+/// [OpenVEX](https://github.com/openvex/spec)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VexStatus {
+    /// The product contains the vulnerable artifact
+    Affected,
+    /// The product isn't affected, for the given reason
+    NotAffected(VexJustification),
+    /// The product contains a fix for the vulnerability
+    Fixed,
+    /// It's not yet known whether the product is affected
+    UnderInvestigation,
+}
+
+impl Display for VexStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            VexStatus::Affected => write!(f, "affected"),
+            VexStatus::NotAffected(_) => write!(f, "not_affected"),
+            VexStatus::Fixed => write!(f, "fixed"),
+            VexStatus::UnderInvestigation => write!(f, "under_investigation"),
+        }
+    }
+}
+
+/// Why a product isn't affected. OpenVEX requires one of these with
+/// every `not_affected` statement
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VexJustification {
+    /// The vulnerable artifact isn't in the product
+    ComponentNotPresent,
+    /// The artifact is present but not the vulnerable code in it
+    VulnerableCodeNotPresent,
+    /// The vulnerable code can't be executed
+    VulnerableCodeNotInExecutePath,
+    /// The vulnerable code can't be reached with attacker-controlled input
+    VulnerableCodeCannotBeControlledByAdversary,
+    /// The product has mitigations that stop the vulnerability being exploited
+    InlineMitigationsAlreadyExist,
+}
+
+impl Display for VexJustification {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            VexJustification::ComponentNotPresent => write!(f, "component_not_present"),
+            VexJustification::VulnerableCodeNotPresent => write!(f, "vulnerable_code_not_present"),
+            VexJustification::VulnerableCodeNotInExecutePath => {
+                write!(f, "vulnerable_code_not_in_execute_path")
+            }
+            VexJustification::VulnerableCodeCannotBeControlledByAdversary => {
+                write!(f, "vulnerable_code_cannot_be_controlled_by_adversary")
+            }
+            VexJustification::InlineMitigationsAlreadyExist => {
+                write!(f, "inline_mitigations_already_exist")
+            }
+        }
+    }
+}
+
+/// One vulnerability's status for one product
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct VexStatement {
+    vulnerability: String,
+    product: GitOid,
+    status: VexStatus,
+}
+
+/// Builds an [OpenVEX](https://github.com/openvex/spec) document of
+/// per-product vulnerability statuses, with products identified by
+/// gitoid URI, e.g. from the results of `match_advisories`
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct VexDocument {
+    id: String,
+    author: String,
+    timestamp: String,
+    statements: Vec<VexStatement>,
+}
+
+impl VexDocument {
+    /// Start a document. `timestamp` is an RFC 3339 time, e.g.
+    /// `2023-01-08T18:02:03Z`
+    pub fn new<S: ToString>(id: S, author: S, timestamp: S) -> Self {
+        VexDocument {
+            id: id.to_string(),
+            author: author.to_string(),
+            timestamp: timestamp.to_string(),
+            statements: Vec::new(),
+        }
+    }
+
+    /// Add a statement of `product`'s status for `vulnerability`
+    pub fn statement<S: ToString>(
+        mut self,
+        vulnerability: S,
+        product: GitOid,
+        status: VexStatus,
+    ) -> Self {
+        self.statements.push(VexStatement {
+            vulnerability: vulnerability.to_string(),
+            product,
+            status,
+        });
+        self
+    }
+
+    /// Add a statement for every advisory and root: `affected` if the
+    /// root's BOM contains one of the advisory's artifacts and
+    /// `not_affected` because the component isn't present otherwise
+    pub fn matches(mut self, advisories: &[Advisory], roots: &[(GitOid, GitBom)]) -> Self {
+        let matches = match_advisories(advisories, roots);
+        for advisory in advisories {
+            for (root, _) in roots {
+                let affected = matches
+                    .iter()
+                    .any(|m| m.advisory() == advisory.id() && m.root() == *root);
+                let status = if affected {
+                    VexStatus::Affected
+                } else {
+                    VexStatus::NotAffected(VexJustification::ComponentNotPresent)
+                };
+                self = self.statement(advisory.id(), *root, status);
+            }
+        }
+        self
+    }
+
+    /// Render the document as OpenVEX JSON
+    pub fn to_json(&self) -> String {
+        let statements: Vec<String> = self
+            .statements
+            .iter()
+            .map(|statement| {
+                let justification = match statement.status {
+                    VexStatus::NotAffected(justification) => {
+                        format!(",\n      \"justification\": \"{}\"", justification)
+                    }
+                    _ => String::new(),
+                };
+                format!(
+                    "    {{\n      \"vulnerability\": {{ \"name\": {} }},\n      \"products\": [ {{ \"@id\": {} }} ],\n      \"status\": \"{}\"{}\n    }}",
+                    json_string(&statement.vulnerability),
+                    json_string(&statement.product.uri()),
+                    statement.status,
+                    justification
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\n  \"@context\": \"https://openvex.dev/ns/v0.2.0\",\n  \"@id\": {},\n  \"author\": {},\n  \"timestamp\": {},\n  \"version\": 1,\n  \"statements\": [\n{}\n  ]\n}}\n",
+            json_string(&self.id),
+            json_string(&self.author),
+            json_string(&self.timestamp),
+            statements.join(",\n")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vex_from_matches() {
+        let app = GitOid::new_from_str("app");
+        let tool = GitOid::new_from_str("tool");
+        let openssl = GitOid::new_from_str("openssl.c");
+        let roots = vec![
+            (app, GitBom::new().add(openssl)),
+            (tool, GitBom::new().add(GitOid::new_from_str("tool.c"))),
+        ];
+        let advisories = vec![Advisory::new("CVE-2022-0778", GitBom::new().add(openssl))];
+
+        let json = VexDocument::new("urn:example:vex", "Example \"Ops\"", "2023-01-08T18:02:03Z")
+            .matches(&advisories, &roots)
+            .to_json();
+
+        assert!(json.contains("\"author\": \"Example \\\"Ops\\\"\""));
+        assert!(json.contains(&format!(
+            "\"products\": [ {{ \"@id\": \"{}\" }} ],\n      \"status\": \"affected\"\n",
+            app.uri()
+        )));
+        assert!(json.contains(&format!(
+            "\"products\": [ {{ \"@id\": \"{}\" }} ],\n      \"status\": \"not_affected\",\n      \"justification\": \"component_not_present\"",
+            tool.uri()
+        )));
+    }
+}
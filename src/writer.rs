@@ -0,0 +1,80 @@
+use crate::metrics::{count_error, finalize_content};
+use crate::{GitOid, GitOidHasher, HashAlgorithm};
+use std::io::{Result as IOResult, Write};
+
+/// A `Write` adapter that forwards everything to the inner writer and
+/// computes the `GitOid` of the bytes written, so a tool generating an
+/// artifact gets its id without re-reading the output file.
+///
+/// As with `GitOidTeeReader`, the content length must be known when the
+/// writer is created because the git object prefix includes it.
+pub struct GitOidWriter<W> {
+    writer: W,
+    hasher: GitOidHasher,
+}
+
+impl<W: Write> GitOidWriter<W> {
+    /// Wrap `writer`, expecting `expected_length` bytes to be written
+    pub fn new(writer: W, hash_algo: HashAlgorithm, expected_length: usize) -> Self {
+        GitOidWriter {
+            writer,
+            hasher: GitOidHasher::new(hash_algo, expected_length),
+        }
+    }
+
+    /// Get a reference to the wrapped writer
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Flush the inner writer and return it along with the `GitOid` of
+    /// everything written. Will return an `Err` if flushing fails or the
+    /// amount written isn't the expected length
+    pub fn finalize(mut self) -> IOResult<(W, GitOid)> {
+        self.writer.flush()?;
+        let gitoid = count_error(finalize_content(self.hasher))?;
+        Ok((self.writer, gitoid))
+    }
+}
+
+impl<W: Write> Write for GitOidWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> IOResult<usize> {
+        // only hash what the inner writer actually accepted
+        let size = self.writer.write(buf)?;
+        self.hasher.update(&buf[..size]);
+        Ok(size)
+    }
+
+    fn flush(&mut self) -> IOResult<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_writer_forwards_and_hashes() {
+        let mut writer = GitOidWriter::new(Vec::new(), HashAlgorithm::SHA256, 11);
+        writer.write_all(b"hello ").unwrap();
+        writer.write_all(b"world").unwrap();
+
+        let (out, gitoid) = writer.finalize().unwrap();
+
+        assert_eq!(b"hello world".to_vec(), out);
+        assert_eq!(
+            "fee53a18d32820613c0527aa79be5cb30173c823a9b448fa4817767cc84c6f03",
+            gitoid.hex_hash()
+        );
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_writer_wrong_length() {
+        let mut writer = GitOidWriter::new(Vec::new(), HashAlgorithm::SHA1, 3);
+        writer.write_all(b"hello world").unwrap();
+
+        assert!(writer.finalize().is_err());
+    }
+}